@@ -0,0 +1,685 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::type_expr::TypeExpr;
+use crate::types::BinType;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinValue {
+    None,
+    Bool(bool),
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F32(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Mtx44([f32; 16]),
+    Color([u8; 4]),
+    String(String),
+    Hash(u32),
+    File(u64),
+    List(BinType, Vec<BinValue>),
+    List2(BinType, Vec<BinValue>),
+    Pointer(Option<BinObject>),
+    Embed(BinObject),
+    Link(u32),
+    Option(BinType, Option<Box<BinValue>>),
+    Map(BinType, BinType, Vec<(BinValue, BinValue)>),
+    Flag(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinField {
+    pub hash: u32,
+    pub value: BinValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinObject {
+    pub class: u32,
+    pub fields: Vec<BinField>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinEntry {
+    pub path: u32,
+    pub object: BinObject,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinPatch {
+    pub entry: u32,
+    pub path: String,
+    pub value: BinValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinFile {
+    pub version: u32,
+    /// Present when the file starts with a `PTCH` header.
+    pub patch_header: Option<u64>,
+    pub linked: Vec<String>,
+    pub entries: Vec<BinEntry>,
+    pub patches: Vec<BinPatch>,
+}
+
+impl BinValue {
+    pub fn bin_type(&self) -> BinType {
+        match self {
+            BinValue::None => BinType::None,
+            BinValue::Bool(_) => BinType::Bool,
+            BinValue::I8(_) => BinType::I8,
+            BinValue::U8(_) => BinType::U8,
+            BinValue::I16(_) => BinType::I16,
+            BinValue::U16(_) => BinType::U16,
+            BinValue::I32(_) => BinType::I32,
+            BinValue::U32(_) => BinType::U32,
+            BinValue::I64(_) => BinType::I64,
+            BinValue::U64(_) => BinType::U64,
+            BinValue::F32(_) => BinType::F32,
+            BinValue::Vec2(_) => BinType::Vec2,
+            BinValue::Vec3(_) => BinType::Vec3,
+            BinValue::Vec4(_) => BinType::Vec4,
+            BinValue::Mtx44(_) => BinType::Mtx44,
+            BinValue::Color(_) => BinType::Color,
+            BinValue::String(_) => BinType::String,
+            BinValue::Hash(_) => BinType::Hash,
+            BinValue::File(_) => BinType::File,
+            BinValue::List(..) => BinType::List,
+            BinValue::List2(..) => BinType::List2,
+            BinValue::Pointer(_) => BinType::Pointer,
+            BinValue::Embed(_) => BinType::Embed,
+            BinValue::Link(_) => BinType::Link,
+            BinValue::Option(..) => BinType::Option,
+            BinValue::Map(..) => BinType::Map,
+            BinValue::Flag(_) => BinType::Flag,
+        }
+    }
+
+    /// Type of the value as written in ritobin text, without classes.
+    pub fn type_expr(&self) -> TypeExpr {
+        match self {
+            BinValue::List(item, _) | BinValue::List2(item, _) | BinValue::Option(item, _) => {
+                TypeExpr::new(self.bin_type(), None, Some(*item), None)
+            }
+            BinValue::Map(key, value, _) => {
+                TypeExpr::new(BinType::Map, Some(*key), Some(*value), None)
+            }
+            _ => TypeExpr::nestable(self.bin_type(), None),
+        }
+    }
+}
+
+impl BinObject {
+    pub fn field(&self, hash: u32) -> Option<&BinValue> {
+        self.fields
+            .iter()
+            .find(|f| f.hash == hash)
+            .map(|f| &f.value)
+    }
+
+    pub fn field_mut(&mut self, hash: u32) -> Option<&mut BinValue> {
+        self.fields
+            .iter_mut()
+            .find(|f| f.hash == hash)
+            .map(|f| &mut f.value)
+    }
+}
+
+impl BinFile {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        read_bin(&fs::read(path)?)
+    }
+
+    pub fn entry(&self, path: u32) -> Option<&BinEntry> {
+        self.entries.iter().find(|e| e.path == path)
+    }
+}
+
+fn invalid(offset: usize, message: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} at {:#x}", message, offset),
+    )
+}
+
+/// Nesting of pointers, embeds and containers past this is rejected instead
+/// of overflowing the stack.
+const MAX_DEPTH: usize = 64;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        match self.data.get(self.pos..self.pos + count) {
+            Some(result) => {
+                self.pos += count;
+                Ok(result)
+            }
+            None => Err(invalid(self.pos, "Unexpected end of file")),
+        }
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    fn floats<const N: usize>(&mut self) -> io::Result<[f32; N]> {
+        let mut result = [0.0; N];
+        for item in result.iter_mut() {
+            *item = self.f32()?;
+        }
+        Ok(result)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let size = self.u16()? as usize;
+        let offset = self.pos;
+        let bytes = self.bytes(size)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid(offset, "Invalid utf-8 string"))
+    }
+
+    fn bin_type(&mut self) -> io::Result<BinType> {
+        let offset = self.pos;
+        let value = self.u8()?;
+        BinType::from_u8(value).ok_or_else(|| invalid(offset, format!("Unknown type {:#x}", value)))
+    }
+
+    fn nestable_type(&mut self) -> io::Result<BinType> {
+        let offset = self.pos;
+        let value = self.bin_type()?;
+        if !value.is_nestable() {
+            return Err(invalid(offset, format!("{:?} is not nestable", value)));
+        }
+        Ok(value)
+    }
+
+    /// Reads a `u32` size prefix and checks that exactly that many bytes get consumed.
+    fn sized<T>(&mut self, read: impl FnOnce(&mut Self) -> io::Result<T>) -> io::Result<T> {
+        let size = self.u32()? as usize;
+        let start = self.pos;
+        let result = read(self)?;
+        if self.pos - start != size {
+            return Err(invalid(
+                start,
+                format!("Size mismatch, expected {} got {}", size, self.pos - start),
+            ));
+        }
+        Ok(result)
+    }
+
+    /// Runs `read` one nesting level deeper.
+    fn nested<T>(&mut self, read: impl FnOnce(&mut Self) -> io::Result<T>) -> io::Result<T> {
+        if self.depth == MAX_DEPTH {
+            return Err(invalid(self.pos, "Values nested too deep"));
+        }
+        self.depth += 1;
+        let result = read(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn fields(&mut self) -> io::Result<Vec<BinField>> {
+        let count = self.u16()?;
+        let mut results = Vec::new();
+        for _ in 0..count {
+            let hash = self.u32()?;
+            let value_type = self.bin_type()?;
+            let value = self.value(value_type)?;
+            results.push(BinField { hash, value });
+        }
+        Ok(results)
+    }
+
+    fn object(&mut self) -> io::Result<Option<BinObject>> {
+        let class = self.u32()?;
+        if class == 0 {
+            return Ok(None);
+        }
+        let fields = self.nested(|r| r.sized(|r| r.fields()))?;
+        Ok(Some(BinObject { class, fields }))
+    }
+
+    fn value(&mut self, value_type: BinType) -> io::Result<BinValue> {
+        Ok(match value_type {
+            BinType::None => BinValue::None,
+            BinType::Bool => BinValue::Bool(self.u8()? != 0),
+            BinType::I8 => BinValue::I8(self.u8()? as i8),
+            BinType::U8 => BinValue::U8(self.u8()?),
+            BinType::I16 => BinValue::I16(self.u16()? as i16),
+            BinType::U16 => BinValue::U16(self.u16()?),
+            BinType::I32 => BinValue::I32(self.u32()? as i32),
+            BinType::U32 => BinValue::U32(self.u32()?),
+            BinType::I64 => BinValue::I64(self.u64()? as i64),
+            BinType::U64 => BinValue::U64(self.u64()?),
+            BinType::F32 => BinValue::F32(self.f32()?),
+            BinType::Vec2 => BinValue::Vec2(self.floats()?),
+            BinType::Vec3 => BinValue::Vec3(self.floats()?),
+            BinType::Vec4 => BinValue::Vec4(self.floats()?),
+            BinType::Mtx44 => BinValue::Mtx44(self.floats()?),
+            BinType::Color => BinValue::Color(self.array()?),
+            BinType::String => BinValue::String(self.string()?),
+            BinType::Hash => BinValue::Hash(self.u32()?),
+            BinType::File => BinValue::File(self.u64()?),
+            BinType::List | BinType::List2 => {
+                let item_type = self.nestable_type()?;
+                let items = self.nested(|r| {
+                    r.sized(|r| {
+                        let count = r.u32()?;
+                        (0..count).map(|_| r.value(item_type)).collect()
+                    })
+                })?;
+                match value_type {
+                    BinType::List => BinValue::List(item_type, items),
+                    _ => BinValue::List2(item_type, items),
+                }
+            }
+            BinType::Pointer => BinValue::Pointer(self.object()?),
+            BinType::Embed => {
+                let offset = self.pos;
+                BinValue::Embed(
+                    self.object()?
+                        .ok_or_else(|| invalid(offset, "Embed without class"))?,
+                )
+            }
+            BinType::Link => BinValue::Link(self.u32()?),
+            BinType::Option => {
+                let item_type = self.nestable_type()?;
+                let offset = self.pos;
+                let item = match self.u8()? {
+                    0 => None,
+                    1 => Some(Box::new(self.nested(|r| r.value(item_type))?)),
+                    count => return Err(invalid(offset, format!("Option count {}", count))),
+                };
+                BinValue::Option(item_type, item)
+            }
+            BinType::Map => {
+                let key_type = self.nestable_type()?;
+                let value_type = self.nestable_type()?;
+                let items = self.nested(|r| {
+                    r.sized(|r| {
+                        let count = r.u32()?;
+                        (0..count)
+                            .map(|_| Ok((r.value(key_type)?, r.value(value_type)?)))
+                            .collect()
+                    })
+                })?;
+                BinValue::Map(key_type, value_type, items)
+            }
+            BinType::Flag => BinValue::Flag(self.u8()? != 0),
+        })
+    }
+}
+
+/// Parses a binary property bin, optionally prefixed by a `PTCH` header.
+pub fn read_bin(data: &[u8]) -> io::Result<BinFile> {
+    let mut reader = Reader {
+        data,
+        pos: 0,
+        depth: 0,
+    };
+    let mut magic = reader.array::<4>()?;
+    let mut patch_header = None;
+    if &magic == b"PTCH" {
+        patch_header = Some(reader.u64()?);
+        magic = reader.array()?;
+    }
+    if &magic != b"PROP" {
+        return Err(invalid(reader.pos - 4, "Not a PROP file"));
+    }
+
+    let version = reader.u32()?;
+    let mut linked = Vec::new();
+    if version >= 2 {
+        for _ in 0..reader.u32()? {
+            linked.push(reader.string()?);
+        }
+    }
+
+    // Counts are not trusted for preallocation, a corrupt one would abort.
+    let count = reader.u32()?;
+    let mut classes = Vec::new();
+    for _ in 0..count {
+        classes.push(reader.u32()?);
+    }
+    let mut entries = Vec::with_capacity(classes.len());
+    for class in classes {
+        entries.push(reader.sized(|r| {
+            let path = r.u32()?;
+            let fields = r.fields()?;
+            Ok(BinEntry {
+                path,
+                object: BinObject { class, fields },
+            })
+        })?);
+    }
+
+    let mut patches = Vec::new();
+    if patch_header.is_some() && version >= 3 {
+        for _ in 0..reader.u32()? {
+            let entry = reader.u32()?;
+            patches.push(reader.sized(|r| {
+                let value_type = r.bin_type()?;
+                let path = r.string()?;
+                let value = r.value(value_type)?;
+                Ok(BinPatch { entry, path, value })
+            })?);
+        }
+    }
+
+    if reader.pos != data.len() {
+        return Err(invalid(reader.pos, "Trailing data"));
+    }
+    Ok(BinFile {
+        version,
+        patch_header,
+        linked,
+        entries,
+        patches,
+    })
+}
+
+struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn floats(&mut self, values: &[f32]) {
+        for value in values {
+            self.data.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn string(&mut self, value: &str) -> io::Result<()> {
+        let size = u16::try_from(value.len())
+            .map_err(|_| unencodable(format!("String of {} bytes", value.len())))?;
+        self.u16(size);
+        self.data.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+
+    fn bin_type(&mut self, value: BinType) {
+        self.u8(value as u8);
+    }
+
+    /// Writes a `u32` size prefix covering everything `write` appends.
+    fn sized(&mut self, write: impl FnOnce(&mut Self) -> io::Result<()>) -> io::Result<()> {
+        let start = self.data.len();
+        self.u32(0);
+        write(self)?;
+        let size = (self.data.len() - start - 4) as u32;
+        self.data[start..start + 4].copy_from_slice(&size.to_le_bytes());
+        Ok(())
+    }
+
+    fn fields(&mut self, fields: &[BinField]) -> io::Result<()> {
+        let count = u16::try_from(fields.len())
+            .map_err(|_| unencodable(format!("Object with {} fields", fields.len())))?;
+        self.u16(count);
+        for field in fields {
+            self.u32(field.hash);
+            self.bin_type(field.value.bin_type());
+            self.value(&field.value)?;
+        }
+        Ok(())
+    }
+
+    fn object(&mut self, object: Option<&BinObject>) -> io::Result<()> {
+        match object {
+            Some(object) => {
+                self.u32(object.class);
+                self.sized(|w| w.fields(&object.fields))
+            }
+            None => {
+                self.u32(0);
+                Ok(())
+            }
+        }
+    }
+
+    fn value(&mut self, value: &BinValue) -> io::Result<()> {
+        match value {
+            BinValue::None => {}
+            BinValue::Bool(x) | BinValue::Flag(x) => self.u8(*x as u8),
+            BinValue::I8(x) => self.u8(*x as u8),
+            BinValue::U8(x) => self.u8(*x),
+            BinValue::I16(x) => self.u16(*x as u16),
+            BinValue::U16(x) => self.u16(*x),
+            BinValue::I32(x) => self.u32(*x as u32),
+            BinValue::U32(x) | BinValue::Hash(x) | BinValue::Link(x) => self.u32(*x),
+            BinValue::I64(x) => self.u64(*x as u64),
+            BinValue::U64(x) | BinValue::File(x) => self.u64(*x),
+            BinValue::F32(x) => self.floats(&[*x]),
+            BinValue::Vec2(x) => self.floats(x),
+            BinValue::Vec3(x) => self.floats(x),
+            BinValue::Vec4(x) => self.floats(x),
+            BinValue::Mtx44(x) => self.floats(x),
+            BinValue::Color(x) => self.data.extend_from_slice(x),
+            BinValue::String(x) => return self.string(x),
+            BinValue::List(item_type, items) | BinValue::List2(item_type, items) => {
+                self.bin_type(*item_type);
+                return self.sized(|w| {
+                    w.u32(items.len() as u32);
+                    for item in items {
+                        w.value(item)?;
+                    }
+                    Ok(())
+                });
+            }
+            BinValue::Pointer(object) => return self.object(object.as_ref()),
+            BinValue::Embed(object) => return self.object(Some(object)),
+            BinValue::Option(item_type, item) => {
+                self.bin_type(*item_type);
+                match item {
+                    Some(item) => {
+                        self.u8(1);
+                        return self.value(item);
+                    }
+                    None => self.u8(0),
+                }
+            }
+            BinValue::Map(key_type, value_type, items) => {
+                self.bin_type(*key_type);
+                self.bin_type(*value_type);
+                return self.sized(|w| {
+                    w.u32(items.len() as u32);
+                    for (key, value) in items {
+                        w.value(key)?;
+                        w.value(value)?;
+                    }
+                    Ok(())
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unencodable(what: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} does not fit the bin format", what),
+    )
+}
+
+/// Encodes `bin` in the binary property bin format, the inverse of `read_bin`.
+pub fn write_bin(bin: &BinFile) -> io::Result<Vec<u8>> {
+    let mut writer = Writer { data: Vec::new() };
+    if let Some(header) = bin.patch_header {
+        writer.data.extend_from_slice(b"PTCH");
+        writer.u64(header);
+    }
+    writer.data.extend_from_slice(b"PROP");
+    writer.u32(bin.version);
+    if bin.version >= 2 {
+        writer.u32(bin.linked.len() as u32);
+        for linked in &bin.linked {
+            writer.string(linked)?;
+        }
+    }
+
+    writer.u32(bin.entries.len() as u32);
+    for entry in &bin.entries {
+        writer.u32(entry.object.class);
+    }
+    for entry in &bin.entries {
+        writer.sized(|w| {
+            w.u32(entry.path);
+            w.fields(&entry.object.fields)
+        })?;
+    }
+
+    if bin.patch_header.is_some() && bin.version >= 3 {
+        writer.u32(bin.patches.len() as u32);
+        for patch in &bin.patches {
+            writer.u32(patch.entry);
+            writer.sized(|w| {
+                w.bin_type(patch.value.bin_type());
+                w.string(&patch.path)?;
+                w.value(&patch.value)
+            })?;
+        }
+    }
+    Ok(writer.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> BinFile {
+        let fields = vec![
+            BinField {
+                hash: 0x10,
+                value: BinValue::String("Characters/Annie".into()),
+            },
+            BinField {
+                hash: 0x11,
+                value: BinValue::List(BinType::U32, vec![BinValue::U32(1), BinValue::U32(2)]),
+            },
+        ];
+        BinFile {
+            version: 3,
+            patch_header: None,
+            linked: vec!["Shared.bin".into()],
+            entries: vec![BinEntry {
+                path: 0x20,
+                object: BinObject {
+                    class: 0x30,
+                    fields,
+                },
+            }],
+            patches: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn corrupt_entry_count_is_an_error() {
+        let mut data = b"PROP".to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read_bin(&data).is_err());
+    }
+
+    #[test]
+    fn corrupt_field_count_is_an_error() {
+        let mut data = b"PROP".to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&0x30u32.to_le_bytes());
+        data.extend_from_slice(&6u32.to_le_bytes());
+        data.extend_from_slice(&0x20u32.to_le_bytes());
+        data.extend_from_slice(&u16::MAX.to_le_bytes());
+        assert!(read_bin(&data).is_err());
+    }
+
+    /// Entry whose only field is `depth` pointers nested in each other.
+    fn nested_pointers(depth: usize) -> BinFile {
+        let mut value = BinValue::Pointer(None);
+        for _ in 0..depth {
+            value = BinValue::Pointer(Some(BinObject {
+                class: 0x30,
+                fields: vec![BinField { hash: 0x10, value }],
+            }));
+        }
+        let mut bin = sample();
+        bin.entries[0].object.fields = vec![BinField { hash: 0x10, value }];
+        bin
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let data = write_bin(&nested_pointers(MAX_DEPTH)).unwrap();
+        assert_eq!(read_bin(&data).unwrap(), nested_pointers(MAX_DEPTH));
+        let data = write_bin(&nested_pointers(MAX_DEPTH + 1)).unwrap();
+        let error = read_bin(&data).unwrap_err();
+        assert!(error.to_string().contains("nested too deep"), "{}", error);
+    }
+
+    #[test]
+    fn unencodable_values_are_an_error() {
+        let mut bin = sample();
+        bin.entries[0].object.fields[0].value = BinValue::String("x".repeat(0x10000));
+        assert!(write_bin(&bin).is_err());
+        let field = bin.entries[0].object.fields[1].clone();
+        bin.entries[0].object.fields = vec![field; 0x10000];
+        assert!(write_bin(&bin).is_err());
+    }
+
+    #[test]
+    fn truncated_file_is_an_error() {
+        let data = write_bin(&sample()).unwrap();
+        assert_eq!(read_bin(&data).unwrap(), sample());
+        for length in 0..data.len() {
+            assert!(read_bin(&data[..length]).is_err(), "{} bytes", length);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::BTreeSet;
+
+use crate::bin::{BinFile, BinObject, BinValue};
+use crate::bin_path;
+use crate::codegen::json_key;
+use crate::hashes::HashNames;
+use crate::schema::{MetaFile, PropertyInfo};
+use crate::types::BinType;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    UnknownClass(u32),
+    UnknownProperty { class: u32, property: u32 },
+    DuplicateProperty(u32),
+    TypeMismatch { expected: BinType, found: BinType },
+    ElementTypeMismatch { expected: BinType, found: BinType },
+    KeyTypeMismatch { expected: BinType, found: BinType },
+    WrongClass { expected: u32, found: u32 },
+    WrongCount { expected: usize, found: usize },
+    UnknownEntry(u32),
+    InvalidPath(String),
+    IndexOutOfRange { index: usize, count: usize },
+    NullPointer,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Where the problem was found, see `bin_path`.
+    pub path: String,
+    pub problem: Problem,
+}
+
+impl Diagnostic {
+    pub fn display(&self, names: &HashNames) -> String {
+        let problem = match &self.problem {
+            Problem::UnknownClass(class) => format!("unknown class {}", json_key(names, *class)),
+            Problem::UnknownProperty { class, property } => format!(
+                "unknown property {} in {}",
+                json_key(names, *property),
+                json_key(names, *class)
+            ),
+            Problem::DuplicateProperty(property) => {
+                format!("duplicate property {}", json_key(names, *property))
+            }
+            Problem::TypeMismatch { expected, found } => {
+                format!("expected {:?} found {:?}", expected, found)
+            }
+            Problem::ElementTypeMismatch { expected, found } => {
+                format!("expected {:?} elements found {:?}", expected, found)
+            }
+            Problem::KeyTypeMismatch { expected, found } => {
+                format!("expected {:?} keys found {:?}", expected, found)
+            }
+            Problem::WrongClass { expected, found } => format!(
+                "expected {} found {}",
+                json_key(names, *expected),
+                json_key(names, *found)
+            ),
+            Problem::WrongCount { expected, found } => {
+                format!("expected {} elements found {}", expected, found)
+            }
+            Problem::UnknownEntry(entry) => format!("unknown entry {}", json_key(names, *entry)),
+            Problem::InvalidPath(path) => format!("invalid path {:?}", path),
+            Problem::IndexOutOfRange { index, count } => {
+                format!("index {} out of range for {} elements", index, count)
+            }
+            Problem::NullPointer => "null pointer".to_string(),
+        };
+        format!("{}: {}", self.path, problem)
+    }
+}
+
+struct Checker<'a> {
+    meta: &'a MetaFile,
+    names: &'a HashNames,
+    results: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn report(&mut self, path: &str, problem: Problem) {
+        self.results.push(Diagnostic {
+            path: path.to_string(),
+            problem,
+        });
+    }
+
+    fn object(&mut self, object: &BinObject, path: &str) {
+        if self.meta.class(object.class).is_none() {
+            self.report(path, Problem::UnknownClass(object.class));
+            return;
+        }
+        let mut seen = BTreeSet::new();
+        for field in &object.fields {
+            let path = bin_path::field(self.names, path, field.hash);
+            if !seen.insert(field.hash) {
+                self.report(&path, Problem::DuplicateProperty(field.hash));
+            }
+            match self.meta.find_property(object.class, field.hash) {
+                Some((_, _, info)) => self.property(info, &field.value, &path),
+                None => self.report(
+                    &path,
+                    Problem::UnknownProperty {
+                        class: object.class,
+                        property: field.hash,
+                    },
+                ),
+            }
+        }
+    }
+
+    fn class(&mut self, expected: Option<u32>, object: &BinObject, path: &str) {
+        if let Some(expected) = expected.filter(|&c| self.meta.class(c).is_some()) {
+            let known = self.meta.class(object.class).is_some();
+            if known && !self.meta.is_subclass(object.class, expected) {
+                self.report(
+                    path,
+                    Problem::WrongClass {
+                        expected,
+                        found: object.class,
+                    },
+                );
+            }
+        }
+        self.object(object, path);
+    }
+
+    fn nestable(&mut self, other: Option<u32>, value: &BinValue, path: &str) {
+        match value {
+            BinValue::Pointer(Some(object)) | BinValue::Embed(object) => {
+                self.class(other, object, path)
+            }
+            _ => {}
+        }
+    }
+
+    fn element_type(&mut self, expected: BinType, found: BinType, path: &str) -> bool {
+        if expected != found {
+            self.report(path, Problem::ElementTypeMismatch { expected, found });
+        }
+        expected == found
+    }
+
+    fn property(&mut self, info: &PropertyInfo, value: &BinValue, path: &str) {
+        let found = value.bin_type();
+        if found != info.value_type {
+            let expected = info.value_type;
+            self.report(path, Problem::TypeMismatch { expected, found });
+            return;
+        }
+        match (value, &info.container, &info.map) {
+            (BinValue::List(item, items) | BinValue::List2(item, items), Some(container), _) => {
+                if !self.element_type(container.value_type, *item, path) {
+                    return;
+                }
+                if let Some(expected) = container.fixed_size {
+                    if expected != items.len() {
+                        let found = items.len();
+                        self.report(path, Problem::WrongCount { expected, found });
+                    }
+                }
+                for (index, item) in items.iter().enumerate() {
+                    let path = bin_path::item(path, index);
+                    self.nestable(info.other_class, item, &path);
+                }
+            }
+            (BinValue::Option(item, value), Some(container), _) => {
+                if self.element_type(container.value_type, *item, path) {
+                    if let Some(value) = value {
+                        self.nestable(info.other_class, value, path);
+                    }
+                }
+            }
+            (BinValue::Map(key, item, items), _, Some(map)) => {
+                if map.key_type != *key {
+                    let expected = map.key_type;
+                    let found = *key;
+                    self.report(path, Problem::KeyTypeMismatch { expected, found });
+                    return;
+                }
+                if !self.element_type(map.value_type, *item, path) {
+                    return;
+                }
+                for (key, value) in items {
+                    let path = bin_path::map_item(self.names, path, key);
+                    self.nestable(info.other_class, value, &path);
+                }
+            }
+            _ => self.nestable(info.other_class, value, path),
+        }
+    }
+}
+
+/// Checks a single property value, as used for patch overrides.
+pub fn check_property(
+    meta: &MetaFile,
+    names: &HashNames,
+    info: &PropertyInfo,
+    value: &BinValue,
+    path: &str,
+) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        meta,
+        names,
+        results: Vec::new(),
+    };
+    checker.property(info, value, path);
+    checker.results
+}
+
+/// Checks a single container element of `value_type`, `other` being the
+/// class of the owning property.
+pub fn check_element(
+    meta: &MetaFile,
+    names: &HashNames,
+    value_type: BinType,
+    other: Option<u32>,
+    value: &BinValue,
+    path: &str,
+) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        meta,
+        names,
+        results: Vec::new(),
+    };
+    let found = value.bin_type();
+    if found != value_type {
+        let expected = value_type;
+        checker.report(path, Problem::TypeMismatch { expected, found });
+    } else {
+        checker.nestable(other, value, path);
+    }
+    checker.results
+}
+
+/// Checks every entry of `bin` against the classes and properties of `meta`.
+pub fn check_bin(meta: &MetaFile, names: &HashNames, bin: &BinFile) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        meta,
+        names,
+        results: Vec::new(),
+    };
+    for entry in &bin.entries {
+        let path = bin_path::entry(names, entry.path);
+        checker.object(&entry.object, &path);
+    }
+    checker.results
+}
@@ -0,0 +1,541 @@
+use serde_json::Value;
+
+use crate::bin::{BinField, BinFile, BinObject, BinValue};
+use crate::codegen::{default_color, default_floats, default_hash};
+use crate::schema::{MetaFile, PropertyInfo};
+use crate::types::BinType;
+
+/// Value a freshly constructed instance holds when the dump recorded nothing.
+pub fn zero_value(
+    meta: &MetaFile,
+    value_type: BinType,
+    other: Option<u32>,
+    property: Option<&PropertyInfo>,
+) -> BinValue {
+    let container = property.and_then(|p| p.container.as_ref());
+    let map = property.and_then(|p| p.map.as_ref());
+    match value_type {
+        BinType::None => BinValue::None,
+        BinType::Bool => BinValue::Bool(false),
+        BinType::I8 => BinValue::I8(0),
+        BinType::U8 => BinValue::U8(0),
+        BinType::I16 => BinValue::I16(0),
+        BinType::U16 => BinValue::U16(0),
+        BinType::I32 => BinValue::I32(0),
+        BinType::U32 => BinValue::U32(0),
+        BinType::I64 => BinValue::I64(0),
+        BinType::U64 => BinValue::U64(0),
+        BinType::F32 => BinValue::F32(0.0),
+        BinType::Vec2 => BinValue::Vec2([0.0; 2]),
+        BinType::Vec3 => BinValue::Vec3([0.0; 3]),
+        BinType::Vec4 => BinValue::Vec4([0.0; 4]),
+        BinType::Mtx44 => BinValue::Mtx44([
+            1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+        ]),
+        BinType::Color => BinValue::Color([0; 4]),
+        BinType::String => BinValue::String(String::new()),
+        BinType::Hash => BinValue::Hash(0),
+        BinType::File => BinValue::File(0),
+        BinType::List | BinType::List2 => {
+            let item_type = container.map_or(BinType::None, |c| c.value_type);
+            let count = container.and_then(|c| c.fixed_size).unwrap_or(0);
+            let items = (0..count)
+                .map(|_| zero_value(meta, item_type, other, None))
+                .collect();
+            match value_type {
+                BinType::List => BinValue::List(item_type, items),
+                _ => BinValue::List2(item_type, items),
+            }
+        }
+        BinType::Pointer => BinValue::Pointer(None),
+        BinType::Embed => BinValue::Embed(match other {
+            Some(other) => default_object(meta, other),
+            None => BinObject {
+                class: 0,
+                fields: Vec::new(),
+            },
+        }),
+        BinType::Link => BinValue::Link(0),
+        BinType::Option => {
+            BinValue::Option(container.map_or(BinType::None, |c| c.value_type), None)
+        }
+        BinType::Map => BinValue::Map(
+            map.map_or(BinType::None, |m| m.key_type),
+            map.map_or(BinType::None, |m| m.value_type),
+            Vec::new(),
+        ),
+        BinType::Flag => BinValue::Flag(false),
+    }
+}
+
+/// Whether `value` holds a placeholder `zero_value` made up for a class or
+/// container missing from the dump, which `read_bin` would reject: an embed
+/// without class or a container whose item type is not nestable.
+pub fn has_placeholder(value: &BinValue) -> bool {
+    let object = |x: &BinObject| x.fields.iter().any(|f| has_placeholder(&f.value));
+    match value {
+        BinValue::Embed(x) => x.class == 0 || object(x),
+        BinValue::Pointer(Some(x)) => object(x),
+        BinValue::List(item, items) | BinValue::List2(item, items) => {
+            !item.is_nestable() || items.iter().any(has_placeholder)
+        }
+        BinValue::Option(item, value) => {
+            !item.is_nestable() || value.as_deref().is_some_and(has_placeholder)
+        }
+        BinValue::Map(key, value, items) => {
+            !key.is_nestable()
+                || !value.is_nestable()
+                || items
+                    .iter()
+                    .any(|(k, v)| has_placeholder(k) || has_placeholder(v))
+        }
+        _ => false,
+    }
+}
+
+/// Converts a nestable value from the dump's `defaults` JSON.
+pub fn nestable_from_json(
+    meta: &MetaFile,
+    value: &Value,
+    value_type: BinType,
+    other: Option<u32>,
+) -> Option<BinValue> {
+    Some(match value_type {
+        BinType::Bool => BinValue::Bool(value.as_bool()?),
+        BinType::I8 => BinValue::I8(value.as_i64()?.try_into().ok()?),
+        BinType::U8 => BinValue::U8(value.as_u64()?.try_into().ok()?),
+        BinType::I16 => BinValue::I16(value.as_i64()?.try_into().ok()?),
+        BinType::U16 => BinValue::U16(value.as_u64()?.try_into().ok()?),
+        BinType::I32 => BinValue::I32(value.as_i64()?.try_into().ok()?),
+        BinType::U32 => BinValue::U32(value.as_u64()?.try_into().ok()?),
+        BinType::I64 => BinValue::I64(value.as_i64()?),
+        BinType::U64 => BinValue::U64(value.as_u64()?),
+        BinType::F32 => BinValue::F32(value.as_f64()? as f32),
+        BinType::Vec2 => BinValue::Vec2(default_floats(value, 2)?.try_into().ok()?),
+        BinType::Vec3 => BinValue::Vec3(default_floats(value, 3)?.try_into().ok()?),
+        BinType::Vec4 => BinValue::Vec4(default_floats(value, 4)?.try_into().ok()?),
+        BinType::Mtx44 => BinValue::Mtx44(default_floats(value, 16)?.try_into().ok()?),
+        BinType::Color => BinValue::Color(default_color(value)?),
+        BinType::String => BinValue::String(value.as_str()?.to_string()),
+        BinType::Hash => BinValue::Hash(default_hash(value)? as u32),
+        BinType::File => BinValue::File(default_hash(value)?),
+        BinType::Link => BinValue::Link(default_hash(value)? as u32),
+        BinType::Flag => BinValue::Flag(value.as_bool()?),
+        // The dumper does not descend into embeds or pointers, they are always
+        // default constructed or null.
+        BinType::Embed | BinType::Pointer => zero_value(meta, value_type, other, None),
+        _ => return None,
+    })
+}
+
+/// Converts a property value from the dump's `defaults` JSON, `None` when it
+/// does not match the property type.
+pub fn value_from_json(
+    meta: &MetaFile,
+    value: &Value,
+    property: &PropertyInfo,
+) -> Option<BinValue> {
+    let other = property.other_class.filter(|&c| meta.class(c).is_some());
+    match (property.value_type, &property.container, &property.map) {
+        (BinType::List | BinType::List2, Some(container), _) => {
+            let items = value
+                .as_array()?
+                .iter()
+                .map(|x| nestable_from_json(meta, x, container.value_type, other))
+                .collect::<Option<Vec<_>>>()?;
+            Some(match property.value_type {
+                BinType::List => BinValue::List(container.value_type, items),
+                _ => BinValue::List2(container.value_type, items),
+            })
+        }
+        (BinType::Option, Some(container), _) => {
+            let item = match value {
+                Value::Null => None,
+                _ => Some(Box::new(nestable_from_json(
+                    meta,
+                    value,
+                    container.value_type,
+                    other,
+                )?)),
+            };
+            Some(BinValue::Option(container.value_type, item))
+        }
+        // Maps are always dumped empty.
+        (BinType::Map, _, Some(map)) => {
+            value.as_object()?;
+            Some(BinValue::Map(map.key_type, map.value_type, Vec::new()))
+        }
+        _ => nestable_from_json(meta, value, property.value_type, other),
+    }
+}
+
+/// Whether the dump knows the default of `property`. Pointers, directly or as
+/// container items, are dumped as null whatever the constructor sets, so a
+/// null there is no evidence the game's default is null.
+pub fn has_known_default(property: &PropertyInfo) -> bool {
+    property.value_type != BinType::Pointer
+        && !matches!(&property.container, Some(c) if c.value_type == BinType::Pointer)
+}
+
+/// Default value of `property` as seen from an instance of `class`.
+pub fn default_property(
+    meta: &MetaFile,
+    class: u32,
+    property: u32,
+    info: &PropertyInfo,
+) -> BinValue {
+    meta.default_value(class, property)
+        .and_then(|value| value_from_json(meta, value, info))
+        .unwrap_or_else(|| {
+            let other = info.other_class.filter(|&c| meta.class(c).is_some());
+            zero_value(meta, info.value_type, other, Some(info))
+        })
+}
+
+/// Object of `class` with every property, including inherited ones, set to
+/// its default. Properties without a known default are left out.
+pub fn default_object(meta: &MetaFile, class: u32) -> BinObject {
+    let fields = meta
+        .all_properties(class)
+        .into_iter()
+        .filter(|(_, _, info)| has_known_default(info))
+        .map(|(_, hash, info)| BinField {
+            hash,
+            value: default_property(meta, class, hash, info),
+        })
+        .collect();
+    BinObject { class, fields }
+}
+
+/// Largest difference at which floats still count as equal to their default.
+pub const DEFAULT_EPSILON: f32 = 1e-6;
+
+fn floats_match(a: &[f32], b: &[f32], epsilon: f32) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(x, y)| x == y || (x - y).abs() <= epsilon)
+}
+
+/// Same class and fields regardless of field order.
+fn objects_match(a: &BinObject, b: &BinObject, epsilon: f32) -> bool {
+    a.class == b.class
+        && a.fields.len() == b.fields.len()
+        && a.fields.iter().all(|field| {
+            b.field(field.hash)
+                .is_some_and(|other| values_match(&field.value, other, epsilon))
+        })
+}
+
+/// Compares values with F32, vector and matrix components allowed to differ by `epsilon`.
+pub fn values_match(a: &BinValue, b: &BinValue, epsilon: f32) -> bool {
+    match (a, b) {
+        (BinValue::F32(x), BinValue::F32(y)) => floats_match(&[*x], &[*y], epsilon),
+        (BinValue::Vec2(x), BinValue::Vec2(y)) => floats_match(x, y, epsilon),
+        (BinValue::Vec3(x), BinValue::Vec3(y)) => floats_match(x, y, epsilon),
+        (BinValue::Vec4(x), BinValue::Vec4(y)) => floats_match(x, y, epsilon),
+        (BinValue::Mtx44(x), BinValue::Mtx44(y)) => floats_match(x, y, epsilon),
+        (BinValue::List(t, x), BinValue::List(u, y))
+        | (BinValue::List2(t, x), BinValue::List2(u, y)) => {
+            t == u
+                && x.len() == y.len()
+                && x.iter().zip(y).all(|(x, y)| values_match(x, y, epsilon))
+        }
+        (BinValue::Pointer(Some(x)), BinValue::Pointer(Some(y)))
+        | (BinValue::Embed(x), BinValue::Embed(y)) => objects_match(x, y, epsilon),
+        (BinValue::Option(t, x), BinValue::Option(u, y)) => {
+            t == u
+                && match (x, y) {
+                    (Some(x), Some(y)) => values_match(x, y, epsilon),
+                    (x, y) => x.is_none() && y.is_none(),
+                }
+        }
+        (BinValue::Map(k, v, x), BinValue::Map(l, w, y)) => {
+            k == l
+                && v == w
+                && x.len() == y.len()
+                && x.iter().zip(y).all(|((a, b), (c, d))| {
+                    values_match(a, c, epsilon) && values_match(b, d, epsilon)
+                })
+        }
+        _ => a == b,
+    }
+}
+
+/// Calls `f` on every object directly nested in `value`, looking through containers.
+pub fn nested_objects(value: &mut BinValue, f: &mut dyn FnMut(&mut BinObject)) {
+    match value {
+        BinValue::Pointer(Some(object)) | BinValue::Embed(object) => f(object),
+        BinValue::List(_, items) | BinValue::List2(_, items) => {
+            for item in items {
+                nested_objects(item, f);
+            }
+        }
+        BinValue::Option(_, Some(item)) => nested_objects(item, f),
+        BinValue::Map(_, _, items) => {
+            for (_, item) in items {
+                nested_objects(item, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Calls `f` on `value` and every value nested in it, fields of objects included.
+pub fn visit_values(value: &BinValue, f: &mut dyn FnMut(&BinValue)) {
+    f(value);
+    match value {
+        BinValue::Pointer(Some(object)) | BinValue::Embed(object) => {
+            for field in &object.fields {
+                visit_values(&field.value, f);
+            }
+        }
+        BinValue::List(_, items) | BinValue::List2(_, items) => {
+            for item in items {
+                visit_values(item, f);
+            }
+        }
+        BinValue::Option(_, Some(item)) => visit_values(item, f),
+        BinValue::Map(_, _, items) => {
+            for (key, item) in items {
+                visit_values(key, f);
+                visit_values(item, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Removes fields of `object` and nested objects that equal their class default.
+/// Embeds left with only default fields are removed as a whole, fields without
+/// a known default are kept.
+pub fn minimize_object(meta: &MetaFile, object: &mut BinObject, epsilon: f32) {
+    let class = object.class;
+    object.fields.retain_mut(|field| {
+        nested_objects(&mut field.value, &mut |x| minimize_object(meta, x, epsilon));
+        let info = match meta.find_property(class, field.hash) {
+            Some((_, _, info)) if has_known_default(info) => info,
+            _ => return true,
+        };
+        let mut default = default_property(meta, class, field.hash, info);
+        nested_objects(&mut default, &mut |x| minimize_object(meta, x, epsilon));
+        !values_match(&field.value, &default, epsilon)
+    });
+}
+
+/// Adds every missing field of `object` and nested objects with its default,
+/// except fields without a known default. Known fields are put in declaration
+/// order, bases first, unknown ones last.
+pub fn materialize_object(meta: &MetaFile, object: &mut BinObject) {
+    for field in &mut object.fields {
+        nested_objects(&mut field.value, &mut |x| materialize_object(meta, x));
+    }
+    let class = object.class;
+    if meta.class(class).is_none() {
+        return;
+    }
+    let mut fields = Vec::new();
+    for (_, hash, info) in meta.all_properties(class) {
+        let value = match object.fields.iter().position(|f| f.hash == hash) {
+            Some(index) => object.fields.remove(index).value,
+            None if has_known_default(info) => default_property(meta, class, hash, info),
+            None => continue,
+        };
+        fields.push(BinField { hash, value });
+    }
+    fields.append(&mut object.fields);
+    object.fields = fields;
+}
+
+/// Minimizes every entry and the objects inside patch values.
+pub fn minimize_bin(meta: &MetaFile, bin: &mut BinFile, epsilon: f32) {
+    for entry in &mut bin.entries {
+        minimize_object(meta, &mut entry.object, epsilon);
+    }
+    for patch in &mut bin.patches {
+        nested_objects(&mut patch.value, &mut |x| minimize_object(meta, x, epsilon));
+    }
+}
+
+/// Materializes every entry and the objects inside patch values.
+pub fn materialize_bin(meta: &MetaFile, bin: &mut BinFile) {
+    for entry in &mut bin.entries {
+        materialize_object(meta, &mut entry.object);
+    }
+    for patch in &mut bin.patches {
+        nested_objects(&mut patch.value, &mut |x| materialize_object(meta, x));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bin::{read_bin, write_bin, BinEntry};
+    use crate::schema::testing::{class, container, meta, property};
+
+    fn bin(object: BinObject) -> Vec<u8> {
+        write_bin(&BinFile {
+            version: 3,
+            patch_header: None,
+            linked: Vec::new(),
+            entries: vec![BinEntry { path: 0x1, object }],
+            patches: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn placeholders_are_detected() {
+        // 0x99 is referenced but missing from the dump, the list lacks its container.
+        let holder = class(
+            None,
+            16,
+            vec![(0x1, property(8, BinType::Embed, Some(0x99)))],
+        );
+        let mut list = property(8, BinType::List, None);
+        list["container"] = serde_json::Value::Null;
+        let lists = class(None, 16, vec![(0x2, list)]);
+        let valid = class(
+            None,
+            32,
+            vec![
+                (0x3, property(8, BinType::Embed, Some(0x40))),
+                (0x4, container(16, BinType::Option, BinType::F32, None)),
+            ],
+        );
+        let item = class(None, 8, vec![(0x5, property(0, BinType::U32, None))]);
+        let meta = meta(vec![
+            (0x10, holder),
+            (0x20, lists),
+            (0x30, valid),
+            (0x40, item),
+        ]);
+        for class in [0x10, 0x20] {
+            let object = default_object(&meta, class);
+            assert!(object.fields.iter().any(|x| has_placeholder(&x.value)));
+            assert!(read_bin(&bin(object)).is_err());
+        }
+        let object = default_object(&meta, 0x30);
+        assert!(!object.fields.iter().any(|x| has_placeholder(&x.value)));
+        assert!(read_bin(&bin(object)).is_ok());
+    }
+
+    #[test]
+    fn pointers_have_no_known_default() {
+        let holder = class(
+            None,
+            32,
+            vec![
+                (0x1, property(0, BinType::Pointer, Some(0x20))),
+                (
+                    0x2,
+                    container(8, BinType::List2, BinType::Pointer, Some(0x20)),
+                ),
+                (0x3, container(24, BinType::Option, BinType::U8, None)),
+            ],
+        );
+        let meta = meta(vec![(0x10, holder), (0x20, class(None, 8, Vec::new()))]);
+        let object = default_object(&meta, 0x10);
+        let hashes = object.fields.iter().map(|x| x.hash).collect::<Vec<_>>();
+        assert_eq!(hashes, vec![0x3]);
+    }
+
+    #[test]
+    fn minimize_and_materialize_leave_pointers_alone() {
+        let holder = class(
+            None,
+            16,
+            vec![
+                (0x1, property(0, BinType::Pointer, Some(0x20))),
+                (0x2, property(8, BinType::U32, None)),
+            ],
+        );
+        let meta = meta(vec![(0x10, holder), (0x20, class(None, 8, Vec::new()))]);
+        let mut object = BinObject {
+            class: 0x10,
+            fields: vec![
+                BinField {
+                    hash: 0x1,
+                    value: BinValue::Pointer(None),
+                },
+                BinField {
+                    hash: 0x2,
+                    value: BinValue::U32(0),
+                },
+            ],
+        };
+        minimize_object(&meta, &mut object, DEFAULT_EPSILON);
+        assert_eq!(object.fields.len(), 1);
+        assert_eq!(object.fields[0].value, BinValue::Pointer(None));
+
+        let mut object = BinObject {
+            class: 0x10,
+            fields: Vec::new(),
+        };
+        materialize_object(&meta, &mut object);
+        assert_eq!(object.fields.len(), 1);
+        assert_eq!(object.fields[0].hash, 0x2);
+    }
+
+    #[test]
+    fn minimize_then_materialize_gives_back_the_same_bytes() {
+        let mut base = class(
+            None,
+            16,
+            vec![
+                (0x1, property(0, BinType::U32, None)),
+                (0x2, property(4, BinType::F32, None)),
+            ],
+        );
+        base["defaults"] = serde_json::json!({"0x1": 7, "0x2": 1.5});
+        let derived = class(
+            Some(0x10),
+            48,
+            vec![
+                (0x3, property(16, BinType::Embed, Some(0x30))),
+                (0x4, container(24, BinType::List, BinType::String, None)),
+            ],
+        );
+        let mut item = class(None, 16, vec![(0x5, property(0, BinType::Vec3, None))]);
+        item["defaults"] = serde_json::json!({"0x5": [1, 2, 3]});
+        let meta = meta(vec![(0x10, base), (0x20, derived), (0x30, item)]);
+
+        let field = |hash, value| BinField { hash, value };
+        let item = |x| {
+            BinValue::Embed(BinObject {
+                class: 0x30,
+                fields: vec![field(0x5, BinValue::Vec3([1.0, 2.0, x]))],
+            })
+        };
+        let full = BinObject {
+            class: 0x20,
+            fields: vec![
+                field(0x1, BinValue::U32(7)),
+                field(0x2, BinValue::F32(2.0)),
+                field(0x3, item(4.0)),
+                field(0x4, BinValue::List(BinType::String, Vec::new())),
+                field(0x99, BinValue::U8(1)),
+            ],
+        };
+        let mut object = full.clone();
+        minimize_object(&meta, &mut object, DEFAULT_EPSILON);
+        let hashes = object.fields.iter().map(|x| x.hash).collect::<Vec<_>>();
+        assert_eq!(hashes, vec![0x2, 0x3, 0x99]);
+        assert_eq!(object.fields[1].value, item(4.0));
+        assert!(bin(object.clone()).len() < bin(full.clone()).len());
+
+        materialize_object(&meta, &mut object);
+        assert_eq!(bin(object), bin(full));
+
+        // Within epsilon counts as the default, embeds left empty go as a whole.
+        let mut object = BinObject {
+            class: 0x20,
+            fields: vec![
+                field(0x2, BinValue::F32(1.5 + DEFAULT_EPSILON / 2.0)),
+                field(0x3, item(3.0 - DEFAULT_EPSILON / 2.0)),
+            ],
+        };
+        minimize_object(&meta, &mut object, DEFAULT_EPSILON);
+        assert_eq!(object.fields, Vec::new());
+    }
+}
@@ -0,0 +1,523 @@
+use serde_json::{json, Map, Value};
+
+use crate::bin::{BinFile, BinObject, BinValue};
+use crate::bin_defaults::{default_property, values_match};
+use crate::bin_path::{self, key_text};
+use crate::codegen::json_key;
+use crate::hashes::HashNames;
+use crate::schema::MetaFile;
+use crate::type_expr::TypeExpr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffKind {
+    /// `is_default` when the value equals the class default, so only the
+    /// encoding changed.
+    Added {
+        value: BinValue,
+        is_default: bool,
+    },
+    Removed {
+        value: BinValue,
+        is_default: bool,
+    },
+    Changed {
+        old: BinValue,
+        new: BinValue,
+    },
+    /// Value was stored with another type, `expected` is the dumped type.
+    TypeChanged {
+        old: TypeExpr,
+        new: TypeExpr,
+        expected: Option<TypeExpr>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    /// Where the values differ, see `bin_path`.
+    pub path: String,
+    pub kind: DiffKind,
+}
+
+fn float_json(value: f32) -> Value {
+    // Go through the shortest f32 text so 0.1 does not print as 0.10000000149011612.
+    json!(value.to_string().parse::<f64>().unwrap_or(f64::NAN))
+}
+
+fn floats_json(values: &[f32]) -> Value {
+    Value::Array(values.iter().map(|&x| float_json(x)).collect())
+}
+
+fn object_json(names: &HashNames, object: &BinObject) -> Value {
+    let mut result = Map::new();
+    result.insert("__class".into(), json_key(names, object.class).into());
+    for field in &object.fields {
+        result.insert(json_key(names, field.hash), value_json(names, &field.value));
+    }
+    Value::Object(result)
+}
+
+/// Value in the bin JSON layout described by `bin-schema`.
+pub fn value_json(names: &HashNames, value: &BinValue) -> Value {
+    match value {
+        BinValue::None => Value::Null,
+        BinValue::Bool(x) | BinValue::Flag(x) => json!(x),
+        BinValue::I8(x) => json!(x),
+        BinValue::U8(x) => json!(x),
+        BinValue::I16(x) => json!(x),
+        BinValue::U16(x) => json!(x),
+        BinValue::I32(x) => json!(x),
+        BinValue::U32(x) => json!(x),
+        BinValue::I64(x) => json!(x),
+        BinValue::U64(x) => json!(x),
+        BinValue::F32(x) => float_json(*x),
+        BinValue::Vec2(x) => floats_json(x),
+        BinValue::Vec3(x) => floats_json(x),
+        BinValue::Vec4(x) => floats_json(x),
+        BinValue::Mtx44(x) => Value::Array(x.chunks(4).map(floats_json).collect()),
+        BinValue::Color(x) => json!(x),
+        BinValue::String(x) => json!(x),
+        BinValue::Hash(x) | BinValue::Link(x) => json_key(names, *x).into(),
+        BinValue::File(x) => format!("{:#x}", x).into(),
+        BinValue::List(_, items) | BinValue::List2(_, items) => {
+            Value::Array(items.iter().map(|x| value_json(names, x)).collect())
+        }
+        BinValue::Pointer(None) => Value::Null,
+        BinValue::Pointer(Some(object)) | BinValue::Embed(object) => object_json(names, object),
+        BinValue::Option(_, item) => match item {
+            Some(item) => value_json(names, item),
+            None => Value::Null,
+        },
+        BinValue::Map(_, _, items) => Value::Object(
+            items
+                .iter()
+                .map(|(k, v)| (key_text(names, k), value_json(names, v)))
+                .collect(),
+        ),
+    }
+}
+
+struct Differ<'a> {
+    meta: &'a MetaFile,
+    names: &'a HashNames,
+    epsilon: f32,
+    results: Vec<DiffEntry>,
+}
+
+impl<'a> Differ<'a> {
+    fn report(&mut self, path: &str, kind: DiffKind) {
+        self.results.push(DiffEntry {
+            path: path.to_string(),
+            kind,
+        });
+    }
+
+    /// Added or removed element where no class default applies.
+    fn added(&mut self, path: &str, value: BinValue) {
+        let is_default = false;
+        self.report(path, DiffKind::Added { value, is_default });
+    }
+
+    fn removed(&mut self, path: &str, value: BinValue) {
+        let is_default = false;
+        self.report(path, DiffKind::Removed { value, is_default });
+    }
+
+    fn object(&mut self, old: &BinObject, new: &BinObject, path: &str) {
+        let class = new.class;
+        let mut hashes: Vec<u32> = old.fields.iter().map(|f| f.hash).collect();
+        for field in &new.fields {
+            if !hashes.contains(&field.hash) {
+                hashes.push(field.hash);
+            }
+        }
+        for hash in hashes {
+            let path = bin_path::field(self.names, path, hash);
+            let info = self
+                .meta
+                .find_property(class, hash)
+                .map(|(_, _, info)| info);
+            let is_default = |value: &BinValue| match info {
+                Some(info) => {
+                    let default = default_property(self.meta, class, hash, info);
+                    values_match(value, &default, self.epsilon)
+                }
+                None => false,
+            };
+            match (old.field(hash), new.field(hash)) {
+                (Some(old), Some(new)) => {
+                    let expected = info.map(|x| x.type_expr().without_classes());
+                    self.value(old, new, expected.as_ref(), &path);
+                }
+                (Some(value), None) => {
+                    let is_default = is_default(value);
+                    let value = value.clone();
+                    self.report(&path, DiffKind::Removed { value, is_default });
+                }
+                (None, Some(value)) => {
+                    let is_default = is_default(value);
+                    let value = value.clone();
+                    self.report(&path, DiffKind::Added { value, is_default });
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn value(&mut self, old: &BinValue, new: &BinValue, expected: Option<&TypeExpr>, path: &str) {
+        if values_match(old, new, self.epsilon) {
+            return;
+        }
+        let (old_type, new_type) = (old.type_expr(), new.type_expr());
+        if old_type != new_type {
+            let kind = DiffKind::TypeChanged {
+                old: old_type,
+                new: new_type,
+                expected: expected.cloned(),
+            };
+            return self.report(path, kind);
+        }
+        let item = expected.and_then(|x| x.item());
+        match (old, new) {
+            (BinValue::Embed(a), BinValue::Embed(b))
+            | (BinValue::Pointer(Some(a)), BinValue::Pointer(Some(b)))
+                if a.class == b.class =>
+            {
+                self.object(a, b, path)
+            }
+            (BinValue::List(_, a), BinValue::List(_, b))
+            | (BinValue::List2(_, a), BinValue::List2(_, b)) => {
+                for index in 0..a.len().max(b.len()) {
+                    let path = bin_path::item(path, index);
+                    match (a.get(index), b.get(index)) {
+                        (Some(x), Some(y)) => self.value(x, y, item, &path),
+                        (Some(value), None) => self.removed(&path, value.clone()),
+                        (None, Some(value)) => self.added(&path, value.clone()),
+                        (None, None) => {}
+                    }
+                }
+            }
+            (BinValue::Option(_, Some(a)), BinValue::Option(_, Some(b))) => {
+                self.value(a, b, item, path)
+            }
+            (BinValue::Map(_, _, a), BinValue::Map(_, _, b)) => self.map(a, b, item, path),
+            _ => {
+                let (old, new) = (old.clone(), new.clone());
+                self.report(path, DiffKind::Changed { old, new });
+            }
+        }
+    }
+
+    fn map(
+        &mut self,
+        old: &[(BinValue, BinValue)],
+        new: &[(BinValue, BinValue)],
+        item: Option<&TypeExpr>,
+        path: &str,
+    ) {
+        for (key, value) in old {
+            let path = bin_path::map_item(self.names, path, key);
+            match new.iter().find(|(k, _)| k == key) {
+                Some((_, other)) => self.value(value, other, item, &path),
+                None => self.removed(&path, value.clone()),
+            }
+        }
+        for (key, value) in new {
+            if !old.iter().any(|(k, _)| k == key) {
+                let path = bin_path::map_item(self.names, path, key);
+                self.added(&path, value.clone());
+            }
+        }
+    }
+}
+
+/// Compares `old` and `new` by entry path and property path, ignoring field
+/// order and float differences up to `epsilon`.
+pub fn diff_bins(
+    meta: &MetaFile,
+    names: &HashNames,
+    old: &BinFile,
+    new: &BinFile,
+    epsilon: f32,
+) -> Vec<DiffEntry> {
+    let mut differ = Differ {
+        meta,
+        names,
+        epsilon,
+        results: Vec::new(),
+    };
+    for linked in &old.linked {
+        if !new.linked.contains(linked) {
+            differ.removed("linked", BinValue::String(linked.clone()));
+        }
+    }
+    for linked in &new.linked {
+        if !old.linked.contains(linked) {
+            differ.added("linked", BinValue::String(linked.clone()));
+        }
+    }
+    for entry in &old.entries {
+        let path = bin_path::entry(names, entry.path);
+        match new.entry(entry.path) {
+            Some(other) if other.object.class == entry.object.class => {
+                differ.object(&entry.object, &other.object, &path)
+            }
+            Some(other) => {
+                let old = BinValue::Embed(entry.object.clone());
+                let new = BinValue::Embed(other.object.clone());
+                differ.report(&path, DiffKind::Changed { old, new });
+            }
+            None => differ.removed(&path, BinValue::Embed(entry.object.clone())),
+        }
+    }
+    for entry in &new.entries {
+        if old.entry(entry.path).is_none() {
+            let path = bin_path::entry(names, entry.path);
+            differ.added(&path, BinValue::Embed(entry.object.clone()));
+        }
+    }
+    for patch in &old.patches {
+        let path = bin_path::patch(names, patch);
+        let other = new
+            .patches
+            .iter()
+            .find(|p| p.entry == patch.entry && p.path == patch.path);
+        match other {
+            Some(other) => differ.value(&patch.value, &other.value, None, &path),
+            None => differ.removed(&path, patch.value.clone()),
+        }
+    }
+    for patch in &new.patches {
+        if !old
+            .patches
+            .iter()
+            .any(|p| p.entry == patch.entry && p.path == patch.path)
+        {
+            let path = bin_path::patch(names, patch);
+            differ.added(&path, patch.value.clone());
+        }
+    }
+    differ.results
+}
+
+/// One line per difference, `+` added, `-` removed, `~` changed and `!` retyped.
+pub fn diff_text(names: &HashNames, diff: &[DiffEntry]) -> String {
+    let mut out = String::new();
+    for entry in diff {
+        let suffix = |is_default: bool| if is_default { " (default)" } else { "" };
+        let line = match &entry.kind {
+            DiffKind::Added { value, is_default } => format!(
+                "+ {} = {}{}",
+                entry.path,
+                value_json(names, value),
+                suffix(*is_default)
+            ),
+            DiffKind::Removed { value, is_default } => format!(
+                "- {} = {}{}",
+                entry.path,
+                value_json(names, value),
+                suffix(*is_default)
+            ),
+            DiffKind::Changed { old, new } => format!(
+                "~ {}: {} -> {}",
+                entry.path,
+                value_json(names, old),
+                value_json(names, new)
+            ),
+            DiffKind::TypeChanged { old, new, expected } => match expected {
+                Some(expected) => format!(
+                    "! {}: {} -> {}, expected {}",
+                    entry.path, old, new, expected
+                ),
+                None => format!("! {}: {} -> {}", entry.path, old, new),
+            },
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+pub fn diff_json(names: &HashNames, diff: &[DiffEntry]) -> Value {
+    let results = diff
+        .iter()
+        .map(|entry| match &entry.kind {
+            DiffKind::Added { value, is_default } => json!({
+                "path": entry.path,
+                "change": "added",
+                "new": value_json(names, value),
+                "default": is_default,
+            }),
+            DiffKind::Removed { value, is_default } => json!({
+                "path": entry.path,
+                "change": "removed",
+                "old": value_json(names, value),
+                "default": is_default,
+            }),
+            DiffKind::Changed { old, new } => json!({
+                "path": entry.path,
+                "change": "changed",
+                "old": value_json(names, old),
+                "new": value_json(names, new),
+            }),
+            DiffKind::TypeChanged { old, new, expected } => json!({
+                "path": entry.path,
+                "change": "type",
+                "old": old.to_string(),
+                "new": new.to_string(),
+                "expected": expected.as_ref().map(|x| x.to_string()),
+            }),
+        })
+        .collect();
+    Value::Array(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bin::{BinEntry, BinField};
+    use crate::bin_defaults::DEFAULT_EPSILON;
+    use crate::hashes::fnv1a;
+    use crate::schema::testing::{class, container, map, meta, property};
+    use crate::types::BinType;
+
+    fn h(name: &str) -> u32 {
+        fnv1a(name)
+    }
+
+    fn object(class: &str, fields: Vec<(&str, BinValue)>) -> BinObject {
+        BinObject {
+            class: h(class),
+            fields: fields
+                .into_iter()
+                .map(|(name, value)| BinField {
+                    hash: h(name),
+                    value,
+                })
+                .collect(),
+        }
+    }
+
+    fn bin(linked: &str, entries: Vec<(&str, Vec<(&str, BinValue)>)>) -> BinFile {
+        BinFile {
+            version: 3,
+            patch_header: None,
+            linked: vec![linked.to_string()],
+            entries: entries
+                .into_iter()
+                .map(|(path, fields)| BinEntry {
+                    path: h(path),
+                    object: object("Item", fields),
+                })
+                .collect(),
+            patches: Vec::new(),
+        }
+    }
+
+    fn u32s(items: &[u32]) -> BinValue {
+        BinValue::List(
+            BinType::U32,
+            items.iter().map(|&x| BinValue::U32(x)).collect(),
+        )
+    }
+
+    fn counts(items: &[(&str, u32)]) -> BinValue {
+        let items = items
+            .iter()
+            .map(|&(k, v)| (BinValue::Hash(h(k)), BinValue::U32(v)))
+            .collect();
+        BinValue::Map(BinType::Hash, BinType::U32, items)
+    }
+
+    fn inner(x: u32) -> BinValue {
+        BinValue::Embed(object("Inner", vec![("mX", BinValue::U32(x))]))
+    }
+
+    #[test]
+    fn differences_are_reported_by_path() {
+        let mut item = class(
+            None,
+            96,
+            vec![
+                (h("mA"), property(0, BinType::U32, None)),
+                (h("mF"), property(4, BinType::F32, None)),
+                (h("mList"), container(8, BinType::List, BinType::U32, None)),
+                (h("mMap"), map(24, BinType::Hash, BinType::U32, None)),
+                (h("mInner"), property(48, BinType::Embed, Some(h("Inner")))),
+                (h("mZero"), property(56, BinType::U32, None)),
+                (h("mWrong"), property(60, BinType::U32, None)),
+                (h("mNew"), property(64, BinType::U32, None)),
+            ],
+        );
+        item["defaults"] = serde_json::json!({ format!("{:#x}", h("mNew")): 7 });
+        let inner_class = class(None, 8, vec![(h("mX"), property(0, BinType::U32, None))]);
+        let meta = meta(vec![(h("Item"), item), (h("Inner"), inner_class)]);
+        let mut names = HashNames::new();
+        for name in [
+            "Item", "Inner", "mA", "mF", "mList", "mMap", "mInner", "mX", "mZero", "mWrong",
+            "mNew", "k1", "k2", "k3", "Entry", "Gone", "New",
+        ] {
+            names.insert(h(name), name);
+        }
+
+        let old = bin(
+            "a.bin",
+            vec![
+                (
+                    "Entry",
+                    vec![
+                        ("mA", BinValue::U32(1)),
+                        ("mF", BinValue::F32(1.0)),
+                        ("mList", u32s(&[1, 2, 3])),
+                        ("mMap", counts(&[("k1", 1), ("k2", 2)])),
+                        ("mInner", inner(0)),
+                        ("mZero", BinValue::U32(0)),
+                        ("mWrong", BinValue::U32(1)),
+                    ],
+                ),
+                ("Gone", Vec::new()),
+            ],
+        );
+        let new = bin(
+            "b.bin",
+            vec![
+                (
+                    "Entry",
+                    vec![
+                        ("mWrong", BinValue::String("x".into())),
+                        ("mInner", inner(4)),
+                        ("mMap", counts(&[("k2", 2), ("k3", 3)])),
+                        ("mList", u32s(&[1, 5])),
+                        ("mF", BinValue::F32(1.0 + DEFAULT_EPSILON / 2.0)),
+                        ("mA", BinValue::U32(2)),
+                        ("mNew", BinValue::U32(7)),
+                    ],
+                ),
+                ("New", Vec::new()),
+            ],
+        );
+        let diff = diff_bins(&meta, &names, &old, &new, DEFAULT_EPSILON);
+        assert_eq!(
+            diff_text(&names, &diff),
+            [
+                "- linked = \"a.bin\"",
+                "+ linked = \"b.bin\"",
+                "~ Entry.mA: 1 -> 2",
+                "~ Entry.mList[1]: 2 -> 5",
+                "- Entry.mList[2] = 3",
+                "- Entry.mMap{k1} = 1",
+                "+ Entry.mMap{k3} = 3",
+                "~ Entry.mInner.mX: 0 -> 4",
+                "- Entry.mZero = 0 (default)",
+                "! Entry.mWrong: u32 -> string, expected u32",
+                "+ Entry.mNew = 7 (default)",
+                "- Gone = {\"__class\":\"Item\"}",
+                "+ New = {\"__class\":\"Item\"}",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(diff_json(&names, &diff)[8]["default"], true);
+        assert_eq!(diff_bins(&meta, &names, &old, &old, 0.0), Vec::new());
+    }
+}
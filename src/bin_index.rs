@@ -0,0 +1,480 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value};
+
+use crate::bin::{BinFile, BinObject, BinValue};
+use crate::bin_defaults::{default_property, values_match, DEFAULT_EPSILON};
+use crate::bin_diff::value_json;
+use crate::codegen::json_key;
+use crate::hashes::HashNames;
+use crate::schema::MetaFile;
+use crate::wad::{is_wad, WadFile};
+
+/// Distinct values kept per property, the rest is only counted.
+pub const MAX_VALUES: usize = 64;
+
+#[derive(Debug, Clone, Default)]
+pub struct PropertyUsage {
+    /// Objects that set the property.
+    pub count: usize,
+    /// Of those, how many hold something other than the class default.
+    pub non_default: usize,
+    pub values: BTreeMap<String, usize>,
+    /// Uses whose value did not fit in `values` anymore.
+    pub other_values: usize,
+    pub files: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ClassUsage {
+    /// Entries and nested objects of exactly this class.
+    pub count: usize,
+    pub files: BTreeSet<String>,
+    /// Keyed by property, counted on the class that declares it.
+    pub properties: BTreeMap<u32, PropertyUsage>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CorpusIndex {
+    pub files: Vec<String>,
+    pub entries: usize,
+    /// Files that failed to load, with the reason.
+    pub errors: Vec<(String, String)>,
+    pub classes: BTreeMap<u32, ClassUsage>,
+}
+
+/// Short text a value is grouped by: scalars as JSON, objects by class and
+/// containers by length.
+fn value_key(names: &HashNames, value: &BinValue) -> String {
+    match value {
+        BinValue::Pointer(None) => "null".to_string(),
+        BinValue::Pointer(Some(object)) | BinValue::Embed(object) => {
+            format!("<{}>", json_key(names, object.class))
+        }
+        BinValue::List(_, items) | BinValue::List2(_, items) => format!("[{}]", items.len()),
+        BinValue::Map(_, _, items) => format!("{{{}}}", items.len()),
+        BinValue::Option(_, Some(item)) => value_key(names, item),
+        _ => value_json(names, value).to_string(),
+    }
+}
+
+/// Every `.bin` and WAD archive below `dir`, sorted.
+pub fn find_inputs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut results = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_wad(&path)
+                || path
+                    .extension()
+                    .is_some_and(|x| x.eq_ignore_ascii_case("bin"))
+            {
+                results.push(path);
+            }
+        }
+    }
+    results.sort();
+    Ok(results)
+}
+
+impl CorpusIndex {
+    /// Parses every `.bin` below `dir` and the bins inside WAD archives,
+    /// files that fail to parse are recorded in `errors`.
+    pub fn build(meta: &MetaFile, names: &HashNames, dir: &Path) -> io::Result<Self> {
+        let mut result = Self::default();
+        for path in find_inputs(dir)? {
+            let file = path
+                .strip_prefix(dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            if !is_wad(&path) {
+                match BinFile::load(&path) {
+                    Ok(bin) => result.add_bin(meta, names, &file, &bin),
+                    Err(e) => result.errors.push((file, e.to_string())),
+                }
+                continue;
+            }
+            let mut wad = match WadFile::open(&path) {
+                Ok(wad) => wad,
+                Err(e) => {
+                    result.errors.push((file, e.to_string()));
+                    continue;
+                }
+            };
+            wad.for_each_bin(|entry, bin| {
+                let inner = match names.get_file(entry.path) {
+                    Some(name) => format!("{}/{}", file, name),
+                    None => format!("{}/{:016x}.bin", file, entry.path),
+                };
+                match bin {
+                    Ok(bin) => result.add_bin(meta, names, &inner, &bin),
+                    Err(e) => result.errors.push((inner, e.to_string())),
+                }
+            })?;
+        }
+        Ok(result)
+    }
+
+    /// Counts the entries of `bin` and every object nested in them.
+    pub fn add_bin(&mut self, meta: &MetaFile, names: &HashNames, file: &str, bin: &BinFile) {
+        self.files.push(file.to_string());
+        self.entries += bin.entries.len();
+        for entry in &bin.entries {
+            self.object(meta, names, file, &entry.object);
+        }
+    }
+
+    fn object(&mut self, meta: &MetaFile, names: &HashNames, file: &str, object: &BinObject) {
+        let class = object.class;
+        let usage = self.classes.entry(class).or_default();
+        usage.count += 1;
+        usage.files.insert(file.to_string());
+        for field in &object.fields {
+            let (owner, non_default) = match meta.find_property(class, field.hash) {
+                Some((owner, _, info)) => {
+                    let default = default_property(meta, class, field.hash, info);
+                    (
+                        owner,
+                        !values_match(&field.value, &default, DEFAULT_EPSILON),
+                    )
+                }
+                None => (class, true),
+            };
+            let property = self
+                .classes
+                .entry(owner)
+                .or_default()
+                .properties
+                .entry(field.hash)
+                .or_default();
+            property.count += 1;
+            property.non_default += non_default as usize;
+            property.files.insert(file.to_string());
+            let key = value_key(names, &field.value);
+            let distinct = property.values.len();
+            match property.values.get_mut(&key) {
+                Some(count) => *count += 1,
+                None if distinct < MAX_VALUES => {
+                    property.values.insert(key, 1);
+                }
+                None => property.other_values += 1,
+            }
+            self.value(meta, names, file, &field.value);
+        }
+    }
+
+    fn value(&mut self, meta: &MetaFile, names: &HashNames, file: &str, value: &BinValue) {
+        match value {
+            BinValue::Pointer(Some(object)) | BinValue::Embed(object) => {
+                self.object(meta, names, file, object)
+            }
+            BinValue::List(_, items) | BinValue::List2(_, items) => {
+                for item in items {
+                    self.value(meta, names, file, item);
+                }
+            }
+            BinValue::Option(_, Some(item)) => self.value(meta, names, file, item),
+            BinValue::Map(_, _, items) => {
+                for (_, item) in items {
+                    self.value(meta, names, file, item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Dumped classes with no object of them or any subclass.
+    pub fn unused_classes(&self, meta: &MetaFile) -> Vec<u32> {
+        meta.classes
+            .keys()
+            .copied()
+            .filter(|&c| {
+                meta.subclasses(c)
+                    .iter()
+                    .all(|x| self.classes.get(x).is_none_or(|x| x.count == 0))
+            })
+            .collect()
+    }
+
+    /// Dumped properties as `(class, property)` that were never set.
+    pub fn unused_properties(&self, meta: &MetaFile) -> Vec<(u32, u32)> {
+        let mut results = Vec::new();
+        for (&class, info) in &meta.classes {
+            for &property in info.properties.keys() {
+                let used = self
+                    .classes
+                    .get(&class)
+                    .is_some_and(|x| x.properties.contains_key(&property));
+                if !used {
+                    results.push((class, property));
+                }
+            }
+        }
+        results
+    }
+
+    pub fn to_json(&self, meta: &MetaFile, names: &HashNames) -> Value {
+        let mut classes = Map::new();
+        for (&class, usage) in &self.classes {
+            let mut properties = Map::new();
+            for (&property, usage) in &usage.properties {
+                let known = meta
+                    .class(class)
+                    .is_some_and(|x| x.properties.contains_key(&property));
+                properties.insert(
+                    json_key(names, property),
+                    json!({
+                        "hash": format!("{:#x}", property),
+                        "known": known,
+                        "count": usage.count,
+                        "non_default": usage.non_default,
+                        "values": usage.values,
+                        "other_values": usage.other_values,
+                        "files": usage.files,
+                    }),
+                );
+            }
+            classes.insert(
+                json_key(names, class),
+                json!({
+                    "hash": format!("{:#x}", class),
+                    "known": meta.class(class).is_some(),
+                    "count": usage.count,
+                    "files": usage.files,
+                    "properties": properties,
+                }),
+            );
+        }
+        let unused_classes = self
+            .unused_classes(meta)
+            .into_iter()
+            .map(|c| json_key(names, c))
+            .collect::<Vec<_>>();
+        let unused_properties = self
+            .unused_properties(meta)
+            .into_iter()
+            .map(|(c, p)| format!("{}.{}", json_key(names, c), json_key(names, p)))
+            .collect::<Vec<_>>();
+        let errors = self
+            .errors
+            .iter()
+            .map(|(file, error)| json!({ "file": file, "error": error }))
+            .collect::<Vec<_>>();
+        json!({
+            "version": meta.version,
+            "files": self.files.len(),
+            "entries": self.entries,
+            "errors": errors,
+            "classes": classes,
+            "unused_classes": unused_classes,
+            "unused_properties": unused_properties,
+        })
+    }
+
+    /// Readable overview: usage per class and property, then everything unused.
+    pub fn summary(&self, meta: &MetaFile, names: &HashNames) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "{} files, {} entries, {} failed",
+            self.files.len(),
+            self.entries,
+            self.errors.len()
+        )
+        .unwrap();
+        for (file, error) in &self.errors {
+            writeln!(out, "failed {}: {}", file, error).unwrap();
+        }
+        for (&class, usage) in &self.classes {
+            writeln!(
+                out,
+                "{}: {} objects in {} files",
+                json_key(names, class),
+                usage.count,
+                usage.files.len()
+            )
+            .unwrap();
+            for (&property, usage) in &usage.properties {
+                writeln!(
+                    out,
+                    "    {}: {} set, {} non-default, {}{} distinct values, {} files",
+                    json_key(names, property),
+                    usage.count,
+                    usage.non_default,
+                    usage.values.len(),
+                    if usage.other_values > 0 { "+" } else { "" },
+                    usage.files.len()
+                )
+                .unwrap();
+            }
+        }
+        for class in self.unused_classes(meta) {
+            writeln!(out, "unused class {}", json_key(names, class)).unwrap();
+        }
+        for (class, property) in self.unused_properties(meta) {
+            writeln!(
+                out,
+                "unused property {}.{}",
+                json_key(names, class),
+                json_key(names, property)
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bin::{write_bin, BinEntry, BinField};
+    use crate::schema::testing::{class, meta, property};
+    use crate::types::BinType;
+
+    fn object(class: u32, fields: Vec<(u32, BinValue)>) -> BinObject {
+        BinObject {
+            class,
+            fields: fields
+                .into_iter()
+                .map(|(hash, value)| BinField { hash, value })
+                .collect(),
+        }
+    }
+
+    fn bin(entries: Vec<BinObject>) -> Vec<u8> {
+        write_bin(&BinFile {
+            version: 3,
+            patch_header: None,
+            linked: Vec::new(),
+            entries: entries
+                .into_iter()
+                .enumerate()
+                .map(|(path, object)| BinEntry {
+                    path: path as u32,
+                    object,
+                })
+                .collect(),
+            patches: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn usage_defaults_and_unused_schema_are_counted() {
+        let mut base = class(None, 8, vec![(0x1, property(0, BinType::U32, None))]);
+        base["defaults"] = json!({"0x1": 3});
+        let derived = class(
+            Some(0x10),
+            24,
+            vec![(0x2, property(8, BinType::Embed, Some(0x30)))],
+        );
+        let inner = class(
+            None,
+            8,
+            vec![
+                (0x3, property(0, BinType::U32, None)),
+                (0x4, property(4, BinType::U32, None)),
+            ],
+        );
+        let unused = class(None, 8, vec![(0x5, property(0, BinType::U32, None))]);
+        let meta = meta(vec![
+            (0x10, base),
+            (0x20, derived),
+            (0x30, inner),
+            (0x40, unused),
+        ]);
+
+        let dir = std::env::temp_dir().join(format!("bin-index-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let one = bin(vec![
+            object(
+                0x20,
+                vec![
+                    (0x1, BinValue::U32(3)),
+                    (
+                        0x2,
+                        BinValue::Embed(object(0x30, vec![(0x3, BinValue::U32(1))])),
+                    ),
+                ],
+            ),
+            object(0x20, vec![(0x1, BinValue::U32(5)), (0x99, BinValue::U8(1))]),
+        ]);
+        fs::write(dir.join("one.bin"), one).unwrap();
+        let two = bin(vec![object(0x20, vec![(0x1, BinValue::U32(5))])]);
+        fs::write(dir.join("sub").join("two.BIN"), two).unwrap();
+        fs::write(dir.join("bad.bin"), b"nope").unwrap();
+        fs::write(dir.join("notes.txt"), b"skipped").unwrap();
+        let index = CorpusIndex::build(&meta, &HashNames::new(), &dir);
+        fs::remove_dir_all(&dir).unwrap();
+        let index = index.unwrap();
+
+        assert_eq!(index.files, vec!["one.bin", "sub/two.BIN"]);
+        assert_eq!(index.entries, 3);
+        assert_eq!(index.errors.len(), 1);
+        assert_eq!(index.errors[0].0, "bad.bin");
+
+        let derived = &index.classes[&0x20];
+        assert_eq!(derived.count, 3);
+        assert_eq!(derived.files.len(), 2);
+        // Inherited properties are counted on the declaring class.
+        let inherited = &index.classes[&0x10].properties[&0x1];
+        assert_eq!(index.classes[&0x10].count, 0);
+        assert_eq!((inherited.count, inherited.non_default), (3, 2));
+        let values = inherited.values.iter().map(|(k, &v)| (k.as_str(), v));
+        assert_eq!(values.collect::<Vec<_>>(), vec![("3", 1), ("5", 2)]);
+        assert_eq!(inherited.files.len(), 2);
+        let embed = &derived.properties[&0x2];
+        assert_eq!((embed.count, embed.non_default), (1, 1));
+        assert_eq!(embed.values.keys().collect::<Vec<_>>(), vec!["<0x30>"]);
+        let unknown = &derived.properties[&0x99];
+        assert_eq!((unknown.count, unknown.non_default), (1, 1));
+        assert_eq!(index.classes[&0x30].count, 1);
+
+        assert_eq!(index.unused_classes(&meta), vec![0x40]);
+        assert_eq!(
+            index.unused_properties(&meta),
+            vec![(0x30, 0x4), (0x40, 0x5)]
+        );
+        let json = index.to_json(&meta, &HashNames::new());
+        assert_eq!(
+            json["classes"]["0x20"]["properties"]["0x99"]["known"],
+            false
+        );
+        assert_eq!(json["unused_properties"], json!(["0x30.0x4", "0x40.0x5"]));
+    }
+
+    #[test]
+    fn distinct_values_are_capped() {
+        let meta = meta(vec![(
+            0x10,
+            class(None, 8, vec![(0x1, property(0, BinType::U32, None))]),
+        )]);
+        let mut index = CorpusIndex::default();
+        let entries = (0..MAX_VALUES as u32 + 2)
+            .map(|x| BinEntry {
+                path: x,
+                object: object(0x10, vec![(0x1, BinValue::U32(x))]),
+            })
+            .collect();
+        let bin = BinFile {
+            version: 3,
+            patch_header: None,
+            linked: Vec::new(),
+            entries,
+            patches: Vec::new(),
+        };
+        index.add_bin(&meta, &HashNames::new(), "a.bin", &bin);
+        let usage = &index.classes[&0x10].properties[&0x1];
+        assert_eq!(usage.count, MAX_VALUES + 2);
+        assert_eq!(usage.non_default, MAX_VALUES + 1);
+        assert_eq!((usage.values.len(), usage.other_values), (MAX_VALUES, 2));
+    }
+}
@@ -0,0 +1,493 @@
+use std::collections::BTreeSet;
+
+use crate::bin::{BinEntry, BinField, BinFile, BinObject, BinValue};
+use crate::bin_defaults::default_property;
+use crate::bin_path;
+use crate::hashes::HashNames;
+use crate::schema::MetaFile;
+
+/// Both sides changed the same value differently, the result keeps ours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    /// Where both sides changed, see `bin_path`.
+    pub path: String,
+    pub base: Option<BinValue>,
+    pub ours: Option<BinValue>,
+    pub theirs: Option<BinValue>,
+}
+
+impl MergeConflict {
+    pub fn display(&self) -> String {
+        let problem = match (&self.ours, &self.theirs) {
+            (None, _) => "removed by ours and changed by theirs, kept removed",
+            (_, None) => "changed by ours and removed by theirs, kept ours",
+            _ => "changed differently on both sides, kept ours",
+        };
+        format!("{}: {}", self.path, problem)
+    }
+}
+
+/// Items of `base` still in both sides, followed by the ones either side added.
+fn merge_set<T: PartialEq + Clone>(base: &[T], ours: &[T], theirs: &[T]) -> Vec<T> {
+    let mut results: Vec<T> = ours
+        .iter()
+        .filter(|x| !base.contains(x) || theirs.contains(x))
+        .cloned()
+        .collect();
+    for item in theirs {
+        if !base.contains(item) && !results.contains(item) {
+            results.push(item.clone());
+        }
+    }
+    results
+}
+
+fn object_of(value: Option<&BinValue>, class: u32) -> Option<&BinObject> {
+    match value {
+        Some(BinValue::Embed(object) | BinValue::Pointer(Some(object)))
+            if object.class == class =>
+        {
+            Some(object)
+        }
+        _ => None,
+    }
+}
+
+fn items_of(value: Option<&BinValue>) -> Option<&[BinValue]> {
+    match value {
+        Some(BinValue::List(_, items) | BinValue::List2(_, items)) => Some(items),
+        _ => None,
+    }
+}
+
+fn field_or<'b>(
+    object: Option<&'b BinObject>,
+    hash: u32,
+    default: Option<&'b BinValue>,
+) -> Option<&'b BinValue> {
+    object.and_then(|o| o.field(hash)).or(default)
+}
+
+fn map_value<'b>(items: &'b [(BinValue, BinValue)], key: &BinValue) -> Option<&'b BinValue> {
+    items.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+struct Merger<'a> {
+    meta: &'a MetaFile,
+    names: &'a HashNames,
+    set_lists: &'a BTreeSet<u32>,
+    results: Vec<MergeConflict>,
+}
+
+impl<'a> Merger<'a> {
+    /// Merges one value, `None` meaning absent. `property` selects set lists.
+    fn value(
+        &mut self,
+        base: Option<&BinValue>,
+        ours: Option<&BinValue>,
+        theirs: Option<&BinValue>,
+        property: Option<u32>,
+        path: &str,
+    ) -> Option<BinValue> {
+        if ours == theirs || base == theirs {
+            return ours.cloned();
+        }
+        if base == ours {
+            return theirs.cloned();
+        }
+        if let (Some(ours), Some(theirs)) = (ours, theirs) {
+            if let Some(result) = self.nested(base, ours, theirs, property, path) {
+                return Some(result);
+            }
+        }
+        self.results.push(MergeConflict {
+            path: path.to_string(),
+            base: base.cloned(),
+            ours: ours.cloned(),
+            theirs: theirs.cloned(),
+        });
+        ours.cloned()
+    }
+
+    /// Merges inside values both sides changed, `None` when they can not be combined.
+    fn nested(
+        &mut self,
+        base: Option<&BinValue>,
+        ours: &BinValue,
+        theirs: &BinValue,
+        property: Option<u32>,
+        path: &str,
+    ) -> Option<BinValue> {
+        Some(match (ours, theirs) {
+            (BinValue::Embed(a), BinValue::Embed(b)) if a.class == b.class => {
+                BinValue::Embed(self.object(object_of(base, a.class), a, b, path))
+            }
+            (BinValue::Pointer(Some(a)), BinValue::Pointer(Some(b))) if a.class == b.class => {
+                BinValue::Pointer(Some(self.object(object_of(base, a.class), a, b, path)))
+            }
+            (BinValue::List(t, a), BinValue::List(u, b)) if t == u => {
+                BinValue::List(*t, self.list(items_of(base), a, b, property, path)?)
+            }
+            (BinValue::List2(t, a), BinValue::List2(u, b)) if t == u => {
+                BinValue::List2(*t, self.list(items_of(base), a, b, property, path)?)
+            }
+            (BinValue::Option(t, Some(a)), BinValue::Option(u, Some(b))) if t == u => {
+                let base = match base {
+                    Some(BinValue::Option(_, Some(x))) => Some(&**x),
+                    _ => None,
+                };
+                let item = self.value(base, Some(a), Some(b), None, path);
+                BinValue::Option(*t, item.map(Box::new))
+            }
+            (BinValue::Map(k, v, a), BinValue::Map(l, w, b)) if k == l && v == w => {
+                let base = match base {
+                    Some(BinValue::Map(_, _, x)) => x.as_slice(),
+                    _ => &[],
+                };
+                BinValue::Map(*k, *v, self.map(base, a, b, path))
+            }
+            _ => return None,
+        })
+    }
+
+    /// Merges field by field, absent fields count as their class default.
+    fn object(
+        &mut self,
+        base: Option<&BinObject>,
+        ours: &BinObject,
+        theirs: &BinObject,
+        path: &str,
+    ) -> BinObject {
+        let class = ours.class;
+        let mut hashes: Vec<u32> = ours.fields.iter().map(|f| f.hash).collect();
+        for field in &theirs.fields {
+            if !hashes.contains(&field.hash) {
+                hashes.push(field.hash);
+            }
+        }
+        let mut fields = Vec::new();
+        for hash in hashes {
+            let path = bin_path::field(self.names, path, hash);
+            let default = self
+                .meta
+                .find_property(class, hash)
+                .map(|(_, _, info)| default_property(self.meta, class, hash, info));
+            let default = default.as_ref();
+            let (b, o, t) = (
+                field_or(base, hash, default),
+                field_or(Some(ours), hash, default),
+                field_or(Some(theirs), hash, default),
+            );
+            let value = self.value(b, o, t, Some(hash), &path);
+            if let Some(value) = value {
+                if ours.field(hash).is_some() || Some(&value) != default {
+                    fields.push(BinField { hash, value });
+                }
+            }
+        }
+        BinObject { class, fields }
+    }
+
+    /// Set lists are merged by membership, others by index when no side
+    /// changed the length.
+    fn list(
+        &mut self,
+        base: Option<&[BinValue]>,
+        ours: &[BinValue],
+        theirs: &[BinValue],
+        property: Option<u32>,
+        path: &str,
+    ) -> Option<Vec<BinValue>> {
+        if property.is_some_and(|x| self.set_lists.contains(&x)) {
+            return Some(merge_set(base.unwrap_or_default(), ours, theirs));
+        }
+        if ours.len() != theirs.len() || base.is_some_and(|x| x.len() != ours.len()) {
+            return None;
+        }
+        let mut results = Vec::new();
+        for (index, (a, b)) in ours.iter().zip(theirs).enumerate() {
+            let base = base.map(|x| &x[index]);
+            let path = bin_path::item(path, index);
+            results.extend(self.value(base, Some(a), Some(b), None, &path));
+        }
+        Some(results)
+    }
+
+    fn map(
+        &mut self,
+        base: &[(BinValue, BinValue)],
+        ours: &[(BinValue, BinValue)],
+        theirs: &[(BinValue, BinValue)],
+        path: &str,
+    ) -> Vec<(BinValue, BinValue)> {
+        let mut keys: Vec<&BinValue> = ours.iter().map(|(k, _)| k).collect();
+        for (key, _) in theirs {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        let mut results = Vec::new();
+        for key in keys {
+            let path = bin_path::map_item(self.names, path, key);
+            let (b, o, t) = (
+                map_value(base, key),
+                map_value(ours, key),
+                map_value(theirs, key),
+            );
+            if let Some(value) = self.value(b, o, t, None, &path) {
+                results.push((key.clone(), value));
+            }
+        }
+        results
+    }
+}
+
+/// Three-way merge of `ours` and `theirs` against their common `base`. Entries
+/// and embedded objects are merged field by field, maps by key and lists by
+/// index, or by membership for properties in `set_lists`. Conflicts keep ours.
+pub fn merge_bins(
+    meta: &MetaFile,
+    names: &HashNames,
+    base: &BinFile,
+    ours: &BinFile,
+    theirs: &BinFile,
+    set_lists: &BTreeSet<u32>,
+) -> (BinFile, Vec<MergeConflict>) {
+    let mut merger = Merger {
+        meta,
+        names,
+        set_lists,
+        results: Vec::new(),
+    };
+    let mut paths: Vec<u32> = ours.entries.iter().map(|e| e.path).collect();
+    for entry in &theirs.entries {
+        if !paths.contains(&entry.path) {
+            paths.push(entry.path);
+        }
+    }
+    let object =
+        |bin: &BinFile, path: u32| bin.entry(path).map(|x| BinValue::Embed(x.object.clone()));
+    let mut entries = Vec::new();
+    for path in paths {
+        let (b, o, t) = (object(base, path), object(ours, path), object(theirs, path));
+        let key = bin_path::entry(names, path);
+        if let Some(BinValue::Embed(object)) =
+            merger.value(b.as_ref(), o.as_ref(), t.as_ref(), None, &key)
+        {
+            entries.push(BinEntry { path, object });
+        }
+    }
+    let bin = BinFile {
+        version: ours.version,
+        patch_header: ours.patch_header,
+        linked: merge_set(&base.linked, &ours.linked, &theirs.linked),
+        entries,
+        patches: merge_set(&base.patches, &ours.patches, &theirs.patches),
+    };
+    (bin, merger.results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashes::fnv1a;
+    use crate::schema::testing::{class, container, map, meta, property};
+    use crate::types::BinType;
+
+    fn h(name: &str) -> u32 {
+        fnv1a(name)
+    }
+
+    fn sample_meta() -> MetaFile {
+        let item = class(
+            None,
+            96,
+            vec![
+                (h("mA"), property(0, BinType::U32, None)),
+                (h("mB"), property(4, BinType::U32, None)),
+                (h("mTags"), container(8, BinType::List, BinType::Hash, None)),
+                (h("mList"), container(24, BinType::List, BinType::U32, None)),
+                (
+                    h("mOther"),
+                    container(40, BinType::List, BinType::U32, None),
+                ),
+                (h("mMap"), map(56, BinType::Hash, BinType::U32, None)),
+                (h("mInner"), property(80, BinType::Embed, Some(h("Inner")))),
+            ],
+        );
+        let inner = class(None, 8, vec![(h("mX"), property(0, BinType::U32, None))]);
+        meta(vec![(h("Item"), item), (h("Inner"), inner)])
+    }
+
+    fn sample_names() -> HashNames {
+        let mut names = HashNames::new();
+        for name in [
+            "Item", "Inner", "mA", "mB", "mInner", "mX", "mOther", "Entry", "Gone",
+        ] {
+            names.insert(h(name), name);
+        }
+        names
+    }
+
+    fn object(class: &str, fields: Vec<(&str, BinValue)>) -> BinObject {
+        BinObject {
+            class: h(class),
+            fields: fields
+                .into_iter()
+                .map(|(name, value)| BinField {
+                    hash: h(name),
+                    value,
+                })
+                .collect(),
+        }
+    }
+
+    fn bin(linked: &[&str], entries: Vec<(&str, Vec<(&str, BinValue)>)>) -> BinFile {
+        BinFile {
+            version: 3,
+            patch_header: None,
+            linked: linked.iter().map(|x| x.to_string()).collect(),
+            entries: entries
+                .into_iter()
+                .map(|(path, fields)| BinEntry {
+                    path: h(path),
+                    object: object("Item", fields),
+                })
+                .collect(),
+            patches: Vec::new(),
+        }
+    }
+
+    fn u32s(items: &[u32]) -> BinValue {
+        BinValue::List(
+            BinType::U32,
+            items.iter().map(|&x| BinValue::U32(x)).collect(),
+        )
+    }
+
+    fn tags(items: &[&str]) -> BinValue {
+        BinValue::List(
+            BinType::Hash,
+            items.iter().map(|&x| BinValue::Hash(h(x))).collect(),
+        )
+    }
+
+    fn counts(items: &[(&str, u32)]) -> BinValue {
+        let items = items
+            .iter()
+            .map(|&(k, v)| (BinValue::Hash(h(k)), BinValue::U32(v)))
+            .collect();
+        BinValue::Map(BinType::Hash, BinType::U32, items)
+    }
+
+    fn inner(x: u32) -> BinValue {
+        BinValue::Embed(object("Inner", vec![("mX", BinValue::U32(x))]))
+    }
+
+    #[test]
+    fn three_way_merge_keeps_ours_on_conflict() {
+        let base = bin(
+            &["a.bin"],
+            vec![
+                (
+                    "Entry",
+                    vec![
+                        ("mA", BinValue::U32(1)),
+                        ("mB", BinValue::U32(1)),
+                        ("mTags", tags(&["a", "b"])),
+                        ("mList", u32s(&[1, 2])),
+                        ("mOther", u32s(&[1, 2])),
+                        ("mMap", counts(&[("k1", 1)])),
+                        ("mInner", inner(0)),
+                    ],
+                ),
+                ("Gone", vec![("mA", BinValue::U32(1))]),
+            ],
+        );
+        let ours = bin(
+            &["a.bin", "b.bin"],
+            vec![(
+                "Entry",
+                vec![
+                    ("mA", BinValue::U32(2)),
+                    ("mB", BinValue::U32(5)),
+                    ("mTags", tags(&["a", "c"])),
+                    ("mList", u32s(&[9, 2])),
+                    ("mOther", u32s(&[1, 2, 3])),
+                    ("mMap", counts(&[("k1", 1), ("k2", 2)])),
+                    ("mInner", inner(3)),
+                ],
+            )],
+        );
+        let theirs = bin(
+            &["c.bin"],
+            vec![
+                (
+                    "Entry",
+                    vec![
+                        ("mA", BinValue::U32(1)),
+                        ("mB", BinValue::U32(6)),
+                        ("mTags", tags(&["a", "b", "d"])),
+                        ("mList", u32s(&[1, 8])),
+                        ("mOther", u32s(&[1])),
+                        ("mMap", counts(&[("k1", 5)])),
+                        ("mInner", inner(4)),
+                    ],
+                ),
+                ("Gone", vec![("mA", BinValue::U32(2))]),
+                ("New", vec![("mA", BinValue::U32(3))]),
+            ],
+        );
+        let set_lists = BTreeSet::from([h("mTags")]);
+        let names = sample_names();
+        let (merged, conflicts) =
+            merge_bins(&sample_meta(), &names, &base, &ours, &theirs, &set_lists);
+
+        let expected = bin(
+            &["b.bin", "c.bin"],
+            vec![
+                (
+                    "Entry",
+                    vec![
+                        ("mA", BinValue::U32(2)),
+                        ("mB", BinValue::U32(5)),
+                        ("mTags", tags(&["a", "c", "d"])),
+                        ("mList", u32s(&[9, 8])),
+                        ("mOther", u32s(&[1, 2, 3])),
+                        ("mMap", counts(&[("k1", 5), ("k2", 2)])),
+                        ("mInner", inner(3)),
+                    ],
+                ),
+                ("New", vec![("mA", BinValue::U32(3))]),
+            ],
+        );
+        assert_eq!(merged, expected);
+        let conflicts = conflicts.iter().map(|x| x.display()).collect::<Vec<_>>();
+        assert_eq!(
+            conflicts,
+            vec![
+                "Entry.mB: changed differently on both sides, kept ours",
+                "Entry.mOther: changed differently on both sides, kept ours",
+                "Entry.mInner.mX: changed differently on both sides, kept ours",
+                "Gone: removed by ours and changed by theirs, kept removed",
+            ]
+        );
+    }
+
+    #[test]
+    fn absent_fields_merge_as_their_default() {
+        let base = bin(&[], vec![("Entry", vec![])]);
+        let ours = bin(&[], vec![("Entry", vec![("mA", BinValue::U32(0))])]);
+        let theirs = bin(&[], vec![("Entry", vec![("mA", BinValue::U32(4))])]);
+        let (merged, conflicts) = merge_bins(
+            &sample_meta(),
+            &HashNames::new(),
+            &base,
+            &ours,
+            &theirs,
+            &BTreeSet::new(),
+        );
+        assert_eq!(conflicts, Vec::new());
+        assert_eq!(merged, theirs);
+    }
+}
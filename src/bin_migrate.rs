@@ -0,0 +1,410 @@
+use crate::bin::{BinField, BinFile, BinObject, BinValue};
+use crate::bin_defaults::nested_objects;
+use crate::bin_path;
+use crate::codegen::json_key;
+use crate::hashes::HashNames;
+use crate::schema::{MetaFile, PropertyInfo};
+use crate::type_expr::TypeExpr;
+use crate::types::BinType;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// Property was removed from the class, the field was dropped.
+    RemovedProperty { class: u32, property: u32 },
+    /// Value was rewritten for the new property type.
+    Converted { from: TypeExpr, to: TypeExpr },
+    /// Class is missing from the new dump, the object was kept as is.
+    RemovedClass(u32),
+    /// Property is in neither dump, the field was kept as is.
+    UnknownProperty { class: u32, property: u32 },
+    /// No automatic conversion exists, the field was dropped.
+    Unconvertible { from: TypeExpr, to: TypeExpr },
+    /// Integer does not fit the new type, the field was dropped.
+    OutOfRange { value: i128, to: BinType },
+    /// Object class is not accepted by the new property, the field was dropped.
+    WrongClass { expected: u32, found: u32 },
+    /// Element count does not fit the new container, the field was dropped.
+    WrongCount { expected: usize, found: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationNote {
+    /// Where the change was made, see `bin_path`.
+    pub path: String,
+    pub change: Change,
+}
+
+impl MigrationNote {
+    /// Whether the change was made without needing a look from the user.
+    pub fn is_automatic(&self) -> bool {
+        matches!(
+            self.change,
+            Change::RemovedProperty { .. } | Change::Converted { .. }
+        )
+    }
+
+    pub fn display(&self, names: &HashNames) -> String {
+        let change = match &self.change {
+            Change::RemovedProperty { class, property } => format!(
+                "dropped {} removed from {}",
+                json_key(names, *property),
+                json_key(names, *class)
+            ),
+            Change::Converted { from, to } => format!("converted {} to {}", from, to),
+            Change::RemovedClass(class) => {
+                format!(
+                    "class {} no longer exists, kept as is",
+                    json_key(names, *class)
+                )
+            }
+            Change::UnknownProperty { class, property } => format!(
+                "unknown property {} in {}, kept as is",
+                json_key(names, *property),
+                json_key(names, *class)
+            ),
+            Change::Unconvertible { from, to } => {
+                format!("can not convert {} to {}, dropped", from, to)
+            }
+            Change::OutOfRange { value, to } => {
+                format!("{} does not fit {:?}, dropped", value, to)
+            }
+            Change::WrongClass { expected, found } => format!(
+                "expected {} found {}, dropped",
+                json_key(names, *expected),
+                json_key(names, *found)
+            ),
+            Change::WrongCount { expected, found } => {
+                format!("expected {} elements found {}, dropped", expected, found)
+            }
+        };
+        format!("{}: {}", self.path, change)
+    }
+}
+
+fn is_integer(value_type: BinType) -> bool {
+    matches!(
+        value_type,
+        BinType::I8
+            | BinType::U8
+            | BinType::I16
+            | BinType::U16
+            | BinType::I32
+            | BinType::U32
+            | BinType::I64
+            | BinType::U64
+    )
+}
+
+fn integer_value(value: &BinValue) -> Option<i128> {
+    Some(match value {
+        BinValue::I8(x) => *x as i128,
+        BinValue::U8(x) => *x as i128,
+        BinValue::I16(x) => *x as i128,
+        BinValue::U16(x) => *x as i128,
+        BinValue::I32(x) => *x as i128,
+        BinValue::U32(x) => *x as i128,
+        BinValue::I64(x) => *x as i128,
+        BinValue::U64(x) => *x as i128,
+        _ => return None,
+    })
+}
+
+fn integer_from(value_type: BinType, value: i128) -> Option<BinValue> {
+    Some(match value_type {
+        BinType::I8 => BinValue::I8(value.try_into().ok()?),
+        BinType::U8 => BinValue::U8(value.try_into().ok()?),
+        BinType::I16 => BinValue::I16(value.try_into().ok()?),
+        BinType::U16 => BinValue::U16(value.try_into().ok()?),
+        BinType::I32 => BinValue::I32(value.try_into().ok()?),
+        BinType::U32 => BinValue::U32(value.try_into().ok()?),
+        BinType::I64 => BinValue::I64(value.try_into().ok()?),
+        BinType::U64 => BinValue::U64(value.try_into().ok()?),
+        _ => return None,
+    })
+}
+
+struct Migrator<'a> {
+    old: &'a MetaFile,
+    new: &'a MetaFile,
+    names: &'a HashNames,
+    results: Vec<MigrationNote>,
+}
+
+impl<'a> Migrator<'a> {
+    fn note(&self, path: &str, change: Change) -> MigrationNote {
+        MigrationNote {
+            path: path.to_string(),
+            change,
+        }
+    }
+
+    fn object(&mut self, object: &mut BinObject, path: &str) {
+        let class = object.class;
+        if self.new.class(class).is_none() {
+            self.results
+                .push(self.note(path, Change::RemovedClass(class)));
+            return;
+        }
+        for field in std::mem::take(&mut object.fields) {
+            let path = bin_path::field(self.names, path, field.hash);
+            let property = field.hash;
+            let info = match self.new.find_property(class, property) {
+                Some((_, _, info)) => info,
+                None if self.old.find_property(class, property).is_some() => {
+                    let change = Change::RemovedProperty { class, property };
+                    self.results.push(self.note(&path, change));
+                    continue;
+                }
+                None => {
+                    let change = Change::UnknownProperty { class, property };
+                    self.results.push(self.note(&path, change));
+                    object.fields.push(field);
+                    continue;
+                }
+            };
+            let from = field.value.type_expr();
+            match self.property(field.value, info, &path) {
+                Ok(value) => {
+                    let to = value.type_expr();
+                    if from != to {
+                        self.results
+                            .push(self.note(&path, Change::Converted { from, to }));
+                    }
+                    object.fields.push(BinField {
+                        hash: property,
+                        value,
+                    });
+                }
+                Err(note) => self.results.push(note),
+            }
+        }
+    }
+
+    fn property(
+        &mut self,
+        value: BinValue,
+        info: &PropertyInfo,
+        path: &str,
+    ) -> Result<BinValue, MigrationNote> {
+        let value = self.value(value, &info.type_expr(), path)?;
+        let fixed_size = info.container.as_ref().and_then(|c| c.fixed_size);
+        match (&value, fixed_size) {
+            (BinValue::List(_, items) | BinValue::List2(_, items), Some(expected))
+                if items.len() != expected =>
+            {
+                let found = items.len();
+                Err(self.note(path, Change::WrongCount { expected, found }))
+            }
+            _ => Ok(value),
+        }
+    }
+
+    /// Checks `object` may be stored where `expected` is, exactly for embeds.
+    fn nested(
+        &mut self,
+        mut object: BinObject,
+        expected: Option<u32>,
+        exact: bool,
+        path: &str,
+    ) -> Result<BinObject, MigrationNote> {
+        if let Some(expected) = expected.filter(|&c| self.new.class(c).is_some()) {
+            let found = object.class;
+            let allowed = if exact {
+                found == expected
+            } else {
+                self.new.is_subclass(found, expected)
+            };
+            if !allowed {
+                return Err(self.note(path, Change::WrongClass { expected, found }));
+            }
+        }
+        self.object(&mut object, path);
+        Ok(object)
+    }
+
+    fn items(
+        &mut self,
+        items: Vec<BinValue>,
+        to: &TypeExpr,
+        path: &str,
+    ) -> Result<Vec<BinValue>, MigrationNote> {
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| self.value(item, to, &bin_path::item(path, index)))
+            .collect()
+    }
+
+    fn value(
+        &mut self,
+        value: BinValue,
+        to: &TypeExpr,
+        path: &str,
+    ) -> Result<BinValue, MigrationNote> {
+        let unconvertible = |value: &BinValue| Change::Unconvertible {
+            from: value.type_expr(),
+            to: to.without_classes(),
+        };
+        Ok(match (value, to) {
+            (BinValue::Embed(object), TypeExpr::Nestable(BinType::Embed, class))
+            | (BinValue::Pointer(Some(object)), TypeExpr::Nestable(BinType::Embed, class)) => {
+                BinValue::Embed(self.nested(object, *class, true, path)?)
+            }
+            (BinValue::Embed(object), TypeExpr::Nestable(BinType::Pointer, class))
+            | (BinValue::Pointer(Some(object)), TypeExpr::Nestable(BinType::Pointer, class)) => {
+                BinValue::Pointer(Some(self.nested(object, *class, false, path)?))
+            }
+            (value @ BinValue::Pointer(None), TypeExpr::Nestable(BinType::Pointer, _)) => value,
+            (
+                BinValue::List(_, items) | BinValue::List2(_, items),
+                TypeExpr::Container(container, item),
+            ) => {
+                let items = self.items(items, item, path)?;
+                self.container(*container, items, item, path)?
+            }
+            (BinValue::Option(_, value), TypeExpr::Container(container, item)) => {
+                let items = value.into_iter().map(|x| *x).collect();
+                let items = self.items(items, item, path)?;
+                self.container(*container, items, item, path)?
+            }
+            // Option removed, the value is unwrapped.
+            (BinValue::Option(_, Some(value)), TypeExpr::Nestable(..)) => {
+                self.value(*value, to, path)?
+            }
+            (BinValue::Map(_, _, items), TypeExpr::Map(key, item)) => {
+                let mut results = Vec::new();
+                for (k, v) in items {
+                    let path = bin_path::map_item(self.names, path, &k);
+                    results.push((self.value(k, key, &path)?, self.value(v, item, &path)?));
+                }
+                BinValue::Map(key.value_type(), item.value_type(), results)
+            }
+            // Single value moved into a container, like an option being added.
+            (value, TypeExpr::Container(container, item)) if value.bin_type().is_nestable() => {
+                let items = vec![self.value(value, item, path)?];
+                self.container(*container, items, item, path)?
+            }
+            (value, TypeExpr::Nestable(to_type, _)) => {
+                let to_type = *to_type;
+                match value {
+                    value if value.bin_type() == to_type && value.bin_type().is_nestable() => value,
+                    BinValue::Bool(x) | BinValue::Flag(x) if to_type == BinType::Bool => {
+                        BinValue::Bool(x)
+                    }
+                    BinValue::Bool(x) | BinValue::Flag(x) if to_type == BinType::Flag => {
+                        BinValue::Flag(x)
+                    }
+                    value => match integer_value(&value) {
+                        Some(x) if is_integer(to_type) => match integer_from(to_type, x) {
+                            Some(value) => value,
+                            None => {
+                                let change = Change::OutOfRange {
+                                    value: x,
+                                    to: to_type,
+                                };
+                                return Err(self.note(path, change));
+                            }
+                        },
+                        _ => return Err(self.note(path, unconvertible(&value))),
+                    },
+                }
+            }
+            (value, _) => return Err(self.note(path, unconvertible(&value))),
+        })
+    }
+
+    /// Packs converted items into a `list`, `list2` or `option`.
+    fn container(
+        &self,
+        container: BinType,
+        mut items: Vec<BinValue>,
+        item: &TypeExpr,
+        path: &str,
+    ) -> Result<BinValue, MigrationNote> {
+        let item_type = item.value_type();
+        Ok(match container {
+            BinType::List => BinValue::List(item_type, items),
+            BinType::List2 => BinValue::List2(item_type, items),
+            _ if items.len() > 1 => {
+                let found = items.len();
+                return Err(self.note(path, Change::WrongCount { expected: 1, found }));
+            }
+            _ => BinValue::Option(item_type, items.pop().map(Box::new)),
+        })
+    }
+}
+
+/// Rewrites `bin` written against `old` for the classes in `new`: integers are
+/// widened, containers repacked and removed properties dropped. Returns every
+/// change made, fields that could not be converted are dropped and reported.
+/// Patch values are not resolved, only the objects inside them are migrated.
+pub fn migrate_bin(
+    old: &MetaFile,
+    new: &MetaFile,
+    names: &HashNames,
+    bin: &mut BinFile,
+) -> Vec<MigrationNote> {
+    let mut migrator = Migrator {
+        old,
+        new,
+        names,
+        results: Vec::new(),
+    };
+    for entry in &mut bin.entries {
+        let path = bin_path::entry(names, entry.path);
+        migrator.object(&mut entry.object, &path);
+    }
+    for patch in &mut bin.patches {
+        let path = bin_path::patch(names, patch);
+        nested_objects(&mut patch.value, &mut |x| migrator.object(x, &path));
+    }
+    migrator.results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bin::BinEntry;
+    use crate::hashes::fnv1a;
+    use crate::schema::testing::{class, map, meta};
+
+    #[test]
+    fn map_items_are_located_by_key() {
+        let holder = |value_type| {
+            class(
+                None,
+                8,
+                vec![(fnv1a("mMap"), map(0, BinType::Hash, value_type, None))],
+            )
+        };
+        let old = meta(vec![(0x30, holder(BinType::U8))]);
+        let new = meta(vec![(0x30, holder(BinType::I8))]);
+        let mut names = HashNames::new();
+        for name in ["Annie", "mMap", "Foo"] {
+            names.insert(fnv1a(name), name);
+        }
+        let items = vec![
+            (BinValue::Hash(0x1), BinValue::U8(1)),
+            (BinValue::Hash(fnv1a("Foo")), BinValue::U8(200)),
+        ];
+        let mut bin = BinFile {
+            version: 3,
+            patch_header: None,
+            linked: Vec::new(),
+            entries: vec![BinEntry {
+                path: fnv1a("Annie"),
+                object: BinObject {
+                    class: 0x30,
+                    fields: vec![BinField {
+                        hash: fnv1a("mMap"),
+                        value: BinValue::Map(BinType::Hash, BinType::U8, items),
+                    }],
+                },
+            }],
+            patches: Vec::new(),
+        };
+        let notes = migrate_bin(&old, &new, &names, &mut bin);
+        let paths = notes.iter().map(|x| x.path.as_str()).collect::<Vec<_>>();
+        assert!(paths.contains(&"Annie.mMap{Foo}"), "{:?}", notes);
+    }
+}
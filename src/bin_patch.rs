@@ -0,0 +1,352 @@
+use crate::bin::{BinField, BinFile, BinObject, BinPatch, BinValue};
+use crate::bin_check::{check_element, check_property, Diagnostic, Problem};
+use crate::bin_defaults::default_property;
+use crate::bin_path;
+use crate::hashes::{name_or_hash, HashNames};
+use crate::schema::{MetaFile, PropertyInfo};
+use crate::types::BinType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Field(u32),
+    Index(usize),
+}
+
+/// Parses a patch path such as `mItems[2].mName`, names may also be `0x` hashes.
+pub fn parse_path(text: &str) -> Option<Vec<Step>> {
+    let mut results = Vec::new();
+    for segment in text.split('.') {
+        let (name, mut rest) = segment.split_at(segment.find('[').unwrap_or(segment.len()));
+        if name.is_empty() {
+            return None;
+        }
+        results.push(Step::Field(name_or_hash(name)));
+        while let Some(inner) = rest.strip_prefix('[') {
+            let end = inner.find(']')?;
+            results.push(Step::Index(inner[..end].parse().ok()?));
+            rest = &inner[end + 1..];
+        }
+        if !rest.is_empty() {
+            return None;
+        }
+    }
+    Some(results)
+}
+
+struct Patcher<'a> {
+    meta: &'a MetaFile,
+    names: &'a HashNames,
+}
+
+impl<'a> Patcher<'a> {
+    fn error(&self, path: &str, problem: Problem) -> Vec<Diagnostic> {
+        vec![Diagnostic {
+            path: path.to_string(),
+            problem,
+        }]
+    }
+
+    fn object(
+        &self,
+        object: &mut BinObject,
+        steps: &[Step],
+        value: &BinValue,
+        path: &str,
+    ) -> Result<(), Vec<Diagnostic>> {
+        let hash = match steps.first() {
+            Some(Step::Field(hash)) => *hash,
+            _ => return Err(self.error(path, Problem::InvalidPath(path.to_string()))),
+        };
+        let path = bin_path::field(self.names, path, hash);
+        let class = object.class;
+        let info = match self.meta.find_property(class, hash) {
+            Some((_, _, info)) => info,
+            None => {
+                let property = hash;
+                return Err(self.error(&path, Problem::UnknownProperty { class, property }));
+            }
+        };
+        if object.field(hash).is_none() {
+            let value = default_property(self.meta, class, hash, info);
+            object.fields.push(BinField { hash, value });
+        }
+        let slot = object.field_mut(hash).unwrap();
+        self.property(info, slot, &steps[1..], value, &path)
+    }
+
+    fn nested(
+        &self,
+        slot: &mut BinValue,
+        steps: &[Step],
+        value: &BinValue,
+        path: &str,
+    ) -> Result<(), Vec<Diagnostic>> {
+        match slot {
+            BinValue::Embed(object) | BinValue::Pointer(Some(object)) => {
+                self.object(object, steps, value, path)
+            }
+            BinValue::Pointer(None) => Err(self.error(path, Problem::NullPointer)),
+            _ => {
+                let expected = BinType::Embed;
+                let found = slot.bin_type();
+                Err(self.error(path, Problem::TypeMismatch { expected, found }))
+            }
+        }
+    }
+
+    fn property(
+        &self,
+        info: &PropertyInfo,
+        slot: &mut BinValue,
+        steps: &[Step],
+        value: &BinValue,
+        path: &str,
+    ) -> Result<(), Vec<Diagnostic>> {
+        match steps.first() {
+            None => {
+                let results = check_property(self.meta, self.names, info, value, path);
+                if !results.is_empty() {
+                    return Err(results);
+                }
+                *slot = value.clone();
+                Ok(())
+            }
+            Some(Step::Index(index)) => {
+                let (item_type, items) = match slot {
+                    BinValue::List(item_type, items) | BinValue::List2(item_type, items) => {
+                        (*item_type, items)
+                    }
+                    _ => {
+                        let expected = BinType::List;
+                        let found = slot.bin_type();
+                        return Err(self.error(path, Problem::TypeMismatch { expected, found }));
+                    }
+                };
+                let count = items.len();
+                let path = bin_path::item(path, *index);
+                let item = match items.get_mut(*index) {
+                    Some(item) => item,
+                    None => {
+                        let index = *index;
+                        return Err(self.error(&path, Problem::IndexOutOfRange { index, count }));
+                    }
+                };
+                if steps.len() > 1 {
+                    return self.nested(item, &steps[1..], value, &path);
+                }
+                let other = info.other_class;
+                let results = check_element(self.meta, self.names, item_type, other, value, &path);
+                if !results.is_empty() {
+                    return Err(results);
+                }
+                *item = value.clone();
+                Ok(())
+            }
+            Some(Step::Field(_)) => self.nested(slot, steps, value, path),
+        }
+    }
+
+    fn patch(&self, bin: &mut BinFile, patch: &BinPatch) -> Result<(), Vec<Diagnostic>> {
+        let path = bin_path::patch(self.names, patch);
+        let steps = match parse_path(&patch.path) {
+            Some(steps) => steps,
+            None => return Err(self.error(&path, Problem::InvalidPath(patch.path.clone()))),
+        };
+        let entry = match bin.entries.iter_mut().find(|e| e.path == patch.entry) {
+            Some(entry) => entry,
+            None => return Err(self.error(&path, Problem::UnknownEntry(patch.entry))),
+        };
+        // Work on a copy so a failing override leaves no defaults behind.
+        let mut object = entry.object.clone();
+        let root = bin_path::entry(self.names, patch.entry);
+        self.object(&mut object, &steps, &patch.value, &root)?;
+        entry.object = object;
+        Ok(())
+    }
+}
+
+/// Applies entries and path overrides of `patch` to `bin`. Overrides that fail
+/// to resolve or type-check are skipped and reported.
+pub fn apply_patches(
+    meta: &MetaFile,
+    names: &HashNames,
+    bin: &mut BinFile,
+    patch: &BinFile,
+) -> Vec<Diagnostic> {
+    for linked in &patch.linked {
+        if !bin.linked.contains(linked) {
+            bin.linked.push(linked.clone());
+        }
+    }
+    for entry in &patch.entries {
+        match bin.entries.iter_mut().find(|e| e.path == entry.path) {
+            Some(existing) => *existing = entry.clone(),
+            None => bin.entries.push(entry.clone()),
+        }
+    }
+    let patcher = Patcher { meta, names };
+    let mut results = Vec::new();
+    for patch in &patch.patches {
+        if let Err(errors) = patcher.patch(bin, patch) {
+            results.extend(errors);
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bin::{read_bin, write_bin, BinEntry};
+    use crate::hashes::fnv1a;
+    use crate::schema::testing::{class, container, meta, property};
+
+    fn object(class: u32, fields: Vec<(u32, BinValue)>) -> BinObject {
+        BinObject {
+            class,
+            fields: fields
+                .into_iter()
+                .map(|(hash, value)| BinField { hash, value })
+                .collect(),
+        }
+    }
+
+    fn entry(path: u32, fields: Vec<(u32, BinValue)>) -> BinEntry {
+        let object = object(0x10, fields);
+        BinEntry { path, object }
+    }
+
+    fn patch(entry: u32, path: &str, value: BinValue) -> BinPatch {
+        let path = path.to_string();
+        BinPatch { entry, path, value }
+    }
+
+    fn sample_meta() -> MetaFile {
+        let holder = class(
+            None,
+            48,
+            vec![
+                (0x1, property(0, BinType::U32, None)),
+                (0x2, container(8, BinType::List, BinType::Embed, Some(0x20))),
+                (0x3, property(24, BinType::Pointer, Some(0x20))),
+                (0x4, property(32, BinType::Embed, Some(0x20))),
+            ],
+        );
+        let item = class(None, 8, vec![(0x5, property(0, BinType::String, None))]);
+        meta(vec![(0x10, holder), (0x20, item)])
+    }
+
+    fn item(text: &str) -> BinValue {
+        BinValue::Embed(object(0x20, vec![(0x5, BinValue::String(text.into()))]))
+    }
+
+    #[test]
+    fn paths_are_parsed() {
+        assert_eq!(
+            parse_path("mItems[2][0].0x5"),
+            Some(vec![
+                Step::Field(fnv1a("mItems")),
+                Step::Index(2),
+                Step::Index(0),
+                Step::Field(0x5),
+            ])
+        );
+        for text in ["", "a..b", "[0]", "a[", "a[x]", "a[0]b"] {
+            assert_eq!(parse_path(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn patches_survive_a_round_trip_and_apply() {
+        let mut bin = BinFile {
+            version: 3,
+            patch_header: None,
+            linked: vec!["a.bin".into()],
+            entries: vec![
+                entry(
+                    0x100,
+                    vec![
+                        (0x1, BinValue::U32(1)),
+                        (0x2, BinValue::List(BinType::Embed, vec![item("a")])),
+                        (0x3, BinValue::Pointer(None)),
+                    ],
+                ),
+                entry(0x200, vec![(0x1, BinValue::U32(9))]),
+            ],
+            patches: Vec::new(),
+        };
+        let patches = BinFile {
+            version: 3,
+            patch_header: Some(1),
+            linked: vec!["a.bin".into(), "b.bin".into()],
+            entries: vec![
+                entry(0x200, vec![(0x1, BinValue::U32(2))]),
+                entry(0x300, Vec::new()),
+            ],
+            patches: vec![
+                patch(0x100, "0x1", BinValue::U32(5)),
+                patch(0x100, "0x2[0].0x5", BinValue::String("b".into())),
+                patch(0x100, "0x4.0x5", BinValue::String("c".into())),
+                patch(0x100, "0x3.0x5", BinValue::String("d".into())),
+                patch(0x100, "0x2[3]", item("e")),
+                patch(0x100, "0x1", BinValue::String("f".into())),
+                patch(0x300, "0x4.0x5", BinValue::U32(1)),
+                patch(0x999, "0x1", BinValue::U32(1)),
+            ],
+        };
+        let data = write_bin(&patches).unwrap();
+        assert!(data.starts_with(b"PTCH"));
+        let patches = read_bin(&data).unwrap();
+        assert_eq!(write_bin(&patches).unwrap(), data);
+
+        let results = apply_patches(&sample_meta(), &HashNames::new(), &mut bin, &patches);
+        let problems = results
+            .into_iter()
+            .map(|x| (x.path, x.problem))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            vec![
+                ("0x100.0x3".to_string(), Problem::NullPointer),
+                (
+                    "0x100.0x2[3]".to_string(),
+                    Problem::IndexOutOfRange { index: 3, count: 1 }
+                ),
+                (
+                    "0x100.0x1".to_string(),
+                    Problem::TypeMismatch {
+                        expected: BinType::U32,
+                        found: BinType::String
+                    }
+                ),
+                (
+                    "0x300.0x4.0x5".to_string(),
+                    Problem::TypeMismatch {
+                        expected: BinType::String,
+                        found: BinType::U32
+                    }
+                ),
+                ("0x999.0x1".to_string(), Problem::UnknownEntry(0x999)),
+            ]
+        );
+
+        assert_eq!(bin.patch_header, None);
+        assert_eq!(bin.linked, vec!["a.bin".to_string(), "b.bin".to_string()]);
+        assert_eq!(
+            bin.entries,
+            vec![
+                entry(
+                    0x100,
+                    vec![
+                        (0x1, BinValue::U32(5)),
+                        (0x2, BinValue::List(BinType::Embed, vec![item("b")])),
+                        (0x3, BinValue::Pointer(None)),
+                        (0x4, item("c")),
+                    ],
+                ),
+                entry(0x200, vec![(0x1, BinValue::U32(2))]),
+                entry(0x300, Vec::new()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,46 @@
+//! Locations the bin tools report, like `Entry.mItems[2].mMap{"key"}.mName`.
+//! Hashes are used for unknown names, so paths from different tools compare
+//! equal.
+
+use crate::bin::{BinPatch, BinValue};
+use crate::codegen::json_key;
+use crate::hashes::HashNames;
+
+pub fn entry(names: &HashNames, path: u32) -> String {
+    json_key(names, path)
+}
+
+/// Entry followed by the patch path as written in the bin.
+pub fn patch(names: &HashNames, patch: &BinPatch) -> String {
+    format!("{}.{}", json_key(names, patch.entry), patch.path)
+}
+
+pub fn field(names: &HashNames, path: &str, hash: u32) -> String {
+    format!("{}.{}", path, json_key(names, hash))
+}
+
+pub fn item(path: &str, index: usize) -> String {
+    format!("{}[{}]", path, index)
+}
+
+pub fn map_item(names: &HashNames, path: &str, key: &BinValue) -> String {
+    format!("{}{{{}}}", path, key_text(names, key))
+}
+
+/// Map key as shown in paths like `mMap{5}`.
+pub fn key_text(names: &HashNames, key: &BinValue) -> String {
+    match key {
+        BinValue::I8(x) => x.to_string(),
+        BinValue::U8(x) => x.to_string(),
+        BinValue::I16(x) => x.to_string(),
+        BinValue::U16(x) => x.to_string(),
+        BinValue::I32(x) => x.to_string(),
+        BinValue::U32(x) => x.to_string(),
+        BinValue::I64(x) => x.to_string(),
+        BinValue::U64(x) => x.to_string(),
+        BinValue::String(x) => format!("{:?}", x),
+        BinValue::Hash(x) | BinValue::Link(x) => json_key(names, *x),
+        BinValue::File(x) => format!("{:#x}", x),
+        _ => format!("{:?}", key.bin_type()),
+    }
+}
@@ -0,0 +1,835 @@
+use std::fmt::Write;
+use std::io;
+
+use crate::bin::{BinEntry, BinField, BinFile, BinObject, BinPatch, BinValue};
+use crate::hashes::{fnv1a, xxh64, HashNames};
+use crate::schema::MetaFile;
+use crate::type_expr::TypeExpr;
+use crate::types::BinType;
+
+fn is_word(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Known name of `hash` if it actually hashes back to it.
+fn hash_name(names: &HashNames, hash: u32) -> Option<&str> {
+    names.get(hash).filter(|name| fnv1a(name) == hash)
+}
+
+fn write_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\x{:02x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct TextWriter<'a> {
+    names: &'a HashNames,
+    out: String,
+    indent: usize,
+}
+
+impl<'a> TextWriter<'a> {
+    fn pad(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    /// Class and field names are bare words, unknown ones raw hashes.
+    fn name(&mut self, hash: u32) {
+        match hash_name(self.names, hash).filter(|x| is_word(x)) {
+            Some(name) => self.out.push_str(name),
+            None => write!(self.out, "{:#010x}", hash).unwrap(),
+        }
+    }
+
+    /// Hash values are quoted strings, unknown ones raw hashes.
+    fn hash(&mut self, hash: u32) {
+        match hash_name(self.names, hash) {
+            Some(name) => write_string(&mut self.out, name),
+            None => write!(self.out, "{:#010x}", hash).unwrap(),
+        }
+    }
+
+    fn floats(&mut self, values: &[f32]) {
+        let items = values
+            .iter()
+            .map(|x| format!("{:?}", x))
+            .collect::<Vec<_>>();
+        write!(self.out, "{{ {} }}", items.join(", ")).unwrap();
+    }
+
+    fn block<T>(&mut self, items: &[T], mut write: impl FnMut(&mut Self, &T)) {
+        if items.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push_str("{\n");
+        self.indent += 1;
+        for item in items {
+            self.pad();
+            write(self, item);
+            self.out.push('\n');
+        }
+        self.indent -= 1;
+        self.pad();
+        self.out.push('}');
+    }
+
+    fn object(&mut self, object: &BinObject) {
+        self.name(object.class);
+        self.out.push(' ');
+        self.block(&object.fields, |w, field| w.field(field));
+    }
+
+    fn field(&mut self, field: &BinField) {
+        self.name(field.hash);
+        let text = field.value.type_expr();
+        write!(self.out, ": {} = ", text).unwrap();
+        self.value(&field.value);
+    }
+
+    fn value(&mut self, value: &BinValue) {
+        match value {
+            BinValue::None => self.out.push_str("null"),
+            BinValue::Bool(x) | BinValue::Flag(x) => write!(self.out, "{}", x).unwrap(),
+            BinValue::I8(x) => write!(self.out, "{}", x).unwrap(),
+            BinValue::U8(x) => write!(self.out, "{}", x).unwrap(),
+            BinValue::I16(x) => write!(self.out, "{}", x).unwrap(),
+            BinValue::U16(x) => write!(self.out, "{}", x).unwrap(),
+            BinValue::I32(x) => write!(self.out, "{}", x).unwrap(),
+            BinValue::U32(x) => write!(self.out, "{}", x).unwrap(),
+            BinValue::I64(x) => write!(self.out, "{}", x).unwrap(),
+            BinValue::U64(x) => write!(self.out, "{}", x).unwrap(),
+            BinValue::F32(x) => write!(self.out, "{:?}", x).unwrap(),
+            BinValue::Vec2(x) => self.floats(x),
+            BinValue::Vec3(x) => self.floats(x),
+            BinValue::Vec4(x) => self.floats(x),
+            BinValue::Mtx44(x) => {
+                let rows = x.chunks(4).collect::<Vec<_>>();
+                self.block(&rows, |w, row| {
+                    let items = row.iter().map(|x| format!("{:?}", x)).collect::<Vec<_>>();
+                    w.out.push_str(&items.join(", "));
+                });
+            }
+            BinValue::Color(x) => {
+                write!(self.out, "{{ {}, {}, {}, {} }}", x[0], x[1], x[2], x[3]).unwrap()
+            }
+            BinValue::String(x) => write_string(&mut self.out, x),
+            BinValue::Hash(x) | BinValue::Link(x) => self.hash(*x),
+            BinValue::File(x) => write!(self.out, "{:#018x}", x).unwrap(),
+            BinValue::List(_, items) | BinValue::List2(_, items) => {
+                self.block(items, |w, item| w.value(item))
+            }
+            BinValue::Pointer(None) => self.out.push_str("null"),
+            BinValue::Pointer(Some(object)) | BinValue::Embed(object) => self.object(object),
+            BinValue::Option(_, item) => {
+                let items = item.iter().collect::<Vec<_>>();
+                self.block(&items, |w, item| w.value(item));
+            }
+            BinValue::Map(_, _, items) => self.block(items, |w, (key, value)| {
+                w.value(key);
+                w.out.push_str(" = ");
+                w.value(value);
+            }),
+        }
+    }
+}
+
+/// Prints `bin` in the ritobin text format with explicit types on every field.
+pub fn write_text(bin: &BinFile, names: &HashNames) -> String {
+    let mut w = TextWriter {
+        names,
+        out: String::new(),
+        indent: 0,
+    };
+    w.out.push_str("#PROP_text\n");
+    match bin.patch_header {
+        Some(header) => {
+            w.out.push_str("type: string = \"PTCH\"\n");
+            if header != 1 {
+                writeln!(w.out, "patch_header: u64 = {}", header).unwrap();
+            }
+        }
+        None => w.out.push_str("type: string = \"PROP\"\n"),
+    }
+    writeln!(w.out, "version: u32 = {}", bin.version).unwrap();
+    w.out.push_str("linked: list[string] = ");
+    w.block(&bin.linked, |w, linked| write_string(&mut w.out, linked));
+    w.out.push_str("\nentries: map[hash,embed] = ");
+    w.block(&bin.entries, |w, entry| {
+        w.hash(entry.path);
+        w.out.push_str(" = ");
+        w.object(&entry.object);
+    });
+    w.out.push('\n');
+    if bin.patch_header.is_some() {
+        w.out.push_str("patches: map[hash,embed] = ");
+        w.block(&bin.patches, |w, patch| {
+            w.hash(patch.entry);
+            w.out.push_str(" = patch {\n");
+            w.indent += 1;
+            w.pad();
+            w.out.push_str("path: string = ");
+            write_string(&mut w.out, &patch.path);
+            w.out.push('\n');
+            w.pad();
+            let text = patch.value.type_expr();
+            write!(w.out, "value: {} = ", text).unwrap();
+            w.value(&patch.value);
+            w.out.push('\n');
+            w.indent -= 1;
+            w.pad();
+            w.out.push('}');
+        });
+        w.out.push('\n');
+    }
+    w.out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    String(String),
+    Punct(char),
+}
+
+fn tokenize(text: &str) -> io::Result<Vec<(Token, usize)>> {
+    let mut results = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '#' => {
+                while let Some(&x) = chars.peek() {
+                    if x == '\n' {
+                        break;
+                    }
+                    chars.next();
+                }
+            }
+            '{' | '}' | '[' | ']' | ',' | '=' | ':' => results.push((Token::Punct(c), line)),
+            '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(x) if x == c => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => value.push('\n'),
+                            Some('r') => value.push('\r'),
+                            Some('t') => value.push('\t'),
+                            Some('x') => {
+                                let digits = chars.by_ref().take(2).collect::<String>();
+                                let code = u32::from_str_radix(&digits, 16)
+                                    .map_err(|_| syntax(line, "invalid \\x escape"))?;
+                                value.push(char::from_u32(code).unwrap());
+                            }
+                            Some(x) => value.push(x),
+                            None => return Err(syntax(line, "unterminated string")),
+                        },
+                        Some('\n') | None => return Err(syntax(line, "unterminated string")),
+                        Some(x) => value.push(x),
+                    }
+                }
+                results.push((Token::String(value), line));
+            }
+            c => {
+                let mut value = c.to_string();
+                while let Some(&x) = chars.peek() {
+                    if x.is_whitespace() || "{}[],=:\"'#".contains(x) {
+                        break;
+                    }
+                    value.push(x);
+                    chars.next();
+                }
+                results.push((Token::Word(value), line));
+            }
+        }
+    }
+    Ok(results)
+}
+
+fn syntax(line: usize, message: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line, message),
+    )
+}
+
+struct TextReader<'a> {
+    meta: &'a MetaFile,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl<'a> TextReader<'a> {
+    fn line(&self) -> usize {
+        match self.tokens.get(self.pos).or_else(|| self.tokens.last()) {
+            Some((_, line)) => *line,
+            None => 1,
+        }
+    }
+
+    fn error<T>(&self, message: impl std::fmt::Display) -> io::Result<T> {
+        Err(syntax(self.line(), message))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn next(&mut self) -> io::Result<Token> {
+        match self.tokens.get(self.pos) {
+            Some((token, _)) => {
+                self.pos += 1;
+                Ok(token.clone())
+            }
+            None => self.error("unexpected end of file"),
+        }
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punct(c))
+    }
+
+    fn punct(&mut self, c: char) -> io::Result<()> {
+        match self.next()? {
+            Token::Punct(x) if x == c => Ok(()),
+            token => self.error(format!("expected {:?} found {:?}", c, token)),
+        }
+    }
+
+    fn word(&mut self) -> io::Result<String> {
+        match self.next()? {
+            Token::Word(x) => Ok(x),
+            token => self.error(format!("expected word found {:?}", token)),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> io::Result<T> {
+        let word = self.word()?;
+        match word.parse() {
+            Ok(value) => Ok(value),
+            Err(_) => self.error(format!("invalid number {:?}", word)),
+        }
+    }
+
+    fn floats<const N: usize>(&mut self) -> io::Result<[f32; N]> {
+        self.punct('{')?;
+        let mut result = [0.0; N];
+        for (index, item) in result.iter_mut().enumerate() {
+            if index != 0 && self.is_punct(',') {
+                self.punct(',')?;
+            }
+            *item = self.number()?;
+        }
+        self.punct('}')?;
+        Ok(result)
+    }
+
+    /// Names are either `0x` hashes or hashed with FNV-1a.
+    fn name(&mut self) -> io::Result<u32> {
+        let word = self.word()?;
+        Ok(match word.strip_prefix("0x") {
+            Some(hex) => match u32::from_str_radix(hex, 16) {
+                Ok(hash) => hash,
+                Err(_) => return self.error(format!("invalid hash {:?}", word)),
+            },
+            None => fnv1a(&word),
+        })
+    }
+
+    fn hash(&mut self) -> io::Result<u32> {
+        match self.peek() {
+            Some(Token::String(_)) => match self.next()? {
+                Token::String(text) => Ok(fnv1a(&text)),
+                _ => unreachable!(),
+            },
+            _ => self.name(),
+        }
+    }
+
+    fn file(&mut self) -> io::Result<u64> {
+        match self.next()? {
+            Token::String(text) => Ok(xxh64(&text)),
+            Token::Word(word) => match word.strip_prefix("0x") {
+                Some(hex) => match u64::from_str_radix(hex, 16) {
+                    Ok(hash) => Ok(hash),
+                    Err(_) => self.error(format!("invalid hash {:?}", word)),
+                },
+                None => self.error(format!("invalid file {:?}", word)),
+            },
+            token => self.error(format!("expected file found {:?}", token)),
+        }
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        match self.next()? {
+            Token::String(text) => Ok(text),
+            token => self.error(format!("expected string found {:?}", token)),
+        }
+    }
+
+    fn bool(&mut self) -> io::Result<bool> {
+        match self.word()?.as_str() {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            word => self.error(format!("expected bool found {:?}", word)),
+        }
+    }
+
+    /// Reads a type such as `map[hash,embed]` or `pointer<Foo>` up to the `=`.
+    fn text_type(&mut self) -> io::Result<TypeExpr> {
+        let mut text = String::new();
+        while !self.is_punct('=') {
+            match self.next()? {
+                Token::Word(word) => text.push_str(&word),
+                Token::Punct(c @ ('[' | ']' | ',')) => text.push(c),
+                token => return self.error(format!("unexpected {:?} in type", token)),
+            }
+        }
+        match text.parse() {
+            Ok(ty) => Ok(ty),
+            Err(message) => self.error(format!("invalid type {:?}: {}", text, message)),
+        }
+    }
+
+    /// Reads `{ item item ... }`, commas between items are optional.
+    fn block<T>(&mut self, mut read: impl FnMut(&mut Self) -> io::Result<T>) -> io::Result<Vec<T>> {
+        self.punct('{')?;
+        let mut results = Vec::new();
+        while !self.is_punct('}') {
+            results.push(read(self)?);
+            if self.is_punct(',') {
+                self.punct(',')?;
+            }
+        }
+        self.punct('}')?;
+        Ok(results)
+    }
+
+    fn object(&mut self, class: u32) -> io::Result<BinObject> {
+        let fields = self.block(|r| r.field(class))?;
+        Ok(BinObject { class, fields })
+    }
+
+    /// Fields of classes missing from the dump, as in bins newer than it,
+    /// keep the type written next to them.
+    fn field(&mut self, class: u32) -> io::Result<BinField> {
+        let hash = self.name()?;
+        let expected = self
+            .meta
+            .find_property(class, hash)
+            .map(|(_, _, info)| info.type_expr().without_classes());
+        let found = if self.is_punct(':') {
+            self.punct(':')?;
+            Some(self.text_type()?.without_classes())
+        } else {
+            None
+        };
+        let ty = match (expected, found) {
+            (Some(expected), Some(found)) if found != expected => {
+                return self.error(format!("expected {} found {}", expected, found))
+            }
+            (Some(ty), _) => ty,
+            (None, _) if self.meta.class(class).is_some() => {
+                return self.error(format!(
+                    "unknown property {:#010x} in class {:#010x}",
+                    hash, class
+                ))
+            }
+            (None, Some(ty)) => ty,
+            (None, None) => {
+                return self.error(format!(
+                    "property {:#010x} of class {:#010x} is not in the dump and needs a type",
+                    hash, class
+                ))
+            }
+        };
+        self.punct('=')?;
+        let value = self.value(&ty)?;
+        Ok(BinField { hash, value })
+    }
+
+    fn value(&mut self, ty: &TypeExpr) -> io::Result<BinValue> {
+        let none = TypeExpr::nestable(BinType::None, None);
+        let item = ty.item().unwrap_or(&none);
+        let key = ty.key().unwrap_or(&none);
+        Ok(match ty.value_type() {
+            BinType::None => {
+                self.word()?;
+                BinValue::None
+            }
+            BinType::Bool => BinValue::Bool(self.bool()?),
+            BinType::I8 => BinValue::I8(self.number()?),
+            BinType::U8 => BinValue::U8(self.number()?),
+            BinType::I16 => BinValue::I16(self.number()?),
+            BinType::U16 => BinValue::U16(self.number()?),
+            BinType::I32 => BinValue::I32(self.number()?),
+            BinType::U32 => BinValue::U32(self.number()?),
+            BinType::I64 => BinValue::I64(self.number()?),
+            BinType::U64 => BinValue::U64(self.number()?),
+            BinType::F32 => BinValue::F32(self.number()?),
+            BinType::Vec2 => BinValue::Vec2(self.floats()?),
+            BinType::Vec3 => BinValue::Vec3(self.floats()?),
+            BinType::Vec4 => BinValue::Vec4(self.floats()?),
+            BinType::Mtx44 => BinValue::Mtx44(self.floats()?),
+            BinType::Color => {
+                let items = self.block(|r| r.number::<u8>())?;
+                match items.try_into() {
+                    Ok(items) => BinValue::Color(items),
+                    Err(_) => return self.error("rgba needs 4 components"),
+                }
+            }
+            BinType::String => BinValue::String(self.string()?),
+            BinType::Hash => BinValue::Hash(self.hash()?),
+            BinType::File => BinValue::File(self.file()?),
+            BinType::Link => BinValue::Link(self.hash()?),
+            BinType::Flag => BinValue::Flag(self.bool()?),
+            BinType::List => BinValue::List(item.value_type(), self.block(|r| r.value(item))?),
+            BinType::List2 => BinValue::List2(item.value_type(), self.block(|r| r.value(item))?),
+            BinType::Option => {
+                let mut items = self.block(|r| r.value(item))?;
+                if items.len() > 1 {
+                    return self.error("option holds at most one value");
+                }
+                BinValue::Option(item.value_type(), items.pop().map(Box::new))
+            }
+            BinType::Map => {
+                let items = self.block(|r| {
+                    let key = r.value(key)?;
+                    r.punct('=')?;
+                    Ok((key, r.value(item)?))
+                })?;
+                BinValue::Map(key.value_type(), item.value_type(), items)
+            }
+            BinType::Pointer => {
+                if self.peek() == Some(&Token::Word("null".into())) {
+                    self.next()?;
+                    BinValue::Pointer(None)
+                } else {
+                    let class = self.name()?;
+                    BinValue::Pointer(Some(self.object(class)?))
+                }
+            }
+            BinType::Embed => {
+                let class = self.name()?;
+                BinValue::Embed(self.object(class)?)
+            }
+        })
+    }
+
+    fn entry(&mut self) -> io::Result<BinEntry> {
+        let path = self.hash()?;
+        self.punct('=')?;
+        let class = self.name()?;
+        let object = self.object(class)?;
+        Ok(BinEntry { path, object })
+    }
+
+    fn patch(&mut self) -> io::Result<BinPatch> {
+        let entry = self.hash()?;
+        self.punct('=')?;
+        if self.word()? != "patch" {
+            return self.error("expected patch");
+        }
+        let mut path = None;
+        let mut value = None;
+        self.block(|r| {
+            match r.word()?.as_str() {
+                "path" => {
+                    if r.is_punct(':') {
+                        r.punct(':')?;
+                        r.text_type()?;
+                    }
+                    r.punct('=')?;
+                    path = Some(r.string()?);
+                }
+                "value" => {
+                    // Patch paths are only resolved against the base bin, so
+                    // the type can not be inferred here.
+                    r.punct(':')?;
+                    let ty = r.text_type()?;
+                    r.punct('=')?;
+                    value = Some(r.value(&ty)?);
+                }
+                word => return r.error(format!("unknown patch field {:?}", word)),
+            }
+            Ok(())
+        })?;
+        match (path, value) {
+            (Some(path), Some(value)) => Ok(BinPatch { entry, path, value }),
+            _ => self.error("patch needs path and value"),
+        }
+    }
+
+    fn file_contents(&mut self) -> io::Result<BinFile> {
+        let mut result = BinFile {
+            version: 3,
+            patch_header: None,
+            linked: Vec::new(),
+            entries: Vec::new(),
+            patches: Vec::new(),
+        };
+        let mut patch_header = 1;
+        let mut is_patch = false;
+        while self.peek().is_some() {
+            let key = self.word()?;
+            let expected = match key.as_str() {
+                "type" => TypeExpr::nestable(BinType::String, None),
+                "version" => TypeExpr::nestable(BinType::U32, None),
+                "patch_header" => TypeExpr::nestable(BinType::U64, None),
+                "linked" => TypeExpr::new(BinType::List, None, Some(BinType::String), None),
+                "entries" | "patches" => TypeExpr::new(
+                    BinType::Map,
+                    Some(BinType::Hash),
+                    Some(BinType::Embed),
+                    None,
+                ),
+                _ => return self.error(format!("unknown section {:?}", key)),
+            };
+            if self.is_punct(':') {
+                self.punct(':')?;
+                let found = self.text_type()?.without_classes();
+                if found != expected {
+                    return self.error(format!("{} must be {}", key, expected));
+                }
+            }
+            self.punct('=')?;
+            match key.as_str() {
+                "type" => match self.string()?.as_str() {
+                    "PROP" => is_patch = false,
+                    "PTCH" => is_patch = true,
+                    text => return self.error(format!("unknown type {:?}", text)),
+                },
+                "version" => result.version = self.number()?,
+                "patch_header" => patch_header = self.number()?,
+                "linked" => result.linked = self.block(|r| r.string())?,
+                "entries" => result.entries = self.block(|r| r.entry())?,
+                _ => result.patches = self.block(|r| r.patch())?,
+            }
+        }
+        if is_patch {
+            result.patch_header = Some(patch_header);
+        }
+        Ok(result)
+    }
+}
+
+/// Parses the ritobin text format. Field types may be omitted and are then
+/// taken from `meta`, fields missing from the dump need their type written.
+pub fn read_text(meta: &MetaFile, text: &str) -> io::Result<BinFile> {
+    let mut reader = TextReader {
+        meta,
+        tokens: tokenize(text)?,
+        pos: 0,
+    };
+    reader.file_contents()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bin::{read_bin, write_bin};
+    use crate::schema::testing::{class, container, meta, property};
+
+    fn object(class: u32, fields: Vec<(u32, BinValue)>) -> BinObject {
+        BinObject {
+            class,
+            fields: fields
+                .into_iter()
+                .map(|(hash, value)| BinField { hash, value })
+                .collect(),
+        }
+    }
+
+    /// One field of every type in a class missing from the dump, with
+    /// classes and names both known and unknown.
+    fn every_type() -> BinObject {
+        let floats = [0.1, -0.0, f32::NAN, f32::INFINITY, f32::MIN_POSITIVE, 1e30];
+        let inner = object(
+            fnv1a("Foo"),
+            vec![
+                (fnv1a("mName"), BinValue::String("inner".into())),
+                (0x99, BinValue::List(BinType::I8, vec![BinValue::I8(-1)])),
+            ],
+        );
+        let unknown = object(0xabcdef01, vec![(0x5, BinValue::Link(0xdeadbeef))]);
+        let values = vec![
+            BinValue::None,
+            BinValue::Bool(true),
+            BinValue::I8(i8::MIN),
+            BinValue::U8(u8::MAX),
+            BinValue::I16(i16::MIN),
+            BinValue::U16(u16::MAX),
+            BinValue::I32(i32::MIN),
+            BinValue::U32(u32::MAX),
+            BinValue::I64(i64::MIN),
+            BinValue::U64(u64::MAX),
+            BinValue::F32(f32::NEG_INFINITY),
+            BinValue::Vec2([floats[0], floats[1]]),
+            BinValue::Vec3([floats[2], floats[3], floats[4]]),
+            BinValue::Vec4([floats[5], 1.5, -2.25, 3.0e-39]),
+            BinValue::Mtx44(std::array::from_fn(|i| i as f32 / 3.0)),
+            BinValue::Color([1, 2, 3, 255]),
+            BinValue::String("quote \" slash \\ tab \t line \n bell \x07 é".into()),
+            BinValue::Hash(fnv1a("Annie")),
+            BinValue::Hash(0xdeadbeef),
+            BinValue::File(0x0123456789abcdef),
+            BinValue::List(
+                BinType::F32,
+                floats.iter().map(|&x| BinValue::F32(x)).collect(),
+            ),
+            BinValue::List2(
+                BinType::Embed,
+                vec![
+                    BinValue::Embed(inner.clone()),
+                    BinValue::Embed(unknown.clone()),
+                ],
+            ),
+            BinValue::List(BinType::String, Vec::new()),
+            BinValue::Pointer(None),
+            BinValue::Pointer(Some(unknown.clone())),
+            BinValue::Embed(inner.clone()),
+            BinValue::Link(fnv1a("Annie")),
+            BinValue::Option(
+                BinType::Vec3,
+                Some(Box::new(BinValue::Vec3([1.0, 2.0, 3.0]))),
+            ),
+            BinValue::Option(BinType::Pointer, None),
+            BinValue::Map(
+                BinType::Hash,
+                BinType::Pointer,
+                vec![
+                    (BinValue::Hash(0x1), BinValue::Pointer(Some(inner))),
+                    (BinValue::Hash(fnv1a("Annie")), BinValue::Pointer(None)),
+                ],
+            ),
+            BinValue::Map(
+                BinType::U64,
+                BinType::Embed,
+                vec![(BinValue::U64(7), BinValue::Embed(unknown))],
+            ),
+            BinValue::Flag(false),
+        ];
+        object(
+            0x30,
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(index, value)| (0x100 + index as u32, value))
+                .collect(),
+        )
+    }
+
+    fn sample_meta() -> MetaFile {
+        meta(vec![(
+            fnv1a("Foo"),
+            class(
+                None,
+                16,
+                vec![
+                    (fnv1a("mName"), property(0, BinType::String, None)),
+                    (0x99, container(8, BinType::List, BinType::I8, None)),
+                ],
+            ),
+        )])
+    }
+
+    fn sample_names() -> HashNames {
+        let mut names = HashNames::new();
+        for name in ["Foo", "mName", "Annie"] {
+            names.insert(fnv1a(name), name);
+        }
+        names
+    }
+
+    fn assert_round_trip(bin: &BinFile) {
+        let data = write_bin(bin).unwrap();
+        let text = write_text(&read_bin(&data).unwrap(), &sample_names());
+        let parsed = read_text(&sample_meta(), &text).unwrap_or_else(|e| panic!("{}\n{}", e, text));
+        assert_eq!(write_bin(&parsed).unwrap(), data, "{}", text);
+        assert_eq!(write_text(&parsed, &sample_names()), text);
+    }
+
+    #[test]
+    fn every_type_round_trips() {
+        assert_round_trip(&BinFile {
+            version: 3,
+            patch_header: None,
+            linked: vec!["Shared.bin".into(), "Other \"quoted\".bin".into()],
+            entries: vec![
+                BinEntry {
+                    path: fnv1a("Annie"),
+                    object: every_type(),
+                },
+                BinEntry {
+                    path: 0x12345678,
+                    object: object(0x77, Vec::new()),
+                },
+            ],
+            patches: Vec::new(),
+        });
+    }
+
+    #[test]
+    fn patches_round_trip() {
+        for patch_header in [1, 2] {
+            assert_round_trip(&BinFile {
+                version: 3,
+                patch_header: Some(patch_header),
+                linked: Vec::new(),
+                entries: Vec::new(),
+                patches: vec![
+                    BinPatch {
+                        entry: fnv1a("Annie"),
+                        path: "mItems.mName".into(),
+                        value: BinValue::Map(
+                            BinType::String,
+                            BinType::Vec2,
+                            vec![(BinValue::String("a".into()), BinValue::Vec2([1.0, -1.0]))],
+                        ),
+                    },
+                    BinPatch {
+                        entry: 0xdeadbeef,
+                        path: String::new(),
+                        value: BinValue::Embed(every_type()),
+                    },
+                ],
+            });
+        }
+    }
+
+    #[test]
+    fn fields_are_checked_against_the_dump() {
+        let text = "entries: map[hash,embed] = { 0x1 = Foo { mName: pointer<Foo> = null } }";
+        let error = read_text(&sample_meta(), text).unwrap_err().to_string();
+        assert!(error.contains("expected string found pointer"), "{}", error);
+
+        let text =
+            "entries: map[hash,embed] = { 0x1 = Foo { mName = \"x\", 0x98: list[i8] = { 1 } } }";
+        let error = read_text(&sample_meta(), text).unwrap_err().to_string();
+        assert!(error.contains("unknown property 0x00000098"), "{}", error);
+
+        let text = "entries: map[hash,embed] = { 0x1 = Bar { 0x42 = 1 } }";
+        let error = read_text(&sample_meta(), text).unwrap_err().to_string();
+        assert!(error.contains("needs a type"), "{}", error);
+
+        let text = "entries: map[hash,embed] = { 0x1 = Bar { 0x42: pointer<Bar> = null } }";
+        let bin = read_text(&sample_meta(), text).unwrap();
+        let expected = object(fnv1a("Bar"), vec![(0x42, BinValue::Pointer(None))]);
+        assert_eq!(bin.entries[0].object, expected);
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::hashes::{fnv1a, fnv1a_extend, HashNames};
+use crate::name_recovery::HashKind;
+use crate::schema::MetaFile;
+
+/// Prefixes tried before the joined words, `m` being the member prefix.
+pub const PREFIXES: [&str; 2] = ["", "m"];
+
+#[derive(Debug, Clone)]
+pub struct BruteForce {
+    /// Distinct ignoring case, since the hash lowercases anyway.
+    pub words: Vec<String>,
+    pub max_words: usize,
+    /// Suffixes `0` to `max_suffix` are tried after every join, `None` for none.
+    pub max_suffix: Option<u32>,
+    pub threads: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BruteHit {
+    pub hash: u32,
+    pub name: String,
+    pub prefix: &'static str,
+    pub words: Vec<String>,
+    pub suffix: Option<u32>,
+}
+
+impl BruteHit {
+    /// How the name was built, like `m + Spell + Name + 2`.
+    pub fn provenance(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        if !self.prefix.is_empty() {
+            parts.push(self.prefix.to_string());
+        }
+        parts.extend(self.words.iter().cloned());
+        if let Some(suffix) = self.suffix {
+            parts.push(suffix.to_string());
+        }
+        parts.join(" + ")
+    }
+}
+
+/// Uppercases the first letter, the rest is kept so `HP` stays as is.
+pub fn camel_case(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Splits `mSpellDataHP` into `Spell`, `Data` and `HP`, dropping the member prefix.
+pub fn split_camel_case(name: &str) -> Vec<String> {
+    let name = match name.strip_prefix('m') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_uppercase()) => rest,
+        _ => name,
+    };
+    let chars: Vec<char> = name.chars().collect();
+    let mut results = Vec::new();
+    let mut word = String::new();
+    for (index, &c) in chars.iter().enumerate() {
+        let next_lower = chars.get(index + 1).is_some_and(|x| x.is_ascii_lowercase());
+        let prev_lower = index > 0 && !chars[index - 1].is_ascii_uppercase();
+        let starts_word = c.is_ascii_uppercase() && (prev_lower || next_lower);
+        if !c.is_ascii_alphanumeric() || (starts_word && !word.is_empty()) {
+            results.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+        }
+        if c.is_ascii_alphanumeric() {
+            word.push(c);
+        }
+    }
+    results.extend((!word.is_empty()).then_some(word));
+    results
+}
+
+/// Words of the class and property names already resolved in `names`.
+pub fn name_fragments(meta: &MetaFile, names: &HashNames) -> BTreeSet<String> {
+    let mut results = BTreeSet::new();
+    for (&class, info) in &meta.classes {
+        let hashes = std::iter::once(class).chain(info.properties.keys().copied());
+        for name in hashes.filter_map(|x| names.get(x)) {
+            results.extend(split_camel_case(name));
+        }
+    }
+    results
+}
+
+/// Words keeping the first spelling of each, CamelCased, without empty ones.
+pub fn normalize_words(words: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let mut results = Vec::new();
+    for word in words {
+        let word = camel_case(word.trim());
+        if !word.is_empty() && seen.insert(word.to_ascii_lowercase()) {
+            results.push(word);
+        }
+    }
+    results
+}
+
+struct Search<'a> {
+    config: &'a BruteForce,
+    targets: &'a BTreeMap<u32, HashKind>,
+    /// Suffix number and text, tried after the joined words.
+    suffixes: Vec<(Option<u32>, String)>,
+}
+
+impl<'a> Search<'a> {
+    /// Tries every name of `unit`, calling `hit` for each match.
+    fn unit(&self, unit: usize, hit: &mut dyn FnMut(BruteHit)) {
+        let count = self.config.words.len();
+        let (length, first) = (unit / count + 1, unit % count);
+        for prefix in PREFIXES {
+            let hash = fnv1a_extend(fnv1a(prefix), &self.config.words[first]);
+            let mut words = vec![first];
+            self.extend(hash, prefix, &mut words, length, hit);
+        }
+    }
+
+    fn extend(
+        &self,
+        hash: u32,
+        prefix: &'static str,
+        words: &mut Vec<usize>,
+        length: usize,
+        hit: &mut dyn FnMut(BruteHit),
+    ) {
+        if words.len() < length {
+            for index in 0..self.config.words.len() {
+                words.push(index);
+                let hash = fnv1a_extend(hash, &self.config.words[index]);
+                self.extend(hash, prefix, words, length, hit);
+                words.pop();
+            }
+            return;
+        }
+        for (suffix, text) in &self.suffixes {
+            let hash = fnv1a_extend(hash, text);
+            if self.targets.contains_key(&hash) {
+                let words: Vec<String> = words
+                    .iter()
+                    .map(|&x| self.config.words[x].clone())
+                    .collect();
+                hit(BruteHit {
+                    hash,
+                    name: format!("{}{}{}", prefix, words.concat(), text),
+                    prefix,
+                    words,
+                    suffix: *suffix,
+                });
+            }
+        }
+    }
+}
+
+impl BruteForce {
+    /// Units to work through, one per word count and first word. Progress is
+    /// saved as the number of leading units done.
+    pub fn units(&self) -> usize {
+        self.max_words * self.words.len()
+    }
+
+    /// Identifies the search, so progress only carries over to runs with the
+    /// same words in the same order and the same limits.
+    pub fn fingerprint(&self) -> u64 {
+        let text = format!(
+            "{}\n{} {:?}",
+            self.words.join("\n"),
+            self.max_words,
+            self.max_suffix
+        );
+        xxhash_rust::xxh64::xxh64(text.as_bytes(), 0)
+    }
+
+    /// Progress line saved after `done` leading units, `<done> <units> <fingerprint>`.
+    pub fn progress(&self, done: usize) -> String {
+        format!("{} {} {:016x}\n", done, self.units(), self.fingerprint())
+    }
+
+    /// Units already done according to a saved progress line, `None` if it
+    /// belongs to another search.
+    pub fn resume_at(&self, progress: &str) -> Option<usize> {
+        match progress.split_whitespace().collect::<Vec<_>>()[..] {
+            [done, units, fingerprint]
+                if units == self.units().to_string()
+                    && fingerprint == format!("{:016x}", self.fingerprint()) =>
+            {
+                done.parse().ok().filter(|&done| done <= self.units())
+            }
+            _ => None,
+        }
+    }
+
+    /// Tries every join of up to `max_words` words on all threads, starting at
+    /// unit `start`. `hit` is called for each match and `done` with the new
+    /// count of leading units finished, both from the worker threads.
+    pub fn run(
+        &self,
+        targets: &BTreeMap<u32, HashKind>,
+        start: usize,
+        hit: &(dyn Fn(BruteHit) + Sync),
+        done: &(dyn Fn(usize) + Sync),
+    ) {
+        let mut suffixes = vec![(None, String::new())];
+        if let Some(max) = self.max_suffix {
+            suffixes.extend((0..=max).map(|x| (Some(x), x.to_string())));
+        }
+        let search = Search {
+            config: self,
+            targets,
+            suffixes,
+        };
+        let next = AtomicUsize::new(start);
+        // Finished units past the leading ones, so progress only moves forward
+        // once every earlier unit is done too.
+        let progress = Mutex::new((start, BTreeSet::new()));
+        thread::scope(|scope| {
+            for _ in 0..self.threads.max(1) {
+                scope.spawn(|| loop {
+                    let unit = next.fetch_add(1, Ordering::Relaxed);
+                    if unit >= self.units() {
+                        break;
+                    }
+                    search.unit(unit, &mut |x| hit(x));
+                    let mut progress = progress.lock().unwrap();
+                    let (leading, finished) = &mut *progress;
+                    finished.insert(unit);
+                    let before = *leading;
+                    while finished.remove(leading) {
+                        *leading += 1;
+                    }
+                    if *leading != before {
+                        done(*leading);
+                    }
+                });
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(words: &[&str], max_words: usize, max_suffix: Option<u32>) -> BruteForce {
+        BruteForce {
+            words: words.iter().map(|x| x.to_string()).collect(),
+            max_words,
+            max_suffix,
+            threads: 1,
+        }
+    }
+
+    #[test]
+    fn progress_resumes_the_same_search_only() {
+        let base = config(&["Spell", "Name"], 2, Some(9));
+        assert_eq!(base.resume_at(&base.progress(3)), Some(3));
+        let threads = BruteForce {
+            threads: 8,
+            ..base.clone()
+        };
+        assert_eq!(threads.resume_at(&base.progress(3)), Some(3));
+        for other in [
+            config(&["Name", "Spell"], 2, Some(9)),
+            config(&["Spell", "Data"], 2, Some(9)),
+            config(&["Spell", "Name"], 2, Some(5)),
+            config(&["Spell", "Name"], 2, None),
+            config(&["Spell", "Name", "Data", "Hp"], 1, Some(9)),
+        ] {
+            assert_eq!(other.resume_at(&base.progress(3)), None, "{:?}", other);
+        }
+        assert_eq!(base.resume_at("3 4"), None);
+        assert_eq!(base.resume_at(&base.progress(9)), None);
+    }
+
+    #[test]
+    fn every_join_is_found() {
+        let search = config(&["Spell", "Name"], 2, Some(2));
+        let targets = ["mSpellName", "NameSpell2", "Name"]
+            .iter()
+            .map(|x| (fnv1a(x), HashKind::Property))
+            .collect();
+        let hits = Mutex::new(Vec::new());
+        let done = Mutex::new(Vec::new());
+        search.run(&targets, 0, &|x| hits.lock().unwrap().push(x.name), &|x| {
+            done.lock().unwrap().push(x)
+        });
+        let mut hits = hits.into_inner().unwrap();
+        hits.sort();
+        assert_eq!(hits, ["Name", "NameSpell2", "mSpellName"]);
+        assert_eq!(done.into_inner().unwrap(), [1, 2, 3, 4]);
+    }
+}
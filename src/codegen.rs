@@ -0,0 +1,115 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+use crate::hashes::{parse_hex, HashNames};
+use crate::schema::{MetaFile, PropertyInfo};
+use crate::types::BinType;
+
+/// Replaces everything that is not valid in an identifier with `_`.
+pub fn ident(text: &str) -> String {
+    let mut result: String = text
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    result
+}
+
+pub fn class_ident(names: &HashNames, hash: u32) -> String {
+    match names.get(hash) {
+        Some(name) => ident(name),
+        None => format!("Class_{:08x}", hash),
+    }
+}
+
+pub fn field_ident(names: &HashNames, hash: u32) -> String {
+    match names.get(hash) {
+        Some(name) => ident(name),
+        None => format!("field_{:08x}", hash),
+    }
+}
+
+/// Appends `_` to `name` when it is in one of `taken`, the target language's
+/// keywords or the names its generated prelude already declares.
+pub fn escape_ident(mut name: String, taken: &[&[&str]]) -> String {
+    if taken.iter().any(|names| names.contains(&name.as_str())) {
+        name.push('_');
+    }
+    name
+}
+
+/// Key used for `hash` in bin JSON: the name when known, the 0x hash otherwise.
+pub fn json_key(names: &HashNames, hash: u32) -> String {
+    match names.get(hash) {
+        Some(name) => name.to_string(),
+        None => format!("{:#x}", hash),
+    }
+}
+
+/// Class stored by value inside `property`, directly or as container items.
+pub fn embedded_class(property: &PropertyInfo) -> Option<u32> {
+    let embeds = property.value_type == BinType::Embed
+        || matches!(&property.container, Some(c) if c.value_type == BinType::Embed)
+        || matches!(&property.map, Some(m) if m.value_type == BinType::Embed);
+    if embeds {
+        property.other_class
+    } else {
+        None
+    }
+}
+
+/// All classes ordered so that bases and embedded classes come before their users.
+pub fn class_order(meta: &MetaFile) -> Vec<u32> {
+    let mut results = Vec::new();
+    let mut visited = BTreeSet::new();
+    for &hash in meta.classes.keys() {
+        visit_class(meta, hash, &mut visited, &mut results);
+    }
+    results
+}
+
+fn visit_class(meta: &MetaFile, hash: u32, visited: &mut BTreeSet<u32>, results: &mut Vec<u32>) {
+    let class = match meta.class(hash) {
+        Some(class) => class,
+        None => return,
+    };
+    if !visited.insert(hash) {
+        return;
+    }
+    for (base, _) in meta.direct_bases(hash) {
+        visit_class(meta, base, visited, results);
+    }
+    for property in class.properties.values() {
+        if let Some(other) = embedded_class(property) {
+            visit_class(meta, other, visited, results);
+        }
+    }
+    results.push(hash);
+}
+
+/// Floats of a dumped vector or matrix default, matrices flattened row by row.
+pub fn default_floats(value: &Value, count: usize) -> Option<Vec<f32>> {
+    let items = value
+        .as_array()?
+        .iter()
+        .flat_map(|x| x.as_array().cloned().unwrap_or_else(|| vec![x.clone()]))
+        .map(|x| x.as_f64().map(|x| x as f32))
+        .collect::<Option<Vec<_>>>()?;
+    (items.len() == count).then_some(items)
+}
+
+pub fn default_color(value: &Value) -> Option<[u8; 4]> {
+    let items = value
+        .as_array()?
+        .iter()
+        .map(|x| x.as_u64().map(|x| x as u8))
+        .collect::<Option<Vec<_>>>()?;
+    items.try_into().ok()
+}
+
+pub fn default_hash(value: &Value) -> Option<u64> {
+    parse_hex(value.as_str()?)
+}
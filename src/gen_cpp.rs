@@ -0,0 +1,488 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::codegen::{class_ident, class_order, field_ident};
+use crate::hashes::HashNames;
+use crate::schema::{ClassInfo, MetaFile, PropertyInfo};
+use crate::types::{BinType, ContainerStorage};
+
+const PRELUDE: &str = r#"#pragma once
+#include <cstddef>
+#include <cstdint>
+
+namespace meta {
+struct Vector2 { float x, y; };
+struct Vector3 { float x, y, z; };
+struct Vector4 { float x, y, z, w; };
+struct Matrix44 { float m[4][4]; };
+struct Color { std::uint8_t r, g, b, a; };
+struct String { char const* data; std::uint32_t size; std::uint32_t capacity; };
+template <typename T> struct RiotVector { T* data; std::uint32_t size; std::uint32_t capacity; };
+template <typename T> struct StdVector { T* begin; T* end; T* capacity; };
+template <typename T> struct Link { std::uint32_t hash; };
+using Hash = std::uint32_t;
+using PathHash = std::uint64_t;
+}
+
+#if defined(__GNUC__)
+#pragma GCC diagnostic ignored "-Winvalid-offsetof"
+#endif
+"#;
+
+const KEYWORDS: &[&str] = &[
+    "alignas",
+    "alignof",
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "explicit",
+    "export",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "friend",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "mutable",
+    "namespace",
+    "new",
+    "operator",
+    "private",
+    "protected",
+    "public",
+    "register",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typedef",
+    "typename",
+    "union",
+    "unsigned",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+];
+
+enum Member {
+    Field {
+        name: String,
+        decl: String,
+        size: usize,
+    },
+    Flags {
+        bits: Vec<(u8, String)>,
+    },
+    Opaque {
+        name: String,
+        comment: String,
+    },
+}
+
+fn nestable_type(
+    meta: &MetaFile,
+    names: &HashNames,
+    value_type: BinType,
+    other_class: Option<u32>,
+) -> Option<(String, usize)> {
+    // Classes missing from the dump are never declared.
+    let class_name = || {
+        other_class.map(|c| match meta.class(c) {
+            Some(_) => class_ident(names, c),
+            None => "void".into(),
+        })
+    };
+    Some(match value_type {
+        BinType::Bool => ("bool".into(), 1),
+        BinType::I8 => ("std::int8_t".into(), 1),
+        BinType::U8 => ("std::uint8_t".into(), 1),
+        BinType::I16 => ("std::int16_t".into(), 2),
+        BinType::U16 => ("std::uint16_t".into(), 2),
+        BinType::I32 => ("std::int32_t".into(), 4),
+        BinType::U32 => ("std::uint32_t".into(), 4),
+        BinType::I64 => ("std::int64_t".into(), 8),
+        BinType::U64 => ("std::uint64_t".into(), 8),
+        BinType::F32 => ("float".into(), 4),
+        BinType::Vec2 => ("meta::Vector2".into(), 8),
+        BinType::Vec3 => ("meta::Vector3".into(), 12),
+        BinType::Vec4 => ("meta::Vector4".into(), 16),
+        BinType::Mtx44 => ("meta::Matrix44".into(), 64),
+        BinType::Color => ("meta::Color".into(), 4),
+        BinType::String => ("meta::String".into(), 16),
+        BinType::Hash => ("meta::Hash".into(), 4),
+        BinType::File => ("meta::PathHash".into(), 8),
+        BinType::Link => (format!("meta::Link<{}>", class_name()?), 4),
+        BinType::Pointer => (format!("{}*", class_name()?), 8),
+        BinType::Embed => (class_name()?, member_size(meta.class(other_class?)?)),
+        _ => return None,
+    })
+}
+
+/// `sizeof` of the generated struct, empty ones still take a byte and
+/// `alignas` rounds the size up.
+fn member_size(class: &ClassInfo) -> usize {
+    let size = class.size.max(1);
+    if class.alignment.is_power_of_two() {
+        size.next_multiple_of(class.alignment)
+    } else {
+        size
+    }
+}
+
+fn property_member(
+    meta: &MetaFile,
+    names: &HashNames,
+    name: &str,
+    property: &PropertyInfo,
+) -> Member {
+    let opaque = |comment: String| Member::Opaque {
+        name: name.to_string(),
+        comment,
+    };
+    let typed = match (property.value_type, &property.container) {
+        (BinType::List | BinType::List2, Some(container)) => {
+            let item = nestable_type(meta, names, container.value_type, property.other_class);
+            match (item, container.fixed_size, container.storage) {
+                (Some((item, size)), Some(count), _) if size == container.value_size as usize => {
+                    Some((format!("{} {}[{}]", item, name, count), size * count))
+                }
+                (Some((item, _)), None, Some(ContainerStorage::RitoVector)) => {
+                    Some((format!("meta::RiotVector<{}> {}", item, name), 16))
+                }
+                (Some((item, _)), None, Some(ContainerStorage::StdVector)) => {
+                    Some((format!("meta::StdVector<{}> {}", item, name), 24))
+                }
+                _ => None,
+            }
+        }
+        (BinType::List | BinType::List2 | BinType::Option | BinType::Map, _) => None,
+        _ => nestable_type(meta, names, property.value_type, property.other_class)
+            .map(|(item, size)| (format!("{} {}", item, name), size)),
+    };
+    match typed {
+        Some((decl, size)) => Member::Field {
+            name: name.to_string(),
+            decl,
+            size,
+        },
+        None => opaque(type_comment(names, property)),
+    }
+}
+
+fn type_comment(names: &HashNames, property: &PropertyInfo) -> String {
+    property
+        .type_expr()
+        .format_with(&|class| class_ident(names, class))
+}
+
+fn unique_name(used: &mut BTreeSet<String>, name: String, hash: u32) -> String {
+    let mut name = if KEYWORDS.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    };
+    if !used.insert(name.clone()) {
+        name = format!("{}_{:08x}", name, hash);
+        used.insert(name.clone());
+    }
+    name
+}
+
+fn class_members(meta: &MetaFile, names: &HashNames, class: &ClassInfo) -> Vec<(usize, Member)> {
+    let mut used = BTreeSet::new();
+    let mut members = Vec::new();
+    // Bases missing from the dump become bytes up to the next member.
+    if let Some(base) = class.base.filter(|&x| meta.class(x).is_none()) {
+        let name = unique_name(
+            &mut used,
+            format!("base_{}", class_ident(names, base)),
+            base,
+        );
+        let comment = "missing base".to_string();
+        members.push((0, Member::Opaque { name, comment }));
+    }
+    for (&base, &offset) in &class.secondary_bases {
+        // Empty bases take no space in the real layout.
+        if meta.class(base).is_some_and(|x| x.size == 0) {
+            continue;
+        }
+        let name = unique_name(
+            &mut used,
+            format!("base_{}", class_ident(names, base)),
+            base,
+        );
+        let member = match meta.class(base) {
+            Some(info) => Member::Field {
+                decl: format!("{} {}", class_ident(names, base), name),
+                name,
+                size: member_size(info),
+            },
+            None => Member::Opaque {
+                name,
+                comment: "missing secondary base".into(),
+            },
+        };
+        members.push((offset as usize, member));
+    }
+    let mut flags = BTreeMap::<usize, Vec<(u8, String)>>::new();
+    for (&hash, property) in &class.properties {
+        let name = unique_name(&mut used, field_ident(names, hash), hash);
+        let offset = property.offset as usize;
+        if property.value_type == BinType::Flag {
+            flags
+                .entry(offset)
+                .or_default()
+                .push((property.bitmask, name));
+        } else {
+            members.push((offset, property_member(meta, names, &name, property)));
+        }
+    }
+    for (offset, mut bits) in flags {
+        bits.sort();
+        members.push((offset, Member::Flags { bits }));
+    }
+    members.sort_by_key(|(offset, _)| *offset);
+    members
+}
+
+fn write_flags(out: &mut String, offset: usize, bits: &[(u8, String)]) {
+    let mut cursor = 0u8;
+    for (bit, name) in bits {
+        if *bit < cursor || *bit >= 8 {
+            writeln!(
+                out,
+                "    // overlapping flag {} bit {} at {:#x}",
+                name, bit, offset
+            )
+            .unwrap();
+            continue;
+        }
+        if *bit > cursor {
+            writeln!(out, "    std::uint8_t : {};", bit - cursor).unwrap();
+        }
+        writeln!(out, "    std::uint8_t {} : 1;", name).unwrap();
+        cursor = bit + 1;
+    }
+    if cursor < 8 {
+        writeln!(out, "    std::uint8_t : {};", 8 - cursor).unwrap();
+    }
+}
+
+fn write_class(out: &mut String, meta: &MetaFile, names: &HashNames, hash: u32) {
+    let class = match meta.class(hash) {
+        Some(class) => class,
+        None => return,
+    };
+    let name = class_ident(names, hash);
+    let members = class_members(meta, names, class);
+    let mut cursor = class.base.and_then(|b| meta.class(b)).map_or(0, |b| b.size);
+    let mut checks = Vec::new();
+
+    writeln!(out, "// {:#x}", hash).unwrap();
+    write!(out, "struct").unwrap();
+    if class.alignment.is_power_of_two() {
+        write!(out, " alignas({})", class.alignment).unwrap();
+    }
+    write!(out, " {}", name).unwrap();
+    if let Some(base) = class.base.filter(|&x| meta.class(x).is_some()) {
+        write!(out, " : {}", class_ident(names, base)).unwrap();
+    }
+    writeln!(out, " {{").unwrap();
+    for (index, (offset, member)) in members.iter().enumerate() {
+        let offset = *offset;
+        if offset < cursor {
+            let what = match member {
+                Member::Field { name, .. } | Member::Opaque { name, .. } => name.as_str(),
+                Member::Flags { bits } => bits[0].1.as_str(),
+            };
+            writeln!(out, "    // overlapping member {} at {:#x}", what, offset).unwrap();
+            continue;
+        }
+        if offset > cursor {
+            writeln!(
+                out,
+                "    std::uint8_t pad_{:x}[{:#x}];",
+                cursor,
+                offset - cursor
+            )
+            .unwrap();
+        }
+        match member {
+            Member::Field { name, decl, size } => {
+                writeln!(out, "    {}; // {:#x}", decl, offset).unwrap();
+                checks.push((name.clone(), offset));
+                cursor = offset + size;
+            }
+            Member::Flags { bits } => {
+                write_flags(out, offset, bits);
+                cursor = offset + 1;
+            }
+            Member::Opaque { name, comment } => {
+                let end = members[index + 1..]
+                    .iter()
+                    .map(|(offset, _)| *offset)
+                    .find(|&next| next > offset)
+                    .unwrap_or(class.size)
+                    .max(offset + 1);
+                writeln!(
+                    out,
+                    "    std::uint8_t {}[{:#x}]; // {:#x} {}",
+                    name,
+                    end - offset,
+                    offset,
+                    comment
+                )
+                .unwrap();
+                checks.push((name.clone(), offset));
+                cursor = end;
+            }
+        }
+    }
+    if class.size > cursor {
+        writeln!(
+            out,
+            "    std::uint8_t pad_{:x}[{:#x}];",
+            cursor,
+            class.size - cursor
+        )
+        .unwrap();
+    }
+    writeln!(out, "}};").unwrap();
+    writeln!(
+        out,
+        "static_assert(sizeof({0}) == {1:#x}, \"{0} size\");",
+        name,
+        member_size(class)
+    )
+    .unwrap();
+    if class.alignment.is_power_of_two() {
+        writeln!(
+            out,
+            "static_assert(alignof({0}) == {1}, \"{0} alignment\");",
+            name, class.alignment
+        )
+        .unwrap();
+    }
+    for (member, offset) in checks {
+        writeln!(
+            out,
+            "static_assert(offsetof({0}, {1}) == {2:#x}, \"{0}::{1} offset\");",
+            name, member, offset
+        )
+        .unwrap();
+    }
+    writeln!(out).unwrap();
+}
+
+/// Emits a C++ header with one struct per class laid out at the dumped offsets.
+pub fn generate(meta: &MetaFile, names: &HashNames) -> String {
+    let mut out = String::new();
+    writeln!(out, "// Generated from meta_{}.json", meta.version).unwrap();
+    out.push_str(PRELUDE);
+    writeln!(out).unwrap();
+    for &hash in meta.classes.keys() {
+        writeln!(out, "struct {};", class_ident(names, hash)).unwrap();
+    }
+    writeln!(out).unwrap();
+    writeln!(out, "#pragma pack(push, 1)").unwrap();
+    for hash in class_order(meta) {
+        write_class(&mut out, meta, names, hash);
+    }
+    writeln!(out, "#pragma pack(pop)").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::process::Command;
+
+    use super::*;
+    use crate::schema::testing::{class, container, map, meta, property};
+
+    #[test]
+    fn generated_header_compiles() {
+        let empty = class(None, 0, Vec::new());
+        let base = class(None, 16, vec![(0x1, property(8, BinType::U32, None))]);
+        let mut derived = class(
+            Some(0x20),
+            48,
+            vec![
+                (0x2, property(20, BinType::F32, None)),
+                (0x3, property(24, BinType::Embed, Some(0x10))),
+                (0x4, container(32, BinType::Option, BinType::U8, None)),
+                (0x5, property(40, BinType::Pointer, Some(0x20))),
+            ],
+        );
+        derived["secondary_bases"] = serde_json::json!({"0x10": 16});
+        // 0x98 and 0x99 are referenced but missing from the dump.
+        let mut orphan = class(
+            Some(0x99),
+            64,
+            vec![
+                (0x6, property(8, BinType::U32, None)),
+                (0x7, property(16, BinType::Pointer, Some(0x99))),
+                (0x8, property(24, BinType::Link, Some(0x99))),
+                (0x9, property(28, BinType::Embed, Some(0x99))),
+                (0xa, container(32, BinType::List, BinType::U32, None)),
+                (0xb, map(48, BinType::Hash, BinType::String, None)),
+            ],
+        );
+        orphan["secondary_bases"] = serde_json::json!({"0x98": 12});
+        let meta = meta(vec![
+            (0x10, empty),
+            (0x20, base),
+            (0x30, derived),
+            (0x40, orphan),
+        ]);
+        let header = generate(&meta, &HashNames::new());
+
+        let path = std::env::temp_dir().join(format!("gen-cpp-{}.hpp", std::process::id()));
+        std::fs::write(&path, &header).unwrap();
+        let output = Command::new("c++")
+            .args(["-std=c++17", "-fsyntax-only", "-x", "c++"])
+            .arg(&path)
+            .output();
+        std::fs::remove_file(&path).unwrap();
+        let output = match output {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                eprintln!("Skipping generated_header_compiles, no c++ compiler found");
+                return;
+            }
+            Err(e) => panic!("Failed to run c++: {}", e),
+        };
+        assert!(
+            output.status.success(),
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stderr),
+            header
+        );
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use serde_json::Value;
+
+use crate::codegen::{
+    class_ident, default_color, default_floats, default_hash, escape_ident, field_ident,
+};
+use crate::hashes::HashNames;
+use crate::schema::{MetaFile, PropertyInfo};
+use crate::types::BinType;
+
+// Framework types are spelled out from `global::` so dumped classes named
+// `Vector3`, `List` or even `System` cannot shadow them.
+const PRELUDE: &str = r#"#nullable enable
+namespace LeagueMeta
+{
+    [global::System.AttributeUsage(global::System.AttributeTargets.Class)]
+    public sealed class BinClassAttribute : global::System.Attribute
+    {
+        public uint Hash { get; }
+        public BinClassAttribute(uint hash) { Hash = hash; }
+    }
+
+    [global::System.AttributeUsage(global::System.AttributeTargets.Property)]
+    public sealed class BinPropertyAttribute : global::System.Attribute
+    {
+        public uint Hash { get; }
+        public BinType Type { get; }
+        public BinPropertyAttribute(uint hash, BinType type) { Hash = hash; Type = type; }
+    }
+
+    public struct Color
+    {
+        public byte R, G, B, A;
+        public Color(byte r, byte g, byte b, byte a) { R = r; G = g; B = b; A = a; }
+    }
+"#;
+
+const KEYWORDS: &[&str] = &[
+    "abstract",
+    "as",
+    "base",
+    "bool",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "checked",
+    "class",
+    "const",
+    "continue",
+    "decimal",
+    "default",
+    "delegate",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "event",
+    "explicit",
+    "extern",
+    "false",
+    "finally",
+    "fixed",
+    "float",
+    "for",
+    "foreach",
+    "goto",
+    "if",
+    "implicit",
+    "in",
+    "int",
+    "interface",
+    "internal",
+    "is",
+    "lock",
+    "long",
+    "namespace",
+    "new",
+    "null",
+    "object",
+    "operator",
+    "out",
+    "override",
+    "params",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "ref",
+    "return",
+    "sbyte",
+    "sealed",
+    "short",
+    "sizeof",
+    "stackalloc",
+    "static",
+    "string",
+    "struct",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "uint",
+    "ulong",
+    "unchecked",
+    "unsafe",
+    "ushort",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+];
+
+/// `BinClass` and `BinProperty` would make the attribute names ambiguous.
+const PRELUDE_NAMES: &[&str] = &[
+    "BinClass",
+    "BinClassAttribute",
+    "BinProperty",
+    "BinPropertyAttribute",
+    "BinType",
+    "Color",
+];
+
+fn class_name(names: &HashNames, hash: u32) -> String {
+    // `@` only escapes keywords, `@Color` would still be the prelude's `Color`.
+    let name = escape_ident(class_ident(names, hash), &[PRELUDE_NAMES]);
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("@{}", name)
+    } else {
+        name
+    }
+}
+
+fn nestable_type(
+    meta: &MetaFile,
+    names: &HashNames,
+    value_type: BinType,
+    other: Option<u32>,
+) -> String {
+    match value_type {
+        BinType::Bool | BinType::Flag => "bool".into(),
+        BinType::I8 => "sbyte".into(),
+        BinType::U8 => "byte".into(),
+        BinType::I16 => "short".into(),
+        BinType::U16 => "ushort".into(),
+        BinType::I32 => "int".into(),
+        BinType::U32 | BinType::Hash | BinType::Link => "uint".into(),
+        BinType::I64 => "long".into(),
+        BinType::U64 | BinType::File => "ulong".into(),
+        BinType::F32 => "float".into(),
+        BinType::Vec2 => "global::System.Numerics.Vector2".into(),
+        BinType::Vec3 => "global::System.Numerics.Vector3".into(),
+        BinType::Vec4 => "global::System.Numerics.Vector4".into(),
+        BinType::Mtx44 => "global::System.Numerics.Matrix4x4".into(),
+        BinType::Color => "Color".into(),
+        BinType::String => "string".into(),
+        BinType::Pointer | BinType::Embed => match other.filter(|&c| meta.class(c).is_some()) {
+            Some(other) => class_name(names, other),
+            None => "object".into(),
+        },
+        _ => "object".into(),
+    }
+}
+
+fn property_type(meta: &MetaFile, names: &HashNames, property: &PropertyInfo) -> String {
+    match (property.value_type, &property.container, &property.map) {
+        (BinType::List | BinType::List2, Some(container), _) => {
+            let item = nestable_type(meta, names, container.value_type, property.other_class);
+            format!("global::System.Collections.Generic.List<{}>", item)
+        }
+        (BinType::Option, Some(container), _) => {
+            let item = nestable_type(meta, names, container.value_type, property.other_class);
+            format!("{}?", item)
+        }
+        (BinType::Map, _, Some(map)) => {
+            let key = nestable_type(meta, names, map.key_type, None);
+            let value = nestable_type(meta, names, map.value_type, property.other_class);
+            format!(
+                "global::System.Collections.Generic.Dictionary<{}, {}>",
+                key, value
+            )
+        }
+        (BinType::Pointer, _, _) => {
+            format!(
+                "{}?",
+                nestable_type(meta, names, BinType::Pointer, property.other_class)
+            )
+        }
+        _ => nestable_type(meta, names, property.value_type, property.other_class),
+    }
+}
+
+fn string_literal(text: &str) -> String {
+    let mut result = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(result, "\\u{:04x}", c as u32).unwrap(),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn floats_literal(value: &Value, count: usize) -> Option<String> {
+    let items = default_floats(value, count)?
+        .iter()
+        .map(|x| format!("{:?}f", x))
+        .collect::<Vec<_>>();
+    Some(items.join(", "))
+}
+
+fn nestable_literal(value: &Value, value_type: BinType) -> Option<String> {
+    Some(match value_type {
+        BinType::Bool | BinType::Flag => value.as_bool()?.to_string(),
+        BinType::I8 | BinType::I16 | BinType::I32 => value.as_i64()?.to_string(),
+        BinType::I64 => format!("{}L", value.as_i64()?),
+        BinType::U8 | BinType::U16 => value.as_u64()?.to_string(),
+        BinType::U32 => format!("{}u", value.as_u64()?),
+        BinType::U64 => format!("{}ul", value.as_u64()?),
+        BinType::F32 => format!("{:?}f", value.as_f64()? as f32),
+        BinType::Vec2 => format!(
+            "new global::System.Numerics.Vector2({})",
+            floats_literal(value, 2)?
+        ),
+        BinType::Vec3 => format!(
+            "new global::System.Numerics.Vector3({})",
+            floats_literal(value, 3)?
+        ),
+        BinType::Vec4 => format!(
+            "new global::System.Numerics.Vector4({})",
+            floats_literal(value, 4)?
+        ),
+        BinType::Mtx44 => format!(
+            "new global::System.Numerics.Matrix4x4({})",
+            floats_literal(value, 16)?
+        ),
+        BinType::Color => {
+            let [r, g, b, a] = default_color(value)?;
+            format!("new Color({}, {}, {}, {})", r, g, b, a)
+        }
+        BinType::String => string_literal(value.as_str()?),
+        BinType::Hash | BinType::Link => format!("{:#x}u", default_hash(value)?),
+        BinType::File => format!("{:#x}ul", default_hash(value)?),
+        _ => return None,
+    })
+}
+
+fn default_literal(ty: &str, value: &Value, property: &PropertyInfo) -> Option<String> {
+    match (property.value_type, &property.container) {
+        (BinType::List | BinType::List2, Some(container)) => {
+            let items = value
+                .as_array()?
+                .iter()
+                .map(|x| nestable_literal(x, container.value_type))
+                .collect::<Option<Vec<_>>>()?;
+            if items.is_empty() {
+                Some(format!("new {}()", ty))
+            } else {
+                Some(format!("new {} {{ {} }}", ty, items.join(", ")))
+            }
+        }
+        (BinType::Option, Some(container)) => match value {
+            Value::Null => None,
+            _ => nestable_literal(value, container.value_type),
+        },
+        (BinType::Map, _) => Some(format!("new {}()", ty)),
+        (BinType::Embed, _) => Some(format!("new {}()", ty)),
+        _ => nestable_literal(value, property.value_type),
+    }
+}
+
+/// Properties declared on `hash` plus everything reachable through secondary
+/// bases, since C# classes can only inherit the primary base.
+fn declared_properties(meta: &MetaFile, hash: u32) -> Vec<(u32, &PropertyInfo)> {
+    let mut results = Vec::new();
+    if let Some(class) = meta.class(hash) {
+        for &base in class.secondary_bases.keys() {
+            for (_, property, info) in meta.all_properties(base) {
+                results.push((property, info));
+            }
+        }
+        for (&property, info) in &class.properties {
+            results.push((property, info));
+        }
+    }
+    results
+}
+
+fn write_class(out: &mut String, meta: &MetaFile, names: &HashNames, hash: u32) {
+    let class = match meta.class(hash) {
+        Some(class) => class,
+        None => return,
+    };
+    let name = class_name(names, hash);
+
+    writeln!(out, "    [BinClass({:#x})]", hash).unwrap();
+    write!(out, "    public ").unwrap();
+    if class.is.interface {
+        write!(out, "abstract ").unwrap();
+    }
+    write!(out, "class {}", name).unwrap();
+    if let Some(base) = class.base.filter(|&b| meta.class(b).is_some()) {
+        write!(out, " : {}", class_name(names, base)).unwrap();
+    }
+    writeln!(out).unwrap();
+    writeln!(out, "    {{").unwrap();
+
+    let mut used = BTreeSet::new();
+    used.insert(name.trim_start_matches('@').to_string());
+    for (property, info) in declared_properties(meta, hash) {
+        let mut field = field_ident(names, property);
+        if !used.insert(field.clone()) {
+            field = format!("{}_{:08x}", field, property);
+            used.insert(field.clone());
+        }
+        if KEYWORDS.contains(&field.as_str()) {
+            field.insert(0, '@');
+        }
+        let ty = property_type(meta, names, info);
+        let default = meta
+            .default_value(hash, property)
+            .and_then(|value| default_literal(&ty, value, info))
+            .or_else(|| match info.value_type {
+                BinType::List | BinType::List2 | BinType::Map => Some(format!("new {}()", ty)),
+                BinType::Embed if ty != "object" => Some(format!("new {}()", ty)),
+                BinType::String => Some("\"\"".into()),
+                _ => None,
+            });
+        writeln!(
+            out,
+            "        [BinProperty({:#x}, BinType.{:?})]",
+            property, info.value_type
+        )
+        .unwrap();
+        write!(out, "        public {} {} {{ get; set; }}", ty, field).unwrap();
+        match default {
+            Some(default) => writeln!(out, " = {};", default).unwrap(),
+            None => writeln!(out).unwrap(),
+        }
+    }
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+}
+
+/// Emits C# model classes for every class in the dump.
+pub fn generate(meta: &MetaFile, names: &HashNames) -> String {
+    let mut out = String::new();
+    writeln!(out, "// Generated from meta_{}.json", meta.version).unwrap();
+    out.push_str(PRELUDE);
+    writeln!(out).unwrap();
+    writeln!(out, "    public enum BinType : byte").unwrap();
+    writeln!(out, "    {{").unwrap();
+    for code in BinType::ALL {
+        writeln!(out, "        {:?} = {:#x},", code, code as u8).unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    for &hash in meta.classes.keys() {
+        write_class(&mut out, meta, names, hash);
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashes::fnv1a;
+    use crate::schema::testing::{class, container, map, meta, property};
+
+    #[test]
+    fn reserved_class_names_get_a_suffix() {
+        let color = fnv1a("Color");
+        let holder = class(
+            None,
+            16,
+            vec![(0x1, property(8, BinType::Embed, Some(color)))],
+        );
+        let meta = meta(vec![(color, class(None, 8, Vec::new())), (0x10, holder)]);
+        let mut names = HashNames::new();
+        names.insert(color, "Color");
+        let source = generate(&meta, &names);
+        assert!(source.contains("class Color_"), "{}", source);
+        assert!(source.contains("public Color_ "), "{}", source);
+        assert!(!source.contains("@Color"), "{}", source);
+    }
+
+    #[test]
+    fn framework_types_are_not_shadowed() {
+        let holder = class(
+            None,
+            64,
+            vec![
+                (0x1, property(0, BinType::Vec3, None)),
+                (0x2, container(16, BinType::List, BinType::U8, None)),
+                (0x3, map(32, BinType::Hash, BinType::Mtx44, None)),
+            ],
+        );
+        let mut classes = vec![(0x10, holder)];
+        let mut names = HashNames::new();
+        for name in ["Vector3", "List", "System", "BinClass"] {
+            classes.push((fnv1a(name), class(None, 8, Vec::new())));
+            names.insert(fnv1a(name), name);
+        }
+        let source = generate(&meta(classes), &names);
+        for name in ["Vector3", "List", "System"] {
+            assert!(source.contains(&format!("class {}\n", name)), "{}", source);
+        }
+        assert!(source.contains("class BinClass_\n"), "{}", source);
+        assert!(!source.contains("using "), "{}", source);
+        for declaration in [
+            "public global::System.Numerics.Vector3 ",
+            "public global::System.Collections.Generic.List<byte> ",
+            "public global::System.Collections.Generic.Dictionary<uint, global::System.Numerics.Matrix4x4> ",
+        ] {
+            assert!(source.contains(declaration), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,497 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use serde_json::Value;
+
+use crate::codegen::{
+    class_ident, class_order, default_color, default_floats, default_hash, escape_ident,
+    field_ident,
+};
+use crate::hashes::HashNames;
+use crate::schema::{MetaFile, PropertyInfo};
+use crate::types::BinType;
+
+const PRELUDE: &str = r#"from __future__ import annotations
+
+from dataclasses import dataclass, field
+from typing import Any, ClassVar, Dict, List, Optional, Tuple, Type
+
+Vec2 = Tuple[float, float]
+Vec3 = Tuple[float, float, float]
+Vec4 = Tuple[float, float, float, float]
+Mtx44 = Tuple[Vec4, Vec4, Vec4, Vec4]
+Color = Tuple[int, int, int, int]
+Hash = int
+PathHash = int
+Link = int
+"#;
+
+const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+const PRELUDE_NAMES: &[&str] = &[
+    "annotations",
+    "dataclass",
+    "field",
+    "Any",
+    "ClassVar",
+    "Dict",
+    "List",
+    "Optional",
+    "Tuple",
+    "Type",
+    "Vec2",
+    "Vec3",
+    "Vec4",
+    "Mtx44",
+    "Color",
+    "Hash",
+    "PathHash",
+    "Link",
+    "CLASSES",
+    "create",
+    "HASH",
+];
+
+fn class_name(names: &HashNames, hash: u32) -> String {
+    escape_ident(class_ident(names, hash), &[KEYWORDS, PRELUDE_NAMES])
+}
+
+fn nestable_type(
+    meta: &MetaFile,
+    names: &HashNames,
+    value_type: BinType,
+    other: Option<u32>,
+) -> String {
+    match value_type {
+        BinType::Bool | BinType::Flag => "bool".into(),
+        BinType::I8
+        | BinType::U8
+        | BinType::I16
+        | BinType::U16
+        | BinType::I32
+        | BinType::U32
+        | BinType::I64
+        | BinType::U64 => "int".into(),
+        BinType::F32 => "float".into(),
+        BinType::Vec2 => "Vec2".into(),
+        BinType::Vec3 => "Vec3".into(),
+        BinType::Vec4 => "Vec4".into(),
+        BinType::Mtx44 => "Mtx44".into(),
+        BinType::Color => "Color".into(),
+        BinType::String => "str".into(),
+        BinType::Hash => "Hash".into(),
+        BinType::File => "PathHash".into(),
+        BinType::Link => "Link".into(),
+        BinType::Pointer => match other.filter(|&c| meta.class(c).is_some()) {
+            Some(other) => format!("Optional[{}]", class_name(names, other)),
+            None => "Any".into(),
+        },
+        BinType::Embed => match other.filter(|&c| meta.class(c).is_some()) {
+            Some(other) => class_name(names, other),
+            None => "Any".into(),
+        },
+        _ => "Any".into(),
+    }
+}
+
+fn property_type(meta: &MetaFile, names: &HashNames, property: &PropertyInfo) -> String {
+    match (property.value_type, &property.container, &property.map) {
+        (BinType::List | BinType::List2, Some(container), _) => {
+            let item = nestable_type(meta, names, container.value_type, property.other_class);
+            format!("List[{}]", item)
+        }
+        (BinType::Option, Some(container), _) => {
+            let item = nestable_type(meta, names, container.value_type, property.other_class);
+            format!("Optional[{}]", item)
+        }
+        (BinType::Map, _, Some(map)) => {
+            let key = nestable_type(meta, names, map.key_type, None);
+            let value = nestable_type(meta, names, map.value_type, property.other_class);
+            format!("Dict[{}, {}]", key, value)
+        }
+        _ => nestable_type(meta, names, property.value_type, property.other_class),
+    }
+}
+
+fn floats_literal(floats: &[f32]) -> String {
+    let items = floats
+        .iter()
+        .map(|x| format!("{:?}", x))
+        .collect::<Vec<_>>();
+    format!("({})", items.join(", "))
+}
+
+fn nestable_literal(value: &Value, value_type: BinType) -> Option<String> {
+    Some(match value_type {
+        BinType::Bool | BinType::Flag => {
+            if value.as_bool()? {
+                "True".into()
+            } else {
+                "False".into()
+            }
+        }
+        BinType::I8 | BinType::I16 | BinType::I32 | BinType::I64 => value.as_i64()?.to_string(),
+        BinType::U8 | BinType::U16 | BinType::U32 | BinType::U64 => value.as_u64()?.to_string(),
+        BinType::F32 => format!("{:?}", value.as_f64()? as f32),
+        BinType::Vec2 => floats_literal(&default_floats(value, 2)?),
+        BinType::Vec3 => floats_literal(&default_floats(value, 3)?),
+        BinType::Vec4 => floats_literal(&default_floats(value, 4)?),
+        BinType::Mtx44 => {
+            let floats = default_floats(value, 16)?;
+            let rows = floats.chunks(4).map(floats_literal).collect::<Vec<_>>();
+            format!("({})", rows.join(", "))
+        }
+        BinType::Color => {
+            let [r, g, b, a] = default_color(value)?;
+            format!("({}, {}, {}, {})", r, g, b, a)
+        }
+        BinType::String => serde_json::to_string(value.as_str()?).ok()?,
+        BinType::Hash | BinType::File | BinType::Link => format!("{:#x}", default_hash(value)?),
+        _ => return None,
+    })
+}
+
+fn fallback_literal(
+    meta: &MetaFile,
+    names: &HashNames,
+    value_type: BinType,
+    other: Option<u32>,
+) -> String {
+    match value_type {
+        BinType::Bool | BinType::Flag => "False".into(),
+        BinType::F32 => "0.0".into(),
+        BinType::Vec2 => "(0.0, 0.0)".into(),
+        BinType::Vec3 => "(0.0, 0.0, 0.0)".into(),
+        BinType::Vec4 => "(0.0, 0.0, 0.0, 0.0)".into(),
+        BinType::Mtx44 => "((1.0, 0.0, 0.0, 0.0), (0.0, 1.0, 0.0, 0.0), (0.0, 0.0, 1.0, 0.0), (0.0, 0.0, 0.0, 1.0))".into(),
+        BinType::Color => "(0, 0, 0, 0)".into(),
+        BinType::String => "\"\"".into(),
+        BinType::Pointer | BinType::Option => "None".into(),
+        BinType::List | BinType::List2 => "[]".into(),
+        BinType::Map => "{}".into(),
+        BinType::Embed => match other.filter(|&c| meta.class(c).is_some()) {
+            Some(other) => format!("{}()", class_name(names, other)),
+            None => "None".into(),
+        },
+        _ => "0".into(),
+    }
+}
+
+/// Python expression for the default of `property`, and whether it has to go
+/// through `default_factory` because the value is mutable.
+fn default_expr(
+    meta: &MetaFile,
+    names: &HashNames,
+    value: Option<&Value>,
+    property: &PropertyInfo,
+) -> (String, bool) {
+    let literal = value.and_then(|value| match (property.value_type, &property.container) {
+        (BinType::List | BinType::List2, Some(container)) => {
+            let items = value
+                .as_array()?
+                .iter()
+                .map(|x| nestable_literal(x, container.value_type))
+                .collect::<Option<Vec<_>>>()?;
+            Some(format!("[{}]", items.join(", ")))
+        }
+        (BinType::Option, Some(container)) => match value {
+            Value::Null => Some("None".into()),
+            _ => nestable_literal(value, container.value_type),
+        },
+        _ => nestable_literal(value, property.value_type),
+    });
+    let literal = literal.unwrap_or_else(|| {
+        fallback_literal(meta, names, property.value_type, property.other_class)
+    });
+    let mutable = matches!(
+        property.value_type,
+        BinType::List | BinType::List2 | BinType::Map | BinType::Embed
+    );
+    (literal, mutable)
+}
+
+struct Field {
+    hash: u32,
+    name: String,
+    ty: String,
+    default: (String, bool),
+    value_type: BinType,
+}
+
+/// Fields to declare on `hash`: its own properties, properties of secondary
+/// bases, and inherited properties whose default differs from the base class.
+fn class_fields(meta: &MetaFile, names: &HashNames, hash: u32) -> Vec<Field> {
+    let class = match meta.class(hash) {
+        Some(class) => class,
+        None => return Vec::new(),
+    };
+    let mut declared = Vec::new();
+    if let Some(base) = class.base {
+        for (_, property, info) in meta.all_properties(base) {
+            let value = meta.default_value(hash, property);
+            if value.is_some() && value != meta.default_value(base, property) {
+                declared.push((property, info));
+            }
+        }
+    }
+    for &base in class.secondary_bases.keys() {
+        for (_, property, info) in meta.all_properties(base) {
+            declared.push((property, info));
+        }
+    }
+    for (&property, info) in &class.properties {
+        declared.push((property, info));
+    }
+
+    let mut used = BTreeSet::new();
+    let mut results = Vec::new();
+    for (property, info) in declared {
+        if results.iter().any(|f: &Field| f.hash == property) {
+            continue;
+        }
+        let mut name = escape_ident(field_ident(names, property), &[KEYWORDS, PRELUDE_NAMES]);
+        if !used.insert(name.clone()) {
+            name = format!("{}_{:08x}", name, property);
+            used.insert(name.clone());
+        }
+        let value = meta.default_value(hash, property);
+        results.push(Field {
+            hash: property,
+            name,
+            ty: property_type(meta, names, info),
+            default: default_expr(meta, names, value, info),
+            value_type: info.value_type,
+        });
+    }
+    results
+}
+
+fn write_class(out: &mut String, meta: &MetaFile, names: &HashNames, hash: u32) {
+    let class = match meta.class(hash) {
+        Some(class) => class,
+        None => return,
+    };
+    let name = class_name(names, hash);
+    writeln!(out, "@dataclass").unwrap();
+    match class.base.filter(|&b| meta.class(b).is_some()) {
+        Some(base) => writeln!(out, "class {}({}):", name, class_name(names, base)).unwrap(),
+        None => writeln!(out, "class {}:", name).unwrap(),
+    }
+    writeln!(out, "    HASH: ClassVar[int] = {:#x}", hash).unwrap();
+    for field in class_fields(meta, names, hash) {
+        let (default, mutable) = &field.default;
+        let metadata = format!(
+            "{{\"hash\": {:#x}, \"type\": \"{:?}\"}}",
+            field.hash, field.value_type
+        );
+        if *mutable {
+            writeln!(
+                out,
+                "    {}: {} = field(default_factory=lambda: {}, metadata={})",
+                field.name, field.ty, default, metadata
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                out,
+                "    {}: {} = field(default={}, metadata={})",
+                field.name, field.ty, default, metadata
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out).unwrap();
+    writeln!(out).unwrap();
+}
+
+/// Emits Python dataclasses for every class and a registry keyed by class hash.
+pub fn generate(meta: &MetaFile, names: &HashNames) -> String {
+    let mut out = String::new();
+    writeln!(out, "# Generated from meta_{}.json", meta.version).unwrap();
+    out.push_str(PRELUDE);
+    writeln!(out).unwrap();
+    writeln!(out).unwrap();
+    for hash in class_order(meta) {
+        write_class(&mut out, meta, names, hash);
+    }
+    writeln!(out, "CLASSES: Dict[int, Type[Any]] = {{").unwrap();
+    for &hash in meta.classes.keys() {
+        writeln!(out, "    {:#x}: {},", hash, class_name(names, hash)).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "def create(hash: int, **kwargs: Any) -> Any:").unwrap();
+    writeln!(out, "    return CLASSES[hash](**kwargs)").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::process::Command;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::hashes::fnv1a;
+    use crate::schema::testing::{class, container, map, meta, property};
+
+    /// Runs the generated module followed by `check` with python3, `false`
+    /// when python3 is missing.
+    fn run_python(test: &str, source: &str, check: &str) -> bool {
+        let path = std::env::temp_dir().join(format!("gen-py-{}-{}.py", test, std::process::id()));
+        std::fs::write(&path, format!("{}\n{}\n", source, check)).unwrap();
+        let output = Command::new("python3").arg(&path).output();
+        std::fs::remove_file(&path).unwrap();
+        let output = match output {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                eprintln!("Skipping {}, no python3 found", test);
+                return false;
+            }
+            Err(e) => panic!("Failed to run python3: {}", e),
+        };
+        assert!(
+            output.status.success(),
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stderr),
+            source
+        );
+        true
+    }
+
+    #[test]
+    fn keywords_and_clashing_names_are_escaped() {
+        let h = fnv1a;
+        let list = class(
+            None,
+            16,
+            vec![
+                (h("class"), property(0, BinType::U32, None)),
+                (h("None"), property(4, BinType::Bool, None)),
+                (h("m.A"), property(8, BinType::U8, None)),
+                (h("m_A"), property(12, BinType::U8, None)),
+            ],
+        );
+        let holder = class(
+            Some(h("dataclass")),
+            24,
+            vec![(h("field"), property(8, BinType::Embed, Some(h("List"))))],
+        );
+        let meta = meta(vec![
+            (h("List"), list),
+            (h("dataclass"), class(None, 8, Vec::new())),
+            (h("Holder"), holder),
+        ]);
+        let mut names = HashNames::new();
+        for name in [
+            "List",
+            "dataclass",
+            "Holder",
+            "class",
+            "None",
+            "m.A",
+            "m_A",
+            "field",
+        ] {
+            names.insert(h(name), name);
+        }
+        let source = generate(&meta, &names);
+        let (first, second) = match h("m.A") < h("m_A") {
+            true => (h("m.A"), h("m_A")),
+            false => (h("m_A"), h("m.A")),
+        };
+        for line in [
+            "class List_:".to_string(),
+            "class Holder(dataclass_):".to_string(),
+            "    class_: int = field(default=0".to_string(),
+            "    None_: bool = field(default=False".to_string(),
+            format!(
+                "    m_A: int = field(default=0, metadata={{\"hash\": {:#x}",
+                first
+            ),
+            format!(
+                "    m_A_{:08x}: int = field(default=0, metadata={{\"hash\": {:#x}",
+                second, second
+            ),
+            "    field_: List_ = field(default_factory=lambda: List_()".to_string(),
+        ] {
+            assert!(source.contains(&line), "{}\n{}", line, source);
+        }
+        let check = format!(
+            "holder = create({:#x})\nassert holder.field_.class_ == 0 and holder.field_.None_ is False\nassert isinstance(holder, dataclass_)",
+            h("Holder")
+        );
+        run_python("keywords", &source, &check);
+    }
+
+    #[test]
+    fn defaults_become_field_defaults() {
+        let mut base = class(
+            None,
+            128,
+            vec![
+                (0x1, property(0, BinType::U32, None)),
+                (0x2, property(4, BinType::F32, None)),
+                (0x3, property(8, BinType::Bool, None)),
+                (0x4, property(12, BinType::Vec3, None)),
+                (0x5, property(24, BinType::Color, None)),
+                (0x6, property(28, BinType::String, None)),
+                (0x7, property(40, BinType::Hash, None)),
+                (0x8, container(48, BinType::List, BinType::I16, None)),
+                (0x9, container(64, BinType::Option, BinType::U8, None)),
+                (0xa, map(72, BinType::U8, BinType::String, None)),
+                (0xb, property(96, BinType::Embed, Some(0x30))),
+            ],
+        );
+        base["defaults"] = json!({
+            "0x1": 5,
+            "0x2": 0.5,
+            "0x3": true,
+            "0x4": [1, 2.5, -3],
+            "0x5": [1, 2, 3, 255],
+            "0x6": "say \"hi\"",
+            "0x7": "0xdeadbeef",
+            "0x8": [-1, 2],
+            "0x9": null,
+        });
+        let mut derived = class(
+            Some(0x10),
+            136,
+            vec![(0xc, property(128, BinType::U32, None))],
+        );
+        derived["defaults"] = json!({"0x1": 6, "0x2": 0.5, "0x9": 7});
+        let inner = class(None, 4, vec![(0xd, property(0, BinType::F32, None))]);
+        let meta = meta(vec![(0x10, base), (0x20, derived), (0x30, inner)]);
+        let source = generate(&meta, &HashNames::new());
+        for line in [
+            "    field_00000001: int = field(default=5,",
+            "    field_00000002: float = field(default=0.5,",
+            "    field_00000003: bool = field(default=True,",
+            "    field_00000004: Vec3 = field(default=(1.0, 2.5, -3.0),",
+            "    field_00000005: Color = field(default=(1, 2, 3, 255),",
+            "    field_00000006: str = field(default=\"say \\\"hi\\\"\",",
+            "    field_00000007: Hash = field(default=0xdeadbeef,",
+            "    field_00000008: List[int] = field(default_factory=lambda: [-1, 2],",
+            "    field_00000009: Optional[int] = field(default=None,",
+            "    field_0000000a: Dict[int, str] = field(default_factory=lambda: {},",
+            "    field_0000000b: Class_00000030 = field(default_factory=lambda: Class_00000030(),",
+            "    field_0000000d: float = field(default=0.0,",
+            // Only inherited fields with another default are declared again.
+            "class Class_00000020(Class_00000010):\n    HASH: ClassVar[int] = 0x20\n    field_00000001: int = field(default=6,",
+            "    field_00000009: Optional[int] = field(default=7,",
+            "    field_0000000c: int = field(default=0,",
+        ] {
+            assert!(source.contains(line), "{}\n{}", line, source);
+        }
+        assert_eq!(source.matches("field_00000002:").count(), 1, "{}", source);
+        let check = "a = create(0x10)\nb = create(0x20)\nassert (a.field_00000001, b.field_00000001, b.field_00000002) == (5, 6, 0.5)\nassert a.field_00000008 is not b.field_00000008 and a.field_0000000b.field_0000000d == 0.0";
+        run_python("defaults", &source, check);
+    }
+}
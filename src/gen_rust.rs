@@ -0,0 +1,862 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use serde_json::Value;
+
+use crate::codegen::{
+    class_ident, default_color, default_floats, default_hash, escape_ident, field_ident,
+};
+use crate::hashes::HashNames;
+use crate::schema::{MetaFile, PropertyInfo};
+use crate::types::BinType;
+
+const PRELUDE: &str = r#"#![allow(non_camel_case_types, non_snake_case, dead_code, clippy::all)]
+
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct DecodeError(pub String);
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+pub type Result<T> = std::result::Result<T, DecodeError>;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hash(pub u32);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PathHash(pub u64);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Link(pub u32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pointer<T>(pub Option<Box<T>>);
+
+impl<T> Default for Pointer<T> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Map<K, V>(pub Vec<(K, V)>);
+
+impl<K, V> Default for Map<K, V> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn bytes(&mut self, size: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(size).filter(|&end| end <= self.data.len());
+        let end = end.ok_or_else(|| DecodeError(format!("Unexpected end of data at {:#x}", self.pos)))?;
+        let result = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(result)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut result = [0u8; N];
+        result.copy_from_slice(self.bytes(N)?);
+        Ok(result)
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    pub fn string(&mut self) -> Result<String> {
+        let size = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(size)?).into_owned())
+    }
+
+    pub fn expect(&self, found: u8, expected: &[u8]) -> Result<()> {
+        if expected.contains(&found) {
+            Ok(())
+        } else {
+            Err(DecodeError(format!("Expected type {:?} got {} at {:#x}", expected, found, self.pos)))
+        }
+    }
+
+    pub fn skip(&mut self, ty: u8) -> Result<()> {
+        match ty {
+            NONE => {}
+            BOOL | I8 | U8 | FLAG => drop(self.bytes(1)?),
+            I16 | U16 => drop(self.bytes(2)?),
+            I32 | U32 | F32 | COLOR | HASH | LINK => drop(self.bytes(4)?),
+            I64 | U64 | VEC2 | FILE => drop(self.bytes(8)?),
+            VEC3 => drop(self.bytes(12)?),
+            VEC4 => drop(self.bytes(16)?),
+            MTX44 => drop(self.bytes(64)?),
+            STRING => drop(self.string()?),
+            LIST | LIST2 => {
+                self.u8()?;
+                let size = self.u32()? as usize;
+                self.bytes(size)?;
+            }
+            POINTER => {
+                if self.u32()? != 0 {
+                    let size = self.u32()? as usize;
+                    self.bytes(size)?;
+                }
+            }
+            EMBED => {
+                self.u32()?;
+                let size = self.u32()? as usize;
+                self.bytes(size)?;
+            }
+            OPTION => {
+                let item = self.u8()?;
+                if self.u8()? != 0 {
+                    self.skip(item)?;
+                }
+            }
+            MAP => {
+                self.u8()?;
+                self.u8()?;
+                let size = self.u32()? as usize;
+                self.bytes(size)?;
+            }
+            _ => return Err(DecodeError(format!("Unknown type {} at {:#x}", ty, self.pos))),
+        }
+        Ok(())
+    }
+
+    pub fn skip_fields(&mut self) -> Result<()> {
+        let count = self.u16()?;
+        for _ in 0..count {
+            self.u32()?;
+            let ty = self.u8()?;
+            self.skip(ty)?;
+        }
+        Ok(())
+    }
+}
+
+pub trait Decode: Sized {
+    fn decode(r: &mut Reader, ty: u8) -> Result<Self>;
+}
+
+macro_rules! decode_simple {
+    ($($t:ty => [$($code:expr),*] |$r:ident| $body:expr;)*) => {
+        $(impl Decode for $t {
+            fn decode($r: &mut Reader, ty: u8) -> Result<Self> {
+                $r.expect(ty, &[$($code),*])?;
+                Ok($body)
+            }
+        })*
+    };
+}
+
+decode_simple! {
+    bool => [BOOL, FLAG] |r| r.u8()? != 0;
+    i8 => [I8] |r| r.u8()? as i8;
+    u8 => [U8] |r| r.u8()?;
+    i16 => [I16] |r| r.u16()? as i16;
+    u16 => [U16] |r| r.u16()?;
+    i32 => [I32] |r| r.u32()? as i32;
+    u32 => [U32] |r| r.u32()?;
+    i64 => [I64] |r| r.u64()? as i64;
+    u64 => [U64] |r| r.u64()?;
+    f32 => [F32] |r| r.f32()?;
+    [f32; 2] => [VEC2] |r| [r.f32()?, r.f32()?];
+    [f32; 3] => [VEC3] |r| [r.f32()?, r.f32()?, r.f32()?];
+    [f32; 4] => [VEC4] |r| [r.f32()?, r.f32()?, r.f32()?, r.f32()?];
+    [u8; 4] => [COLOR] |r| r.array()?;
+    String => [STRING] |r| r.string()?;
+    Hash => [HASH] |r| Hash(r.u32()?);
+    PathHash => [FILE] |r| PathHash(r.u64()?);
+    Link => [LINK] |r| Link(r.u32()?);
+}
+
+impl Decode for [f32; 16] {
+    fn decode(r: &mut Reader, ty: u8) -> Result<Self> {
+        r.expect(ty, &[MTX44])?;
+        let mut result = [0f32; 16];
+        for item in result.iter_mut() {
+            *item = r.f32()?;
+        }
+        Ok(result)
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(r: &mut Reader, ty: u8) -> Result<Self> {
+        r.expect(ty, &[LIST, LIST2])?;
+        let item = r.u8()?;
+        r.u32()?;
+        let count = r.u32()?;
+        (0..count).map(|_| T::decode(r, item)).collect()
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(r: &mut Reader, ty: u8) -> Result<Self> {
+        r.expect(ty, &[OPTION])?;
+        let item = r.u8()?;
+        match r.u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(T::decode(r, item)?)),
+        }
+    }
+}
+
+impl<K: Decode, V: Decode> Decode for Map<K, V> {
+    fn decode(r: &mut Reader, ty: u8) -> Result<Self> {
+        r.expect(ty, &[MAP])?;
+        let key = r.u8()?;
+        let value = r.u8()?;
+        r.u32()?;
+        let count = r.u32()?;
+        let items = (0..count)
+            .map(|_| Ok((K::decode(r, key)?, V::decode(r, value)?)))
+            .collect::<Result<_>>()?;
+        Ok(Map(items))
+    }
+}
+
+/// Value whose type the dump does not describe, skipped while decoding.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Skipped;
+
+impl Decode for Skipped {
+    fn decode(r: &mut Reader, ty: u8) -> Result<Self> {
+        r.skip(ty)?;
+        Ok(Skipped)
+    }
+}
+
+pub trait DecodeClass: Sized {
+    fn decode_class(r: &mut Reader, hash: u32) -> Result<Self>;
+}
+
+impl<T: DecodeClass> Decode for Pointer<T> {
+    fn decode(r: &mut Reader, ty: u8) -> Result<Self> {
+        r.expect(ty, &[POINTER])?;
+        match r.u32()? {
+            0 => Ok(Pointer(None)),
+            hash => {
+                r.u32()?;
+                Ok(Pointer(Some(Box::new(T::decode_class(r, hash)?))))
+            }
+        }
+    }
+}
+
+pub struct BinFile {
+    pub linked: Vec<String>,
+    pub entries: Vec<(u32, Object)>,
+}
+
+pub fn read_bin(data: &[u8]) -> Result<BinFile> {
+    let mut r = Reader::new(data);
+    let mut magic = r.array::<4>()?;
+    if &magic == b"PTCH" {
+        r.u64()?;
+        magic = r.array::<4>()?;
+    }
+    if &magic != b"PROP" {
+        return Err(DecodeError("Not a PROP bin".into()));
+    }
+    let version = r.u32()?;
+    let mut linked = Vec::new();
+    if version >= 2 {
+        for _ in 0..r.u32()? {
+            linked.push(r.string()?);
+        }
+    }
+    let count = r.u32()? as usize;
+    let classes = (0..count).map(|_| r.u32()).collect::<Result<Vec<_>>>()?;
+    let mut entries = Vec::with_capacity(count);
+    for class in classes {
+        r.u32()?;
+        let path = r.u32()?;
+        entries.push((path, Object::decode_class(&mut r, class)?));
+    }
+    Ok(BinFile { linked, entries })
+}
+"#;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+    "type", "unsafe", "use", "where", "while", "yield",
+];
+
+const PRELUDE_NAMES: &[&str] = &[
+    "Object",
+    "Unknown",
+    "Hash",
+    "PathHash",
+    "Link",
+    "Pointer",
+    "Skipped",
+    "Map",
+    "Reader",
+    "Result",
+    "DecodeError",
+    "Decode",
+    "DecodeClass",
+    "BinFile",
+    "String",
+    "Option",
+    "Vec",
+    "Box",
+    "Default",
+];
+
+struct Context<'a> {
+    meta: &'a MetaFile,
+    names: &'a HashNames,
+    class_names: BTreeMap<u32, String>,
+    pointer_names: BTreeMap<u32, String>,
+    pointers: BTreeSet<u32>,
+}
+
+impl<'a> Context<'a> {
+    /// Pointer enums are named `{Class}Ptr`, a class already called that
+    /// gets escaped like a keyword.
+    fn new(meta: &'a MetaFile, names: &'a HashNames) -> Self {
+        let pointer_names = meta
+            .classes
+            .keys()
+            .map(|&hash| {
+                let name = escape_ident(class_ident(names, hash), &[KEYWORDS, PRELUDE_NAMES]);
+                (hash, format!("{}Ptr", name))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let taken = pointer_names
+            .values()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let class_names = meta
+            .classes
+            .keys()
+            .map(|&hash| {
+                let taken: &[&[&str]] = &[KEYWORDS, PRELUDE_NAMES, &taken];
+                (hash, escape_ident(class_ident(names, hash), taken))
+            })
+            .collect();
+        Context {
+            meta,
+            names,
+            class_names,
+            pointer_names,
+            pointers: BTreeSet::new(),
+        }
+    }
+
+    fn class_name(&self, hash: u32) -> String {
+        self.class_names[&hash].clone()
+    }
+
+    fn pointer_name(&self, hash: u32) -> String {
+        self.pointer_names[&hash].clone()
+    }
+
+    /// Concrete classes a pointer to `hash` can hold.
+    fn pointer_variants(&self, hash: u32) -> Vec<u32> {
+        self.meta
+            .subclasses(hash)
+            .into_iter()
+            .filter(|&c| matches!(self.meta.class(c), Some(class) if !class.is.interface))
+            .collect()
+    }
+
+    fn nestable_type(&mut self, value_type: BinType, other_class: Option<u32>) -> String {
+        match (value_type, other_class) {
+            (BinType::Bool, _) => "bool".into(),
+            (BinType::I8, _) => "i8".into(),
+            (BinType::U8, _) => "u8".into(),
+            (BinType::I16, _) => "i16".into(),
+            (BinType::U16, _) => "u16".into(),
+            (BinType::I32, _) => "i32".into(),
+            (BinType::U32, _) => "u32".into(),
+            (BinType::I64, _) => "i64".into(),
+            (BinType::U64, _) => "u64".into(),
+            (BinType::F32, _) => "f32".into(),
+            (BinType::Vec2, _) => "[f32; 2]".into(),
+            (BinType::Vec3, _) => "[f32; 3]".into(),
+            (BinType::Vec4, _) => "[f32; 4]".into(),
+            (BinType::Mtx44, _) => "[f32; 16]".into(),
+            (BinType::Color, _) => "[u8; 4]".into(),
+            (BinType::String, _) => "String".into(),
+            (BinType::Hash, _) => "Hash".into(),
+            (BinType::File, _) => "PathHash".into(),
+            (BinType::Link, _) => "Link".into(),
+            (BinType::Flag, _) => "bool".into(),
+            (BinType::Embed, Some(other)) if self.meta.class(other).is_some() => {
+                self.class_name(other)
+            }
+            (BinType::Pointer, Some(other)) if self.meta.class(other).is_some() => {
+                self.pointers.insert(other);
+                format!("Pointer<{}>", self.pointer_name(other))
+            }
+            (BinType::Pointer, _) => "Pointer<Object>".into(),
+            _ => "Skipped".into(),
+        }
+    }
+
+    fn property_type(&mut self, property: &PropertyInfo) -> String {
+        match (property.value_type, &property.container, &property.map) {
+            (BinType::List | BinType::List2, Some(container), _) => {
+                let item = self.nestable_type(container.value_type, property.other_class);
+                format!("Vec<{}>", item)
+            }
+            (BinType::Option, Some(container), _) => {
+                let item = self.nestable_type(container.value_type, property.other_class);
+                format!("Option<{}>", item)
+            }
+            (BinType::Map, _, Some(map)) => {
+                let key = self.nestable_type(map.key_type, None);
+                let value = self.nestable_type(map.value_type, property.other_class);
+                format!("Map<{}, {}>", key, value)
+            }
+            _ => self.nestable_type(property.value_type, property.other_class),
+        }
+    }
+}
+
+fn floats_literal(value: &Value, count: usize) -> Option<String> {
+    let items = default_floats(value, count)?
+        .iter()
+        .map(|x| format!("{:?}", x))
+        .collect::<Vec<_>>();
+    Some(format!("[{}]", items.join(", ")))
+}
+
+fn nestable_literal(value: &Value, value_type: BinType) -> Option<String> {
+    Some(match value_type {
+        BinType::Bool | BinType::Flag => value.as_bool()?.to_string(),
+        BinType::I8 | BinType::I16 | BinType::I32 | BinType::I64 => value.as_i64()?.to_string(),
+        BinType::U8 | BinType::U16 | BinType::U32 | BinType::U64 => value.as_u64()?.to_string(),
+        BinType::F32 => format!("{:?}", value.as_f64()? as f32),
+        BinType::Vec2 => floats_literal(value, 2)?,
+        BinType::Vec3 => floats_literal(value, 3)?,
+        BinType::Vec4 => floats_literal(value, 4)?,
+        BinType::Mtx44 => floats_literal(value, 16)?,
+        BinType::Color => format!("{:?}", default_color(value)?),
+        BinType::String => format!("String::from({:?})", value.as_str()?),
+        BinType::Hash => format!("Hash({:#x})", default_hash(value)?),
+        BinType::File => format!("PathHash({:#x})", default_hash(value)?),
+        BinType::Link => format!("Link({:#x})", default_hash(value)?),
+        _ => return None,
+    })
+}
+
+fn default_literal(value: &Value, property: &PropertyInfo) -> Option<String> {
+    match (property.value_type, &property.container) {
+        (BinType::List | BinType::List2, Some(container)) => {
+            let items = value
+                .as_array()?
+                .iter()
+                .map(|x| nestable_literal(x, container.value_type))
+                .collect::<Option<Vec<_>>>()?;
+            Some(format!("vec![{}]", items.join(", ")))
+        }
+        (BinType::Option, Some(container)) => match value {
+            Value::Null => Some("None".into()),
+            _ => Some(format!(
+                "Some({})",
+                nestable_literal(value, container.value_type)?
+            )),
+        },
+        _ => nestable_literal(value, property.value_type),
+    }
+}
+
+struct Field {
+    hash: u32,
+    name: String,
+    ty: String,
+    default: Option<String>,
+}
+
+fn class_fields(ctx: &mut Context, hash: u32) -> Vec<Field> {
+    let mut used = BTreeSet::new();
+    let mut results = Vec::new();
+    for (_, property, info) in ctx.meta.all_properties(hash) {
+        let mut name = escape_ident(field_ident(ctx.names, property), &[KEYWORDS]);
+        if !used.insert(name.clone()) {
+            name = format!("{}_{:08x}", name, property);
+            used.insert(name.clone());
+        }
+        let ty = ctx.property_type(info);
+        let default = ctx
+            .meta
+            .default_value(hash, property)
+            .and_then(|value| default_literal(value, info));
+        results.push(Field {
+            hash: property,
+            name,
+            ty,
+            default,
+        });
+    }
+    results
+}
+
+fn write_class(out: &mut String, ctx: &mut Context, hash: u32) {
+    let name = ctx.class_name(hash);
+    let fields = class_fields(ctx, hash);
+
+    writeln!(out, "#[derive(Debug, Clone, PartialEq)]").unwrap();
+    writeln!(out, "pub struct {} {{", name).unwrap();
+    for field in &fields {
+        writeln!(out, "    pub {}: {},", field.name, field.ty).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "impl Default for {} {{", name).unwrap();
+    writeln!(out, "    fn default() -> Self {{").unwrap();
+    writeln!(out, "        Self {{").unwrap();
+    for field in &fields {
+        let default = field.default.as_deref().unwrap_or("Default::default()");
+        writeln!(out, "            {}: {},", field.name, default).unwrap();
+    }
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "impl {} {{", name).unwrap();
+    writeln!(out, "    pub const HASH: u32 = {:#x};", hash).unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "    pub fn decode_fields(&mut self, r: &mut Reader) -> Result<()> {{"
+    )
+    .unwrap();
+    writeln!(out, "        let count = r.u16()?;").unwrap();
+    writeln!(out, "        for _ in 0..count {{").unwrap();
+    writeln!(out, "            let name = r.u32()?;").unwrap();
+    writeln!(out, "            let ty = r.u8()?;").unwrap();
+    writeln!(out, "            match name {{").unwrap();
+    for field in &fields {
+        writeln!(
+            out,
+            "                {:#x} => self.{} = Decode::decode(r, ty)?,",
+            field.hash, field.name
+        )
+        .unwrap();
+    }
+    writeln!(out, "                _ => r.skip(ty)?,").unwrap();
+    writeln!(out, "            }}").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "        Ok(())").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "impl Decode for {} {{", name).unwrap();
+    writeln!(
+        out,
+        "    fn decode(r: &mut Reader, ty: u8) -> Result<Self> {{"
+    )
+    .unwrap();
+    writeln!(out, "        r.expect(ty, &[EMBED])?;").unwrap();
+    writeln!(out, "        let mut result = Self::default();").unwrap();
+    writeln!(out, "        let hash = r.u32()?;").unwrap();
+    writeln!(out, "        if hash != Self::HASH {{").unwrap();
+    writeln!(
+        out,
+        "            return Err(DecodeError(format!(\"Expected embed {{:#x}} got {{:#x}} at {{:#x}}\", Self::HASH, hash, r.pos)));"
+    )
+    .unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "        r.u32()?;").unwrap();
+    writeln!(out, "        result.decode_fields(r)?;").unwrap();
+    writeln!(out, "        Ok(result)").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+}
+
+fn write_enum(out: &mut String, ctx: &Context, name: &str, variants: &[u32]) {
+    writeln!(out, "#[derive(Debug, Clone, PartialEq)]").unwrap();
+    writeln!(out, "pub enum {} {{", name).unwrap();
+    for &variant in variants {
+        writeln!(out, "    {0}({0}),", ctx.class_name(variant)).unwrap();
+    }
+    writeln!(out, "    Unknown(u32),").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "impl DecodeClass for {} {{", name).unwrap();
+    writeln!(
+        out,
+        "    fn decode_class(r: &mut Reader, hash: u32) -> Result<Self> {{"
+    )
+    .unwrap();
+    writeln!(out, "        Ok(match hash {{").unwrap();
+    for &variant in variants {
+        writeln!(
+            out,
+            "            {0}::HASH => {{ let mut x = {0}::default(); x.decode_fields(r)?; Self::{0}(x) }}",
+            ctx.class_name(variant)
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "            _ => {{ r.skip_fields()?; Self::Unknown(hash) }}"
+    )
+    .unwrap();
+    writeln!(out, "        }})").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+}
+
+/// Emits a Rust module with one struct per class and a decoder for PROP bins.
+pub fn generate(meta: &MetaFile, names: &HashNames) -> String {
+    let mut ctx = Context::new(meta, names);
+    let mut body = String::new();
+    for &hash in meta.classes.keys() {
+        write_class(&mut body, &mut ctx, hash);
+    }
+
+    let concrete = meta
+        .classes
+        .iter()
+        .filter(|(_, class)| !class.is.interface)
+        .map(|(&hash, _)| hash)
+        .collect::<Vec<_>>();
+    write_enum(&mut body, &ctx, "Object", &concrete);
+    let pointers = ctx
+        .pointers
+        .iter()
+        .map(|&hash| (ctx.pointer_name(hash), ctx.pointer_variants(hash)))
+        .collect::<BTreeMap<_, _>>();
+    for (name, variants) in pointers {
+        write_enum(&mut body, &ctx, &name, &variants);
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// Generated from meta_{}.json", meta.version).unwrap();
+    out.push_str(PRELUDE);
+    writeln!(out).unwrap();
+    for code in BinType::ALL {
+        let name = format!("{:?}", code).to_uppercase();
+        writeln!(out, "pub const {}: u8 = {:#x};", name, code as u8).unwrap();
+    }
+    writeln!(out).unwrap();
+    out.push_str(&body);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::process::Command;
+
+    use super::*;
+    use crate::bin::{write_bin, BinEntry, BinField, BinFile, BinObject, BinValue};
+    use crate::hashes::fnv1a;
+    use crate::schema::testing::{class, container, map, meta, property};
+
+    /// Compiles the module generated for `meta` in a directory named after
+    /// `test`, with a `main` printing what `read_bin` returns for each of `bins`.
+    /// `None` when rustc is not installed.
+    fn decode_generated(
+        test: &str,
+        meta: &MetaFile,
+        names: &HashNames,
+        bins: &[BinFile],
+    ) -> Option<Vec<String>> {
+        let mut source = generate(meta, names);
+        source.push_str(
+            "fn main() {\n    for path in std::env::args().skip(1) {\n        let data = std::fs::read(path).unwrap();\n        println!(\"{:?}\", read_bin(&data).map(|x| x.entries));\n    }\n}\n",
+        );
+        let dir = std::env::temp_dir().join(format!("gen-rust-{}-{}", test, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("meta.rs"), source).unwrap();
+        let mut paths = Vec::new();
+        for (index, bin) in bins.iter().enumerate() {
+            let path = dir.join(format!("{}.bin", index));
+            std::fs::write(&path, write_bin(bin).unwrap()).unwrap();
+            paths.push(path);
+        }
+        let output = Command::new("rustc")
+            .args(["--edition", "2021", "-o"])
+            .arg(dir.join("meta"))
+            .arg(dir.join("meta.rs"))
+            .output();
+        let output = match output {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                std::fs::remove_dir_all(&dir).unwrap();
+                eprintln!("Skipping {}, rustc not found", test);
+                return None;
+            }
+            Err(e) => panic!("Failed to run rustc: {}", e),
+        };
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let output = Command::new(dir.join("meta"))
+            .args(&paths)
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let lines = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(String::from)
+            .collect();
+        Some(lines)
+    }
+
+    fn entry(class: u32, fields: Vec<(u32, BinValue)>) -> BinFile {
+        BinFile {
+            version: 3,
+            patch_header: None,
+            linked: Vec::new(),
+            entries: vec![BinEntry {
+                path: 0x30,
+                object: BinObject {
+                    class,
+                    fields: fields
+                        .into_iter()
+                        .map(|(hash, value)| BinField { hash, value })
+                        .collect(),
+                },
+            }],
+            patches: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn generated_module_compiles_and_decodes() {
+        // 0x99 is referenced but missing from the dump.
+        let holder = class(
+            None,
+            64,
+            vec![
+                (0x1, property(8, BinType::Embed, Some(0x99))),
+                (
+                    0x2,
+                    container(16, BinType::List, BinType::Embed, Some(0x99)),
+                ),
+                (0x3, property(32, BinType::Pointer, Some(0x20))),
+                (0x4, property(40, BinType::String, None)),
+                (0x5, map(48, BinType::Hash, BinType::Embed, Some(0x20))),
+                (0x6, container(56, BinType::Option, BinType::F32, None)),
+                (0xa, property(60, BinType::Embed, Some(0x20))),
+            ],
+        );
+        let item = class(None, 8, vec![(0x7, property(4, BinType::U32, None))]);
+        let meta = meta(vec![(0x10, holder), (0x20, item)]);
+
+        let missing = BinObject {
+            class: 0x99,
+            fields: vec![BinField {
+                hash: 0x8,
+                value: BinValue::U32(7),
+            }],
+        };
+        let skipped = entry(
+            0x10,
+            vec![
+                (0x1, BinValue::Embed(missing.clone())),
+                (
+                    0x2,
+                    BinValue::List(BinType::Embed, vec![BinValue::Embed(missing.clone())]),
+                ),
+                (0x9, BinValue::Embed(missing.clone())),
+                (0x4, BinValue::String("after skipped".into())),
+            ],
+        );
+        let mismatched = entry(0x10, vec![(0xa, BinValue::Embed(missing))]);
+        let bins = [skipped, mismatched];
+        let Some(output) = decode_generated("decode", &meta, &HashNames::new(), &bins) else {
+            return;
+        };
+        assert!(output[0].contains("\"after skipped\""), "{:?}", output);
+        assert!(output[0].contains("[Skipped]"), "{:?}", output);
+        assert!(
+            output[1].contains("Expected embed 0x20 got 0x99"),
+            "{:?}",
+            output
+        );
+    }
+
+    #[test]
+    fn pointer_enums_do_not_clash_with_classes() {
+        let (item, item_ptr) = (fnv1a("Item"), fnv1a("ItemPtr"));
+        let holder = class(
+            None,
+            16,
+            vec![
+                (0x1, property(0, BinType::Pointer, Some(item))),
+                (0x2, property(8, BinType::Embed, Some(item_ptr))),
+            ],
+        );
+        let meta = meta(vec![
+            (0x10, holder),
+            (item, class(None, 8, Vec::new())),
+            (item_ptr, class(None, 8, Vec::new())),
+        ]);
+        let mut names = HashNames::new();
+        names.insert(item, "Item");
+        names.insert(item_ptr, "ItemPtr");
+        let source = generate(&meta, &names);
+        assert!(source.contains("pub enum ItemPtr {"), "{}", source);
+        assert!(source.contains("pub struct ItemPtr_ {"), "{}", source);
+        let bin = entry(
+            0x10,
+            vec![(
+                0x2,
+                BinValue::Embed(BinObject {
+                    class: item_ptr,
+                    fields: Vec::new(),
+                }),
+            )],
+        );
+        let Some(output) = decode_generated("names", &meta, &names, &[bin]) else {
+            return;
+        };
+        assert!(output[0].starts_with("Ok("), "{:?}", output);
+    }
+}
@@ -0,0 +1,255 @@
+use serde_json::{json, Map, Value};
+
+use crate::codegen::json_key;
+use crate::hashes::HashNames;
+use crate::schema::{MetaFile, PropertyInfo};
+use crate::types::BinType;
+
+fn class_ref(names: &HashNames, hash: u32) -> Value {
+    let key = json_key(names, hash).replace('~', "~0").replace('/', "~1");
+    json!({ "$ref": format!("#/$defs/{}", key) })
+}
+
+fn integer(min: i128, max: i128) -> Value {
+    json!({ "type": "integer", "minimum": min, "maximum": max })
+}
+
+fn floats(count: usize) -> Value {
+    json!({
+        "type": "array",
+        "items": { "type": "number" },
+        "minItems": count,
+        "maxItems": count,
+    })
+}
+
+fn concrete_subclasses(meta: &MetaFile, hash: u32) -> Vec<u32> {
+    meta.subclasses(hash)
+        .into_iter()
+        .filter(|&c| matches!(meta.class(c), Some(class) if !class.is.interface))
+        .collect()
+}
+
+fn nestable_schema(
+    meta: &MetaFile,
+    names: &HashNames,
+    value_type: BinType,
+    other: Option<u32>,
+) -> Value {
+    let other = other.filter(|&c| meta.class(c).is_some());
+    match value_type {
+        BinType::Bool | BinType::Flag => json!({ "type": "boolean" }),
+        BinType::I8 => integer(i8::MIN.into(), i8::MAX.into()),
+        BinType::U8 => integer(u8::MIN.into(), u8::MAX.into()),
+        BinType::I16 => integer(i16::MIN.into(), i16::MAX.into()),
+        BinType::U16 => integer(u16::MIN.into(), u16::MAX.into()),
+        BinType::I32 => integer(i32::MIN.into(), i32::MAX.into()),
+        BinType::U32 => integer(u32::MIN.into(), u32::MAX.into()),
+        BinType::I64 => json!({ "type": "integer", "minimum": i64::MIN, "maximum": i64::MAX }),
+        BinType::U64 => json!({ "type": "integer", "minimum": u64::MIN, "maximum": u64::MAX }),
+        BinType::F32 => json!({ "type": "number" }),
+        BinType::Vec2 => floats(2),
+        BinType::Vec3 => floats(3),
+        BinType::Vec4 => floats(4),
+        BinType::Mtx44 => json!({
+            "type": "array",
+            "items": floats(4),
+            "minItems": 4,
+            "maxItems": 4,
+        }),
+        BinType::Color => json!({
+            "type": "array",
+            "items": integer(0, 255),
+            "minItems": 4,
+            "maxItems": 4,
+        }),
+        BinType::String | BinType::Hash | BinType::File | BinType::Link => {
+            json!({ "type": "string" })
+        }
+        BinType::Pointer => {
+            let mut variants = match other {
+                Some(other) => concrete_subclasses(meta, other)
+                    .into_iter()
+                    .map(|c| class_ref(names, c))
+                    .collect(),
+                None => vec![json!({ "type": "object" })],
+            };
+            variants.push(json!({ "type": "null" }));
+            json!({ "oneOf": variants })
+        }
+        BinType::Embed => match other {
+            Some(other) => class_ref(names, other),
+            None => json!({ "type": "object" }),
+        },
+        _ => json!({}),
+    }
+}
+
+fn key_schema(key: BinType) -> Value {
+    match key {
+        BinType::I8 | BinType::I16 | BinType::I32 | BinType::I64 => {
+            json!({ "pattern": "^-?[0-9]+$" })
+        }
+        BinType::U8 | BinType::U16 | BinType::U32 | BinType::U64 => {
+            json!({ "pattern": "^[0-9]+$" })
+        }
+        _ => json!({}),
+    }
+}
+
+fn property_schema(meta: &MetaFile, names: &HashNames, property: &PropertyInfo) -> Value {
+    match (property.value_type, &property.container, &property.map) {
+        (BinType::List | BinType::List2, Some(container), _) => {
+            let item = nestable_schema(meta, names, container.value_type, property.other_class);
+            let mut result = json!({ "type": "array", "items": item });
+            if let Some(count) = container.fixed_size {
+                result["minItems"] = count.into();
+                result["maxItems"] = count.into();
+            }
+            result
+        }
+        (BinType::Option, Some(container), _) => {
+            let item = nestable_schema(meta, names, container.value_type, property.other_class);
+            match container.value_type {
+                BinType::Pointer => item,
+                _ => json!({ "anyOf": [item, { "type": "null" }] }),
+            }
+        }
+        (BinType::Map, _, Some(map)) => json!({
+            "type": "object",
+            "propertyNames": key_schema(map.key_type),
+            "additionalProperties": nestable_schema(meta, names, map.value_type, property.other_class),
+        }),
+        _ => nestable_schema(meta, names, property.value_type, property.other_class),
+    }
+}
+
+/// Only `__class` is required, bins leave out fields holding their default
+/// and interfaces have no defaults to tell which those are.
+fn class_schema(meta: &MetaFile, names: &HashNames, hash: u32) -> Value {
+    let mut properties = Map::new();
+    properties.insert("__class".into(), json!({ "const": json_key(names, hash) }));
+    for (_, property, info) in meta.all_properties(hash) {
+        let key = json_key(names, property);
+        let mut schema = property_schema(meta, names, info);
+        schema["description"] = format!("{:#x} {:?}", property, info.value_type).into();
+        match meta.default_value(hash, property) {
+            Some(value) if info.value_type != BinType::Embed => schema["default"] = value.clone(),
+            _ => {}
+        }
+        properties.insert(key, schema);
+    }
+    json!({
+        "type": "object",
+        "description": format!("{:#x}", hash),
+        "properties": properties,
+        "required": ["__class"],
+        "additionalProperties": false,
+    })
+}
+
+/// Builds a JSON Schema (draft 2020-12) for bin JSON with one `$defs` entry per class.
+/// The root accepts an entry of any concrete class.
+pub fn generate(meta: &MetaFile, names: &HashNames) -> Value {
+    let mut defs = Map::new();
+    for &hash in meta.classes.keys() {
+        defs.insert(json_key(names, hash), class_schema(meta, names, hash));
+    }
+    let entries = meta
+        .classes
+        .iter()
+        .filter(|(_, class)| !class.is.interface)
+        .map(|(&hash, _)| class_ref(names, hash))
+        .collect::<Vec<_>>();
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": format!("bin JSON for meta_{}", meta.version),
+        "oneOf": entries,
+        "$defs": defs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashes::fnv1a;
+    use crate::schema::testing::{class, container, meta, property};
+
+    fn errors(schema: &Value, document: &Value) -> Vec<String> {
+        let validator = jsonschema::validator_for(schema).unwrap();
+        validator
+            .iter_errors(document)
+            .map(|x| x.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn class_names_are_escaped_in_refs() {
+        let odd = fnv1a("Odd/Name~1");
+        let holder = class(
+            None,
+            16,
+            vec![(0x1, property(8, BinType::Embed, Some(odd)))],
+        );
+        let meta = meta(vec![
+            (
+                odd,
+                class(None, 8, vec![(0x2, property(0, BinType::U8, None))]),
+            ),
+            (0x10, holder),
+        ]);
+        let mut names = HashNames::new();
+        names.insert(odd, "Odd/Name~1");
+        let schema = generate(&meta, &names);
+        assert_eq!(
+            schema["$defs"]["0x10"]["properties"]["0x1"]["$ref"],
+            "#/$defs/Odd~1Name~01"
+        );
+        let document = json!({
+            "__class": "0x10",
+            "0x1": {"__class": "Odd/Name~1", "0x2": 255},
+        });
+        assert_eq!(errors(&schema, &document), Vec::<String>::new());
+        let document = json!({"__class": "0x10", "0x1": {"__class": "Odd/Name~1", "0x2": 256}});
+        assert_eq!(errors(&schema, &document).len(), 1);
+    }
+
+    #[test]
+    fn defaults_are_annotated_and_fields_optional() {
+        let mut interface = class(
+            None,
+            8,
+            vec![
+                (0x1, property(0, BinType::U32, None)),
+                (0x2, container(4, BinType::Option, BinType::None, None)),
+            ],
+        );
+        interface["is"]["interface"] = json!(true);
+        interface["defaults"] = Value::Null;
+        let mut derived = class(
+            Some(0x10),
+            16,
+            vec![(0x3, property(8, BinType::String, None))],
+        );
+        derived["defaults"] = json!({"0x1": 5, "0x2": null, "0x3": "x"});
+        let meta = meta(vec![(0x10, interface), (0x20, derived)]);
+        let schema = generate(&meta, &HashNames::new());
+        let interface = &schema["$defs"]["0x10"];
+        assert_eq!(interface["required"], json!(["__class"]));
+        assert_eq!(interface["properties"]["0x1"].get("default"), None);
+        let derived = &schema["$defs"]["0x20"];
+        assert_eq!(derived["required"], json!(["__class"]));
+        assert_eq!(derived["properties"]["0x1"]["default"], 5);
+        assert_eq!(derived["properties"]["0x3"]["default"], "x");
+        assert_eq!(schema["oneOf"], json!([{"$ref": "#/$defs/0x20"}]));
+
+        for document in [
+            json!({"__class": "0x20"}),
+            json!({"__class": "0x20", "0x2": null}),
+            json!({"__class": "0x20", "0x1": 7, "0x2": 1, "0x3": "y"}),
+        ] {
+            assert_eq!(errors(&schema, &document), Vec::<String>::new());
+        }
+        assert_eq!(errors(&schema, &json!({"__class": "0x10"})).len(), 1);
+    }
+}
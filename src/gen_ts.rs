@@ -0,0 +1,420 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::codegen::{class_ident, escape_ident, ident, json_key};
+use crate::hashes::HashNames;
+use crate::schema::{MetaFile, PropertyInfo};
+use crate::types::BinType;
+
+const PRELUDE: &str = r#"// Objects carry their class in "__class", either its name or its 0x hash.
+// Field keys are property names, or 0x hashes when the name is unknown.
+export type Vec2 = [number, number];
+export type Vec3 = [number, number, number];
+export type Vec4 = [number, number, number, number];
+export type Mtx44 = [Vec4, Vec4, Vec4, Vec4];
+export type Color = [number, number, number, number];
+export type Hash = string;
+export type PathHash = string;
+export type Link = string;
+"#;
+
+/// Reserved words and predefined types, none of which can name a type.
+const KEYWORDS: &[&str] = &[
+    "any",
+    "bigint",
+    "boolean",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "declare",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "never",
+    "new",
+    "null",
+    "number",
+    "object",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "string",
+    "super",
+    "switch",
+    "symbol",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "undefined",
+    "unknown",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+const PRELUDE_NAMES: &[&str] = &[
+    "Vec2", "Vec3", "Vec4", "Mtx44", "Color", "Hash", "PathHash", "Link", "Entry", "Record",
+    "Array", "Object", "String", "Number", "Boolean",
+];
+
+/// Type names of every class. Each class also declares `{Name}Fields` and
+/// may get a `{Name}Pointer` union, a class already called that gets escaped
+/// like a keyword.
+fn type_names(meta: &MetaFile, names: &HashNames) -> BTreeMap<u32, String> {
+    let generated = meta
+        .classes
+        .keys()
+        .flat_map(|&hash| {
+            let name = escape_ident(class_ident(names, hash), &[KEYWORDS, PRELUDE_NAMES]);
+            [format!("{}Fields", name), format!("{}Pointer", name)]
+        })
+        .collect::<Vec<_>>();
+    let generated = generated.iter().map(String::as_str).collect::<Vec<_>>();
+    meta.classes
+        .keys()
+        .map(|&hash| {
+            let taken: &[&[&str]] = &[KEYWORDS, PRELUDE_NAMES, &generated];
+            (hash, escape_ident(class_ident(names, hash), taken))
+        })
+        .collect()
+}
+
+struct Context<'a> {
+    meta: &'a MetaFile,
+    names: &'a HashNames,
+    type_names: BTreeMap<u32, String>,
+}
+
+impl<'a> Context<'a> {
+    fn type_name(&self, hash: u32) -> String {
+        self.type_names[&hash].clone()
+    }
+}
+
+fn property_key(names: &HashNames, hash: u32) -> String {
+    let key = json_key(names, hash);
+    if ident(&key) == key {
+        key
+    } else {
+        serde_json::to_string(&key).unwrap()
+    }
+}
+
+fn nestable_type(ctx: &Context, value_type: BinType, other: Option<u32>) -> String {
+    let other = other.filter(|&c| ctx.meta.class(c).is_some());
+    match value_type {
+        BinType::Bool | BinType::Flag => "boolean".into(),
+        BinType::I8
+        | BinType::U8
+        | BinType::I16
+        | BinType::U16
+        | BinType::I32
+        | BinType::U32
+        | BinType::I64
+        | BinType::U64
+        | BinType::F32 => "number".into(),
+        BinType::Vec2 => "Vec2".into(),
+        BinType::Vec3 => "Vec3".into(),
+        BinType::Vec4 => "Vec4".into(),
+        BinType::Mtx44 => "Mtx44".into(),
+        BinType::Color => "Color".into(),
+        BinType::String => "string".into(),
+        BinType::Hash => "Hash".into(),
+        BinType::File => "PathHash".into(),
+        BinType::Link => "Link".into(),
+        BinType::Pointer => match other {
+            Some(other) => format!("{}Pointer | null", ctx.type_name(other)),
+            None => "unknown".into(),
+        },
+        BinType::Embed => match other {
+            Some(other) => ctx.type_name(other),
+            None => "unknown".into(),
+        },
+        _ => "unknown".into(),
+    }
+}
+
+fn key_type(key: BinType) -> &'static str {
+    match key {
+        BinType::I8
+        | BinType::U8
+        | BinType::I16
+        | BinType::U16
+        | BinType::I32
+        | BinType::U32
+        | BinType::I64
+        | BinType::U64
+        | BinType::F32 => "number",
+        _ => "string",
+    }
+}
+
+fn property_type(ctx: &Context, property: &PropertyInfo) -> String {
+    match (property.value_type, &property.container, &property.map) {
+        (BinType::List | BinType::List2, Some(container), _) => {
+            let item = nestable_type(ctx, container.value_type, property.other_class);
+            match container.value_type {
+                BinType::Pointer => format!("({})[]", item),
+                _ => format!("{}[]", item),
+            }
+        }
+        (BinType::Option, Some(container), _) => {
+            let item = nestable_type(ctx, container.value_type, property.other_class);
+            match container.value_type {
+                BinType::Pointer => item,
+                _ => format!("{} | null", item),
+            }
+        }
+        (BinType::Map, _, Some(map)) => {
+            let value = nestable_type(ctx, map.value_type, property.other_class);
+            format!("Record<{}, {}>", key_type(map.key_type), value)
+        }
+        _ => nestable_type(ctx, property.value_type, property.other_class),
+    }
+}
+
+fn concrete_subclasses(meta: &MetaFile, hash: u32) -> Vec<u32> {
+    meta.subclasses(hash)
+        .into_iter()
+        .filter(|&c| matches!(meta.class(c), Some(class) if !class.is.interface))
+        .collect()
+}
+
+fn write_class(out: &mut String, ctx: &Context, hash: u32) {
+    let (meta, names) = (ctx.meta, ctx.names);
+    let class = match meta.class(hash) {
+        Some(class) => class,
+        None => return,
+    };
+    let name = ctx.type_name(hash);
+    let bases = meta
+        .direct_bases(hash)
+        .into_iter()
+        .filter(|&(base, _)| meta.class(base).is_some())
+        .map(|(base, _)| format!("{}Fields", ctx.type_name(base)))
+        .collect::<Vec<_>>();
+
+    writeln!(out, "/** {:#x} */", hash).unwrap();
+    write!(out, "export interface {}Fields", name).unwrap();
+    if !bases.is_empty() {
+        write!(out, " extends {}", bases.join(", ")).unwrap();
+    }
+    writeln!(out, " {{").unwrap();
+    for (&property, info) in &class.properties {
+        write!(out, "    /** {:#x} {:?}", property, info.value_type).unwrap();
+        match meta.default_value(hash, property) {
+            Some(value) if info.value_type != BinType::Embed => {
+                // `\/` is the same string in JSON and can not end the comment.
+                let value = value.to_string().replace("*/", "*\\/");
+                write!(out, " @default {}", value).unwrap();
+            }
+            _ => {}
+        }
+        writeln!(out, " */").unwrap();
+        writeln!(
+            out,
+            "    {}?: {};",
+            property_key(names, property),
+            property_type(ctx, info)
+        )
+        .unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(
+        out,
+        "export type {} = {}Fields & {{ __class: {} }};",
+        name,
+        name,
+        serde_json::to_string(&json_key(names, hash)).unwrap()
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+}
+
+fn write_union(out: &mut String, ctx: &Context, name: &str, variants: &[u32]) {
+    let variants = variants
+        .iter()
+        .map(|&c| ctx.type_name(c))
+        .collect::<Vec<_>>();
+    if variants.is_empty() {
+        writeln!(out, "export type {} = never;", name).unwrap();
+    } else {
+        writeln!(
+            out,
+            "export type {} =\n    | {};",
+            name,
+            variants.join("\n    | ")
+        )
+        .unwrap();
+    }
+}
+
+/// Emits TypeScript declarations describing bin JSON for every class, class
+/// defaults become `@default` tags.
+pub fn generate(meta: &MetaFile, names: &HashNames) -> String {
+    let mut out = String::new();
+    writeln!(out, "// Generated from meta_{}.json", meta.version).unwrap();
+    out.push_str(PRELUDE);
+    writeln!(out).unwrap();
+
+    let ctx = Context {
+        meta,
+        names,
+        type_names: type_names(meta, names),
+    };
+    let mut pointers = BTreeSet::new();
+    for (&hash, class) in &meta.classes {
+        write_class(&mut out, &ctx, hash);
+        for property in class.properties.values() {
+            let is_pointer = property.value_type == BinType::Pointer
+                || matches!(&property.container, Some(c) if c.value_type == BinType::Pointer)
+                || matches!(&property.map, Some(m) if m.value_type == BinType::Pointer);
+            if let Some(other) = property.other_class.filter(|_| is_pointer) {
+                if meta.class(other).is_some() {
+                    pointers.insert(other);
+                }
+            }
+        }
+    }
+    for hash in pointers {
+        let name = format!("{}Pointer", ctx.type_name(hash));
+        write_union(&mut out, &ctx, &name, &concrete_subclasses(meta, hash));
+        writeln!(out).unwrap();
+    }
+    let concrete = meta
+        .classes
+        .iter()
+        .filter(|(_, class)| !class.is.interface)
+        .map(|(&hash, _)| hash)
+        .collect::<Vec<_>>();
+    write_union(&mut out, &ctx, "Entry", &concrete);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::hashes::fnv1a;
+    use crate::schema::testing::{class, container, map, meta, property};
+
+    fn assert_contains(source: &str, lines: &[&str]) {
+        for line in lines {
+            assert!(source.contains(line), "{}\n{}", line, source);
+        }
+    }
+
+    #[test]
+    fn generated_names_do_not_clash() {
+        let h = fnv1a;
+        let foo = class(
+            None,
+            16,
+            vec![
+                (h("mPtr"), property(0, BinType::Pointer, Some(h("Foo")))),
+                (h("m.A"), property(8, BinType::U8, None)),
+                (h("class"), property(12, BinType::U32, None)),
+            ],
+        );
+        let mut classes = vec![(h("Foo"), foo)];
+        let mut names = HashNames::new();
+        for name in ["Foo", "mPtr", "m.A", "class"] {
+            names.insert(h(name), name);
+        }
+        for name in ["FooPointer", "FooFields", "string", "Record"] {
+            classes.push((h(name), class(None, 8, Vec::new())));
+            names.insert(h(name), name);
+        }
+        let source = generate(&meta(classes), &names);
+        assert_contains(
+            &source,
+            &[
+                "export interface FooFields {",
+                "    mPtr?: FooPointer | null;",
+                "    \"m.A\"?: number;",
+                "    class?: number;",
+                "export type Foo = FooFields & { __class: \"Foo\" };",
+                "export type FooPointer =\n    | Foo;",
+                "export type FooPointer_ = FooPointer_Fields & { __class: \"FooPointer\" };",
+                "export type FooFields_ = FooFields_Fields & { __class: \"FooFields\" };",
+                "export type string_ = string_Fields & { __class: \"string\" };",
+                "export type Record_ = Record_Fields & { __class: \"Record\" };",
+            ],
+        );
+        assert!(!source.contains("export type FooFields "), "{}", source);
+    }
+
+    #[test]
+    fn defaults_are_documented() {
+        let mut base = class(
+            None,
+            64,
+            vec![
+                (0x1, property(0, BinType::U32, None)),
+                (0x2, property(4, BinType::String, None)),
+                (0x3, property(16, BinType::Vec2, None)),
+                (0x4, container(24, BinType::Option, BinType::F32, None)),
+                (0x5, map(32, BinType::U8, BinType::U8, None)),
+                (0x6, property(56, BinType::Embed, Some(0x30))),
+            ],
+        );
+        base["defaults"] = json!({
+            "0x1": 5,
+            "0x2": "end */ here",
+            "0x3": [0.5, 1],
+            "0x4": null,
+            "0x6": {"__class": "0x30", "0x7": 1},
+        });
+        let mut derived = class(
+            Some(0x10),
+            72,
+            vec![(0x8, property(64, BinType::U32, None))],
+        );
+        derived["defaults"] = json!({"0x1": 6, "0x8": 9});
+        let inner = class(None, 4, vec![(0x7, property(0, BinType::U32, None))]);
+        let meta = meta(vec![(0x10, base), (0x20, derived), (0x30, inner)]);
+        let source = generate(&meta, &HashNames::new());
+        assert_contains(
+            &source,
+            &[
+                "    /** 0x1 U32 @default 5 */\n    \"0x1\"?: number;",
+                "    /** 0x2 String @default \"end *\\/ here\" */",
+                "    /** 0x3 Vec2 @default [0.5,1] */\n    \"0x3\"?: Vec2;",
+                "    /** 0x4 Option @default null */\n    \"0x4\"?: number | null;",
+                "    /** 0x5 Map */\n    \"0x5\"?: Record<number, number>;",
+                "    /** 0x6 Embed */\n    \"0x6\"?: Class_00000030;",
+                "export interface Class_00000020Fields extends Class_00000010Fields {\n    /** 0x8 U32 @default 9 */",
+                "    /** 0x7 U32 */",
+            ],
+        );
+        assert_eq!(source.matches("*/").count(), source.matches("/**").count());
+    }
+}
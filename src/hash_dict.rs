@@ -0,0 +1,247 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::hashes::{fnv1a, parse_hex, xxh64, HashNames};
+use crate::name_recovery::{dump_hashes, is_resolved, HashKind};
+use crate::schema::MetaFile;
+
+/// One line of a hash list, kept with where it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictEntry {
+    pub hash: u64,
+    pub name: String,
+    /// XXH64 file path rather than an FNV-1a name, written with more than 8 digits.
+    pub is_file: bool,
+    /// Text after ` # `, like the provenance brute-force writes.
+    pub comment: Option<String>,
+    /// `file:line` the entry was read from.
+    pub source: String,
+}
+
+impl DictEntry {
+    pub fn expected_hash(&self) -> u64 {
+        if self.is_file {
+            xxh64(&self.name)
+        } else {
+            fnv1a(&self.name) as u64
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.expected_hash() == self.hash
+    }
+
+    /// Line in the `<hex> <name>` format `--hashes` loads.
+    pub fn line(&self) -> String {
+        let hash = if self.is_file {
+            format!("{:016x}", self.hash)
+        } else {
+            format!("{:08x}", self.hash)
+        };
+        match &self.comment {
+            Some(comment) => format!("{} {} # {}", hash, self.name, comment),
+            None => format!("{} {}", hash, self.name),
+        }
+    }
+}
+
+/// Names sharing a hash that are not the same name in another case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    pub hash: u64,
+    pub is_file: bool,
+    pub entries: Vec<DictEntry>,
+}
+
+/// Every entry of several hash lists, duplicates and wrong ones included.
+#[derive(Debug, Clone, Default)]
+pub struct HashDict {
+    pub entries: Vec<DictEntry>,
+}
+
+impl HashDict {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        for (index, line) in text.lines().enumerate() {
+            let (line, comment) = match line.split_once(" # ") {
+                Some((line, comment)) => (line, Some(comment.trim().to_string())),
+                None => (line, None),
+            };
+            if let Some((hex, name)) = line.trim().split_once(' ') {
+                if let Some(hash) = parse_hex(hex) {
+                    self.entries.push(DictEntry {
+                        hash,
+                        name: name.trim().to_string(),
+                        is_file: hex.trim_start_matches("0x").len() > 8,
+                        comment,
+                        source: format!("{}:{}", path.display(), index + 1),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Entries whose name does not hash to their hash.
+    pub fn invalid(&self) -> Vec<&DictEntry> {
+        self.entries.iter().filter(|x| !x.is_valid()).collect()
+    }
+
+    /// Valid entries grouped by hash, where the names differ by more than case.
+    pub fn collisions(&self) -> Vec<Collision> {
+        let mut groups: BTreeMap<(bool, u64), Vec<&DictEntry>> = BTreeMap::new();
+        for entry in self.entries.iter().filter(|x| x.is_valid()) {
+            groups
+                .entry((entry.is_file, entry.hash))
+                .or_default()
+                .push(entry);
+        }
+        groups
+            .into_iter()
+            .filter_map(|((is_file, hash), entries)| {
+                let distinct: BTreeSet<String> = entries
+                    .iter()
+                    .map(|x| x.name.to_ascii_lowercase())
+                    .collect();
+                (distinct.len() > 1).then(|| Collision {
+                    hash,
+                    is_file,
+                    entries: entries.into_iter().cloned().collect(),
+                })
+            })
+            .collect()
+    }
+
+    /// One valid entry per hash, the first loaded wins, sorted with names
+    /// before file paths.
+    pub fn merged(&self) -> Vec<DictEntry> {
+        let mut results: BTreeMap<(bool, u64), DictEntry> = BTreeMap::new();
+        for entry in self.entries.iter().filter(|x| x.is_valid()) {
+            results
+                .entry((entry.is_file, entry.hash))
+                .or_insert_with(|| entry.clone());
+        }
+        results.into_values().collect()
+    }
+
+    /// Merged entries for the class, property, link and file hashes of `meta`.
+    pub fn relevant(&self, meta: &MetaFile) -> Vec<DictEntry> {
+        let hashes = dump_hashes(meta);
+        self.merged()
+            .into_iter()
+            .filter(|x| hashes.contains_key(&(x.is_file, x.hash)))
+            .collect()
+    }
+}
+
+/// Resolved and total hashes of `meta` per kind.
+pub fn coverage(meta: &MetaFile, names: &HashNames) -> BTreeMap<HashKind, (usize, usize)> {
+    let mut results: BTreeMap<HashKind, (usize, usize)> = BTreeMap::new();
+    for (key, info) in dump_hashes(meta) {
+        let counts = results.entry(info.kind).or_default();
+        counts.0 += is_resolved(names, key) as usize;
+        counts.1 += 1;
+    }
+    results
+}
+
+/// Indented line per kind like `class: 950 of 1000 (95.0%)`.
+pub fn coverage_text(coverage: &BTreeMap<HashKind, (usize, usize)>) -> String {
+    let mut out = String::new();
+    for (kind, &(resolved, total)) in coverage {
+        let percent = resolved as f64 * 100.0 / total as f64;
+        writeln!(
+            out,
+            "    {}: {} of {} ({:.1}%)",
+            kind.name(),
+            resolved,
+            total,
+            percent
+        )
+        .unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::{class, meta, property};
+    use crate::types::BinType;
+
+    #[test]
+    fn wrong_names_and_collisions_are_reported() {
+        let dir = std::env::temp_dir().join(format!("hash-dict-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let first = format!(
+            "83d674d7 mBxfrw\n{:08x} mName # from brute-force\n12345678 mWrong\n{:016x} data/a.bin\n",
+            fnv1a("mName"),
+            xxh64("data/a.bin")
+        );
+        fs::write(dir.join("a.txt"), first).unwrap();
+        let second = format!(
+            "83d674d7 mXkexa\n{:08x} MNAME\nnot a line\n{:016x} data/b.bin\n",
+            fnv1a("mName"),
+            xxh64("data/a.bin")
+        );
+        fs::write(dir.join("b.txt"), second).unwrap();
+        let mut dict = HashDict::new();
+        let loaded = dict
+            .load(dir.join("a.txt"))
+            .and_then(|_| dict.load(dir.join("b.txt")));
+        fs::remove_dir_all(&dir).unwrap();
+        loaded.unwrap();
+        assert_eq!(dict.entries.len(), 7);
+
+        let invalid = dict.invalid();
+        let invalid = invalid
+            .iter()
+            .map(|x| (x.name.as_str(), x.source.ends_with(":3")))
+            .collect::<Vec<_>>();
+        assert_eq!(invalid, vec![("mWrong", true), ("data/b.bin", false)]);
+
+        // mBxfrw and mXkexa share an FNV-1a hash, MNAME is only another case.
+        let collisions = dict.collisions();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(
+            (collisions[0].hash, collisions[0].is_file),
+            (0x83d674d7, false)
+        );
+        let names = collisions[0].entries.iter().map(|x| x.name.as_str());
+        assert_eq!(names.collect::<Vec<_>>(), vec!["mBxfrw", "mXkexa"]);
+
+        let merged = dict.merged();
+        let lines = merged.iter().map(|x| x.line()).collect::<Vec<_>>();
+        let mut expected = vec![
+            "83d674d7 mBxfrw".to_string(),
+            format!("{:08x} mName # from brute-force", fnv1a("mName")),
+        ];
+        expected.sort();
+        expected.push(format!("{:016x} data/a.bin", xxh64("data/a.bin")));
+        assert_eq!(lines, expected);
+
+        let item = class(
+            None,
+            8,
+            vec![(fnv1a("mName"), property(0, BinType::U32, None))],
+        );
+        let meta = meta(vec![(fnv1a("Item"), item)]);
+        let relevant = dict.relevant(&meta);
+        assert_eq!(relevant.len(), 1);
+        assert_eq!(relevant[0].name, "mName");
+        let mut names = HashNames::new();
+        names.insert(fnv1a("mName"), "mName");
+        assert_eq!(
+            coverage_text(&coverage(&meta, &names)),
+            "    class: 0 of 1 (0.0%)\n    property: 1 of 1 (100.0%)\n"
+        );
+    }
+}
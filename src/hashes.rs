@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub fn parse_hex(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u64::from_str_radix(digits, 16).ok()
+}
+
+/// Lowercase FNV-1a, used for class, property and entry hashes.
+pub fn fnv1a(text: &str) -> u32 {
+    fnv1a_extend(0x811c9dc5, text)
+}
+
+/// Continues `hash` of some prefix with `text`, so shared prefixes are hashed once.
+pub fn fnv1a_extend(mut hash: u32, text: &str) -> u32 {
+    for byte in text.bytes() {
+        hash ^= byte.to_ascii_lowercase() as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
+/// Lowercase XXH64, used for file paths.
+pub fn xxh64(text: &str) -> u64 {
+    xxhash_rust::xxh64::xxh64(text.to_ascii_lowercase().as_bytes(), 0)
+}
+
+/// Parses `0x` prefixed hashes as is and hashes anything else as a name.
+pub fn name_or_hash(text: &str) -> u32 {
+    match text
+        .strip_prefix("0x")
+        .and_then(|x| u32::from_str_radix(x, 16).ok())
+    {
+        Some(hash) => hash,
+        None => fnv1a(text),
+    }
+}
+
+/// Hash to name lookup loaded from `<hex> <name>` lists. Hashes longer than
+/// 8 digits are XXH64 file paths.
+#[derive(Debug, Default, Clone)]
+pub struct HashNames {
+    names: HashMap<u32, String>,
+    files: HashMap<u64, String>,
+}
+
+impl HashNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let text = fs::read_to_string(path)?;
+        for line in text.lines() {
+            // Anything after ` # ` is a comment, like where the name came from.
+            let line = line.split_once(" # ").map_or(line, |(x, _)| x);
+            if let Some((hex, name)) = line.trim().split_once(' ') {
+                match parse_hex(hex) {
+                    Some(hash) if hex.trim_start_matches("0x").len() > 8 => {
+                        self.insert_file(hash, name.trim())
+                    }
+                    Some(hash) => self.insert(hash as u32, name.trim()),
+                    None => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn insert(&mut self, hash: u32, name: &str) {
+        self.names.insert(hash, name.to_string());
+    }
+
+    pub fn get(&self, hash: u32) -> Option<&str> {
+        self.names.get(&hash).map(|x| x.as_str())
+    }
+
+    pub fn insert_file(&mut self, hash: u64, path: &str) {
+        self.files.insert(hash, path.to_string());
+    }
+
+    pub fn get_file(&self, hash: u64) -> Option<&str> {
+        self.files.get(&hash).map(|x| x.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len() + self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.files.is_empty()
+    }
+}
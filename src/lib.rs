@@ -1,19 +1,41 @@
 #![feature(abi_thiscall)]
 #![allow(dead_code)]
 
+mod hashes;
 mod meta;
 mod meta_dump;
 mod native;
+mod type_expr;
+mod types;
 
 use std::fs::{self, File};
 use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use serde_json::json;
+use serde_json::{json, Value};
 use winapi::um::winnt::DLL_PROCESS_ATTACH;
 
 const PATTERN: &str = "83 3D ? ? ? ? FF 75 DF 33 C0 48 8D 0D ? ? ? ? 48 89 05 $ { ' } 48 89 05";
 type MetaVector = meta::RiotVector<&'static meta::Class>;
 
+fn dump_source(info: &native::ModuleInfo, registry: usize) -> Value {
+    let dumped_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default();
+    json!({
+        "sha256": info.sha256,
+        "time_date_stamp": info.time_date_stamp,
+        "image_base": meta_dump::dump_hex(info.image_base),
+        "size_of_image": info.size_of_image,
+        "version_info": info.version_strings,
+        "signature": PATTERN,
+        "registry": meta_dump::dump_hex(registry - info.base),
+        "dumper": env!("CARGO_PKG_VERSION"),
+        "dumped_at": dumped_at,
+    })
+}
+
 fn main() {
     let folder = "meta";
     native::alloc_console();
@@ -24,21 +46,23 @@ fn main() {
     println!("Base: {:#X}", info.base);
     println!("ImageSize: {:#X}", info.image_size);
     println!("Version: {}", &info.version);
+    println!("SHA256: {}", info.sha256.as_deref().unwrap_or("unknown"));
 
     println!("Stopping other threads!");
     native::pause_threads();
 
     println!("Finding metaclasses...");
-    let classes = info
-        .scan_memory(PATTERN)
-        .map(|x| x as *const MetaVector)
-        .and_then(|x| unsafe { x.as_ref() })
+    let registry = info.scan_memory(PATTERN).expect("Failed to find metaclasses");
+    let classes = unsafe { (registry as *const MetaVector).as_ref() }
         .expect("Failed to find metaclasses");
 
     println!("Processing classes...");
     let meta_info = json!({
+        "format": types::FORMAT_VERSION,
         "version": info.version,
+        "source": dump_source(&info, registry),
         "classes": meta_dump::dump_class_list(info.base, classes.slice()),
+        "duplicates": meta_dump::dump_duplicates(classes.slice()),
     });
 
     println!("Serializing classes...");
@@ -0,0 +1,809 @@
+#![allow(dead_code)]
+
+mod bin;
+mod bin_check;
+mod bin_defaults;
+mod bin_diff;
+mod bin_index;
+mod bin_merge;
+mod bin_migrate;
+mod bin_patch;
+mod bin_path;
+mod bin_text;
+mod brute_force;
+mod codegen;
+mod gen_cpp;
+mod gen_cs;
+mod gen_py;
+mod gen_rust;
+mod gen_schema;
+mod gen_ts;
+mod hash_dict;
+mod hashes;
+mod meta_check;
+mod name_recovery;
+mod name_suggest;
+mod schema;
+mod type_expr;
+mod types;
+mod unresolved;
+mod wad;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use std::sync::Mutex;
+use std::thread;
+
+use bin::{BinEntry, BinFile};
+use hashes::HashNames;
+use schema::MetaFile;
+
+const USAGE: &str = "Usage: lolmetadumper2 <command> [options]
+
+Commands:
+    cpp <meta.json>     Generate C++ header with verified offsets
+    rust <meta.json>    Generate Rust module with a typed .bin decoder
+    cs <meta.json>      Generate C# model classes
+    py <meta.json>      Generate Python dataclasses and a class registry
+    ts <meta.json>      Generate TypeScript declarations for bin JSON
+    bin-schema <meta.json>
+                        Generate JSON Schema for bin JSON documents
+    meta-schema         Print JSON Schema of the meta dump format
+    meta-check <meta.json>
+                        Report duplicate classes, properties shadowing or
+                        inherited twice from bases and class hashes that are
+                        also property hashes, with their inheritance path
+    check <meta.json> <file.bin>...
+                        Validate .bin files, or the bins inside WAD archives,
+                        against the dumped classes
+    patch <meta.json> <base.bin> <patch.bin>...
+                        Apply PTCH overrides to a base bin and write the result
+    bin-to-text <file.bin>
+                        Print a .bin in ritobin text format
+    text-to-bin <meta.json> <file.py>
+                        Parse ritobin text, inferring omitted types from the dump
+    default-bin <meta.json> <class>
+                        Write a .bin with one entry of <class> filled with defaults,
+                        pointers are left out as the dump does not know theirs
+    minimize <meta.json> <file.bin>
+                        Remove fields that equal their class default, pointers
+                        are always kept
+    materialize <meta.json> <file.bin>
+                        Add every missing field with its class default, except
+                        pointers
+    migrate <old.json> <new.json> <file.bin>
+                        Rewrite a .bin for the classes of a newer dump
+    merge <meta.json> <base.bin> <ours.bin> <theirs.bin>
+                        Three-way merge of two edits of the same .bin
+    diff <meta.json> <old.bin> <new.bin>
+                        Compare two .bin files by entry and property path
+    index <meta.json> <dir>
+                        Property usage statistics over every .bin below <dir>,
+                        including the bins inside WAD archives
+    wad-list <file.wad.client>
+                        List the entries of a WAD archive with their paths
+    wad-paths <file.wad.client>...
+                        Write the entry paths recovered from the bins of WAD
+                        archives as a hash list
+    recover-names <meta.json> <file>...
+                        Hash the strings of executables, .bin files and WAD
+                        archives and write the names of unresolved hashes
+    brute-force <meta.json> <wordlist>...
+                        Join words from the wordlists and names already known
+                        into CamelCase names for unresolved hashes, on all cores
+    dict-merge <list>...
+                        Merge hash lists, dropping names that do not match
+                        their hash, the first list wins on collisions
+    dict-verify <list>...
+                        Report names that do not match their hash and hashes
+                        with several names
+    dict-export <meta.json> <list>...
+                        Write only the names the dump refers to
+    dict-coverage <meta.json>...
+                        Resolved class, property, link and file hashes per dump
+    unresolved <meta.json>...
+                        List the class, property, link and file hashes of each
+                        dump without a name, with their context and the first
+                        dump using them, dumps given oldest first
+    suggest-names <meta.json>
+                        Suggest names for unresolved properties from their
+                        siblings, classes and type, verified by hash
+
+Options:
+    --hashes <file>     Load hash names from <hex> <name> list, can be repeated
+    --output <file>     Write result to file instead of stdout
+    --entry <path>      Entry path for default-bin, defaults to the class name
+    --epsilon <value>   Float tolerance for minimize and diff, defaults to 1e-6
+    --format <format>   Output of diff and index, text or json, defaults to text
+    --min-length <n>    Shortest string recover-names extracts, defaults to 4
+    --max-words <n>     Words joined per name by brute-force, defaults to 2
+    --max-suffix <n>    Also try numeric suffixes 0 to <n> in brute-force
+    --threads <n>       Worker threads, defaults to every core
+    --resume <file>     Progress file of brute-force, hits are appended to
+                        --output when continuing, refused for other words or
+                        settings
+    --output-dir <dir>  Write one unresolved-<version>.tsv per dump into <dir>
+    --set-list <name>   Merge this list property by membership instead of index,
+                        can be repeated
+";
+
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Self {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        while let Some(arg) = args.next() {
+            if let Some(key) = arg.strip_prefix("--") {
+                let value = args
+                    .next()
+                    .unwrap_or_else(|| fail(&format!("Missing value for --{}", key)));
+                options.push((key.to_string(), value));
+            } else {
+                positional.push(arg);
+            }
+        }
+        Self {
+            positional,
+            options,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn get_all(&self, key: &str) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    fn input(&self, index: usize) -> &str {
+        self.positional
+            .get(index)
+            .map(|x| x.as_str())
+            .unwrap_or_else(|| fail(USAGE))
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+const META_SCHEMA: &str = include_str!("../schema/meta.schema.json");
+
+fn load_meta(path: &str) -> MetaFile {
+    let meta =
+        MetaFile::load(path).unwrap_or_else(|e| fail(&format!("Failed to load {}: {}", path, e)));
+    match meta.format {
+        Some(format) if format > types::FORMAT_VERSION => fail(&format!(
+            "Unsupported format {} in {}, expected at most {}",
+            format,
+            path,
+            types::FORMAT_VERSION
+        )),
+        _ => meta,
+    }
+}
+
+fn load_bin(path: &str) -> BinFile {
+    BinFile::load(path).unwrap_or_else(|e| fail(&format!("Failed to load {}: {}", path, e)))
+}
+
+fn encode_bin(bin: &BinFile) -> Vec<u8> {
+    bin::write_bin(bin).unwrap_or_else(|e| fail(&format!("Failed to encode bin: {}", e)))
+}
+
+fn load_wad(path: &str) -> wad::WadFile {
+    wad::WadFile::open(path).unwrap_or_else(|e| fail(&format!("Failed to load {}: {}", path, e)))
+}
+
+fn load_names(args: &Args) -> HashNames {
+    let mut names = HashNames::new();
+    for path in args.get_all("hashes") {
+        names
+            .load(path)
+            .unwrap_or_else(|e| fail(&format!("Failed to load {}: {}", path, e)));
+    }
+    names
+}
+
+fn load_epsilon(args: &Args) -> f32 {
+    match args.get("epsilon") {
+        Some(text) => text
+            .parse()
+            .unwrap_or_else(|_| fail(&format!("Invalid epsilon {}", text))),
+        None => bin_defaults::DEFAULT_EPSILON,
+    }
+}
+
+fn load_dict(paths: &[String]) -> hash_dict::HashDict {
+    let mut dict = hash_dict::HashDict::new();
+    for path in paths {
+        dict.load(path)
+            .unwrap_or_else(|e| fail(&format!("Failed to load {}: {}", path, e)));
+    }
+    dict
+}
+
+fn dict_problems(dict: &hash_dict::HashDict) -> Vec<String> {
+    let mut results = Vec::new();
+    for entry in dict.invalid() {
+        results.push(format!(
+            "{}: {} hashes to {:x}, not {:x}",
+            entry.source,
+            entry.name,
+            entry.expected_hash(),
+            entry.hash
+        ));
+    }
+    for collision in dict.collisions() {
+        let names = collision
+            .entries
+            .iter()
+            .map(|x| format!("{} ({})", x.name, x.source))
+            .collect::<Vec<_>>();
+        results.push(format!(
+            "collision {:x}: {}",
+            collision.hash,
+            names.join(", ")
+        ));
+    }
+    results
+}
+
+fn load_number(args: &Args, key: &str) -> Option<usize> {
+    args.get(key).map(|text| {
+        text.parse()
+            .unwrap_or_else(|_| fail(&format!("Invalid --{} {}", key, text)))
+    })
+}
+
+fn write_output(args: &Args, data: impl AsRef<[u8]>) {
+    match args.get("output") {
+        Some(path) => fs::write(path, data)
+            .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e))),
+        None => io::stdout()
+            .write_all(data.as_ref())
+            .unwrap_or_else(|e| fail(&format!("Failed to write output: {}", e))),
+    }
+}
+
+fn main() {
+    let args = Args::parse(env::args().skip(1));
+    let command = args
+        .positional
+        .first()
+        .map(|x| x.as_str())
+        .unwrap_or_default();
+    match command {
+        "cpp" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            write_output(&args, gen_cpp::generate(&meta, &names));
+        }
+        "rust" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            write_output(&args, gen_rust::generate(&meta, &names));
+        }
+        "cs" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            write_output(&args, gen_cs::generate(&meta, &names));
+        }
+        "py" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            write_output(&args, gen_py::generate(&meta, &names));
+        }
+        "ts" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            write_output(&args, gen_ts::generate(&meta, &names));
+        }
+        "bin-schema" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            let schema = gen_schema::generate(&meta, &names);
+            let json_data =
+                serde_json::to_string_pretty(&schema).expect("Failed to serialize json!");
+            write_output(&args, json_data);
+        }
+        "meta-schema" => write_output(&args, META_SCHEMA),
+        "meta-check" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            let conflicts = meta_check::check_meta(&meta);
+            for conflict in &conflicts {
+                println!("{}", conflict.display(&names));
+            }
+            if !conflicts.is_empty() {
+                process::exit(1);
+            }
+        }
+        "check" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            args.input(2);
+            let mut failed = false;
+            for path in &args.positional[2..] {
+                if !wad::is_wad(Path::new(path)) {
+                    let bin = load_bin(path);
+                    for diagnostic in bin_check::check_bin(&meta, &names, &bin) {
+                        println!("{}: {}", path, diagnostic.display(&names));
+                        failed = true;
+                    }
+                    continue;
+                }
+                let mut wad = load_wad(path);
+                wad.for_each_bin(|entry, bin| {
+                    let inner = match names.get_file(entry.path) {
+                        Some(name) => format!("{}/{}", path, name),
+                        None => format!("{}/{:016x}.bin", path, entry.path),
+                    };
+                    match bin {
+                        Ok(bin) => {
+                            for diagnostic in bin_check::check_bin(&meta, &names, &bin) {
+                                println!("{}: {}", inner, diagnostic.display(&names));
+                                failed = true;
+                            }
+                        }
+                        Err(e) => {
+                            println!("{}: {}", inner, e);
+                            failed = true;
+                        }
+                    }
+                })
+                .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path, e)));
+            }
+            if failed {
+                process::exit(1);
+            }
+        }
+        "patch" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            let mut bin = load_bin(args.input(2));
+            args.input(3);
+            let mut failed = false;
+            for path in &args.positional[3..] {
+                let patch = load_bin(path);
+                for diagnostic in bin_patch::apply_patches(&meta, &names, &mut bin, &patch) {
+                    eprintln!("{}: {}", path, diagnostic.display(&names));
+                    failed = true;
+                }
+            }
+            write_output(&args, encode_bin(&bin));
+            if failed {
+                process::exit(1);
+            }
+        }
+        "bin-to-text" => {
+            let names = load_names(&args);
+            let bin = load_bin(args.input(1));
+            write_output(&args, bin_text::write_text(&bin, &names));
+        }
+        "text-to-bin" => {
+            let meta = load_meta(args.input(1));
+            let path = args.input(2);
+            let text = fs::read_to_string(path)
+                .unwrap_or_else(|e| fail(&format!("Failed to load {}: {}", path, e)));
+            let bin = bin_text::read_text(&meta, &text)
+                .unwrap_or_else(|e| fail(&format!("Failed to parse {}: {}", path, e)));
+            write_output(&args, encode_bin(&bin));
+        }
+        "default-bin" => {
+            let meta = load_meta(args.input(1));
+            let class = hashes::name_or_hash(args.input(2));
+            match meta.class(class) {
+                Some(info) if info.is.interface => {
+                    fail(&format!("{} is an interface", args.input(2)))
+                }
+                Some(_) => {}
+                None => fail(&format!("Unknown class {}", args.input(2))),
+            }
+            let object = bin_defaults::default_object(&meta, class);
+            if let Some(field) = object
+                .fields
+                .iter()
+                .find(|x| bin_defaults::has_placeholder(&x.value))
+            {
+                fail(&format!(
+                    "{}.{} refers to a class or container missing from the dump",
+                    args.input(2),
+                    codegen::json_key(&load_names(&args), field.hash)
+                ));
+            }
+            let entry = BinEntry {
+                path: hashes::name_or_hash(args.get("entry").unwrap_or(args.input(2))),
+                object,
+            };
+            let bin = BinFile {
+                version: 3,
+                patch_header: None,
+                linked: Vec::new(),
+                entries: vec![entry],
+                patches: Vec::new(),
+            };
+            write_output(&args, encode_bin(&bin));
+        }
+        "minimize" => {
+            let meta = load_meta(args.input(1));
+            let mut bin = load_bin(args.input(2));
+            bin_defaults::minimize_bin(&meta, &mut bin, load_epsilon(&args));
+            write_output(&args, encode_bin(&bin));
+        }
+        "materialize" => {
+            let meta = load_meta(args.input(1));
+            let mut bin = load_bin(args.input(2));
+            bin_defaults::materialize_bin(&meta, &mut bin);
+            write_output(&args, encode_bin(&bin));
+        }
+        "migrate" => {
+            let old = load_meta(args.input(1));
+            let new = load_meta(args.input(2));
+            let names = load_names(&args);
+            let path = args.input(3);
+            let mut bin = load_bin(path);
+            let mut failed = false;
+            for note in bin_migrate::migrate_bin(&old, &new, &names, &mut bin) {
+                eprintln!("{}: {}", path, note.display(&names));
+                failed |= !note.is_automatic();
+            }
+            write_output(&args, encode_bin(&bin));
+            if failed {
+                process::exit(1);
+            }
+        }
+        "merge" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            let base = load_bin(args.input(2));
+            let ours = load_bin(args.input(3));
+            let theirs = load_bin(args.input(4));
+            let set_lists = args
+                .get_all("set-list")
+                .into_iter()
+                .map(hashes::name_or_hash)
+                .collect::<BTreeSet<_>>();
+            let (bin, conflicts) =
+                bin_merge::merge_bins(&meta, &names, &base, &ours, &theirs, &set_lists);
+            for conflict in &conflicts {
+                eprintln!("{}", conflict.display());
+            }
+            write_output(&args, encode_bin(&bin));
+            if !conflicts.is_empty() {
+                process::exit(1);
+            }
+        }
+        "diff" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            let old = load_bin(args.input(2));
+            let new = load_bin(args.input(3));
+            let diff = bin_diff::diff_bins(&meta, &names, &old, &new, load_epsilon(&args));
+            match args.get("format").unwrap_or("text") {
+                "text" => write_output(&args, bin_diff::diff_text(&names, &diff)),
+                "json" => {
+                    let json_data =
+                        serde_json::to_string_pretty(&bin_diff::diff_json(&names, &diff))
+                            .expect("Failed to serialize json!");
+                    write_output(&args, json_data);
+                }
+                format => fail(&format!("Unknown format {}", format)),
+            }
+            if !diff.is_empty() {
+                process::exit(1);
+            }
+        }
+        "index" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            let dir = args.input(2);
+            let index = bin_index::CorpusIndex::build(&meta, &names, Path::new(dir))
+                .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", dir, e)));
+            match args.get("format").unwrap_or("text") {
+                "text" => write_output(&args, index.summary(&meta, &names)),
+                "json" => {
+                    let json_data = serde_json::to_string_pretty(&index.to_json(&meta, &names))
+                        .expect("Failed to serialize json!");
+                    write_output(&args, json_data);
+                }
+                format => fail(&format!("Unknown format {}", format)),
+            }
+        }
+        "wad-list" => {
+            let names = load_names(&args);
+            let path = args.input(1);
+            let mut wad = load_wad(path);
+            let recovered = wad
+                .path_names()
+                .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path, e)));
+            let mut out = String::new();
+            for entry in &wad.entries {
+                let name = names
+                    .get_file(entry.path)
+                    .or_else(|| recovered.get(&entry.path).map(|x| x.as_str()))
+                    .unwrap_or("?");
+                out.push_str(&format!(
+                    "{:016x} {:?} {} {} {}{}\n",
+                    entry.path,
+                    entry.compression,
+                    entry.compressed_size,
+                    entry.size,
+                    name,
+                    if entry.is_duplicate {
+                        " (duplicate)"
+                    } else {
+                        ""
+                    }
+                ));
+            }
+            write_output(&args, out);
+        }
+        "wad-paths" => {
+            args.input(1);
+            let mut paths = BTreeMap::new();
+            for path in &args.positional[1..] {
+                let recovered = load_wad(path)
+                    .path_names()
+                    .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path, e)));
+                paths.extend(recovered);
+            }
+            let mut out = String::new();
+            for (hash, path) in paths {
+                out.push_str(&format!("{:016x} {}\n", hash, path));
+            }
+            write_output(&args, out);
+        }
+        "recover-names" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            let min_length = match args.get("min-length") {
+                Some(text) => text
+                    .parse()
+                    .unwrap_or_else(|_| fail(&format!("Invalid length {}", text))),
+                None => name_recovery::MIN_LENGTH,
+            };
+            args.input(2);
+            let mut strings = BTreeSet::new();
+            for path in &args.positional[2..] {
+                if wad::is_wad(Path::new(path)) {
+                    load_wad(path)
+                        .for_each_bin(|_, bin| {
+                            if let Ok(bin) = bin {
+                                name_recovery::bin_strings(&bin, &mut strings);
+                            }
+                        })
+                        .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path, e)));
+                    continue;
+                }
+                let data = fs::read(path)
+                    .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path, e)));
+                match bin::read_bin(&data) {
+                    Ok(bin) => name_recovery::bin_strings(&bin, &mut strings),
+                    Err(_) => name_recovery::pe_strings(&data, min_length, &mut strings),
+                }
+            }
+            let targets = name_recovery::unresolved_hashes(&meta, &names);
+            let candidates = name_recovery::candidates(&strings);
+            let recovered = name_recovery::recover_names(&targets, &candidates);
+            let mut out = String::new();
+            for (hash, name) in &recovered {
+                eprintln!("{} {:08x} {}", targets[hash].name(), hash, name);
+                out.push_str(&format!("{:08x} {}\n", hash, name));
+            }
+            eprintln!(
+                "{} strings, resolved {} of {} unresolved hashes",
+                candidates.len(),
+                recovered.len(),
+                targets.len()
+            );
+            write_output(&args, out);
+        }
+        "brute-force" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            args.input(2);
+            let mut words = Vec::new();
+            for path in &args.positional[2..] {
+                let text = fs::read_to_string(path)
+                    .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path, e)));
+                words.extend(text.lines().map(|x| x.to_string()));
+            }
+            words.extend(brute_force::name_fragments(&meta, &names));
+            let config = brute_force::BruteForce {
+                words: brute_force::normalize_words(words),
+                max_words: load_number(&args, "max-words").unwrap_or(2),
+                max_suffix: load_number(&args, "max-suffix").map(|x| x as u32),
+                threads: load_number(&args, "threads")
+                    .unwrap_or_else(|| thread::available_parallelism().map_or(1, |x| x.get())),
+            };
+            let targets = name_recovery::unresolved_hashes(&meta, &names);
+            let units = config.units();
+            let resume = args.get("resume");
+            let start = match resume.and_then(|path| fs::read_to_string(path).ok()) {
+                Some(text) => config
+                    .resume_at(&text)
+                    .unwrap_or_else(|| fail("Progress file is for other words or settings")),
+                None => 0,
+            };
+            // Hits of the unit that was cut short are found again, skip the
+            // ones already written.
+            let mut written = BTreeSet::new();
+            if let Some(path) = args.get("output").filter(|_| start > 0) {
+                if let Ok(text) = fs::read_to_string(path) {
+                    written.extend(text.lines().map(|x| x.to_string()));
+                }
+            }
+            let output: Box<dyn Write + Send> = match args.get("output") {
+                Some(path) => Box::new(
+                    fs::OpenOptions::new()
+                        .create(true)
+                        .write(true)
+                        .append(start > 0)
+                        .truncate(start == 0)
+                        .open(path)
+                        .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e))),
+                ),
+                None => Box::new(io::stdout()),
+            };
+            let output = Mutex::new((output, written));
+            eprintln!(
+                "{} words, {} unresolved hashes, starting at unit {} of {}",
+                config.words.len(),
+                targets.len(),
+                start,
+                units
+            );
+            let hit = |hit: brute_force::BruteHit| {
+                let line = format!(
+                    "{:08x} {} # brute-force {}: {}\n",
+                    hit.hash,
+                    hit.name,
+                    targets[&hit.hash].name(),
+                    hit.provenance()
+                );
+                let mut output = output.lock().unwrap();
+                let (output, written) = &mut *output;
+                if !written.insert(line.trim_end().to_string()) {
+                    return;
+                }
+                output
+                    .write_all(line.as_bytes())
+                    .and_then(|_| output.flush())
+                    .unwrap_or_else(|e| fail(&format!("Failed to write output: {}", e)));
+            };
+            let done = |done: usize| {
+                if let Some(path) = resume {
+                    fs::write(path, config.progress(done))
+                        .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e)));
+                }
+            };
+            config.run(&targets, start, &hit, &done);
+        }
+        "suggest-names" => {
+            let meta = load_meta(args.input(1));
+            let names = load_names(&args);
+            let suggestions = name_suggest::suggest_names(&meta, &names);
+            let mut out = String::new();
+            for suggestion in &suggestions {
+                out.push_str(&format!(
+                    "{:08x} {} # suggest {}: {}\n",
+                    suggestion.property,
+                    suggestion.name,
+                    codegen::json_key(&names, suggestion.class),
+                    suggestion.reason
+                ));
+            }
+            let resolved = suggestions
+                .iter()
+                .map(|x| x.property)
+                .collect::<BTreeSet<_>>();
+            eprintln!(
+                "{} suggestions for {} properties",
+                suggestions.len(),
+                resolved.len()
+            );
+            write_output(&args, out);
+        }
+        "dict-merge" => {
+            args.input(1);
+            let dict = load_dict(&args.positional[1..]);
+            for problem in dict_problems(&dict) {
+                eprintln!("{}", problem);
+            }
+            let mut out = String::new();
+            for entry in dict.merged() {
+                out.push_str(&entry.line());
+                out.push('\n');
+            }
+            write_output(&args, out);
+        }
+        "dict-verify" => {
+            args.input(1);
+            let dict = load_dict(&args.positional[1..]);
+            let problems = dict_problems(&dict);
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            if !problems.is_empty() {
+                process::exit(1);
+            }
+        }
+        "dict-export" => {
+            let meta = load_meta(args.input(1));
+            args.input(2);
+            let dict = load_dict(&args.positional[2..]);
+            let mut out = String::new();
+            for entry in dict.relevant(&meta) {
+                out.push_str(&entry.line());
+                out.push('\n');
+            }
+            write_output(&args, out);
+        }
+        "dict-coverage" => {
+            let names = load_names(&args);
+            args.input(1);
+            let mut out = String::new();
+            for path in &args.positional[1..] {
+                let meta = load_meta(path);
+                let coverage = hash_dict::coverage(&meta, &names);
+                out.push_str(&format!("{} ({}):\n", path, meta.version));
+                out.push_str(&hash_dict::coverage_text(&coverage));
+            }
+            write_output(&args, out);
+        }
+        "unresolved" => {
+            let names = load_names(&args);
+            args.input(1);
+            let dumps = args.positional[1..]
+                .iter()
+                .map(|x| load_meta(x))
+                .collect::<Vec<_>>();
+            let first_versions = unresolved::first_versions(&dumps);
+            if let Some(dir) = args.get("output-dir") {
+                fs::create_dir_all(dir)
+                    .unwrap_or_else(|e| fail(&format!("Failed to create {}: {}", dir, e)));
+            }
+            let mut out = String::new();
+            for (path, meta) in args.positional[1..].iter().zip(&dumps) {
+                let list = unresolved::collect_unresolved(meta, &names);
+                let text = unresolved::unresolved_text(&list, &first_versions);
+                eprintln!("{}: {} unresolved hashes", path, list.len());
+                match args.get("output-dir") {
+                    Some(dir) => {
+                        let file = Path::new(dir).join(format!("unresolved-{}.tsv", meta.version));
+                        fs::write(&file, text).unwrap_or_else(|e| {
+                            fail(&format!("Failed to write {}: {}", file.display(), e))
+                        });
+                    }
+                    None => {
+                        out.push_str(&format!("# {} ({})\n", path, meta.version));
+                        out.push_str(&text);
+                    }
+                }
+            }
+            if args.get("output-dir").is_none() {
+                write_output(&args, out);
+            }
+        }
+        _ => fail(USAGE),
+    }
+}
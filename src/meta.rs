@@ -1,4 +1,4 @@
-use serde::Serialize;
+pub use crate::types::{BinType, ContainerStorage, MapStorage};
 
 #[repr(C)]
 pub struct StdVector<T> {
@@ -34,6 +34,18 @@ impl<'a, T> RiotVector<T> {
     }
 }
 
+#[cfg(test)]
+impl<T> RiotVector<T> {
+    pub fn leak(items: Vec<T>) -> Self {
+        let size = items.len() as u32;
+        RiotVector {
+            data: Box::leak(items.into_boxed_slice()).as_ptr(),
+            size,
+            capacity: size,
+        }
+    }
+}
+
 #[repr(C)]
 pub struct AString {
     data: RiotVector<u8>,
@@ -47,48 +59,6 @@ impl AString {
     }
 }
 
-#[repr(u8)]
-#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-pub enum BinType {
-    None = 0,
-    Bool = 1,
-    I8 = 2,
-    U8 = 3,
-    I16 = 4,
-    U16 = 5,
-    I32 = 6,
-    U32 = 7,
-    I64 = 8,
-    U64 = 9,
-    F32 = 10,
-    Vec2 = 11,
-    Vec3 = 12,
-    Vec4 = 13,
-    Mtx44 = 14,
-    Color = 15,
-    String = 16,
-    Hash = 17,
-    File = 18,
-    List = 0x80 | 0,
-    List2 = 0x80 | 1,
-    Pointer = 0x80 | 2,
-    Embed = 0x80 | 3,
-    Link = 0x80 | 4,
-    Option = 0x80 | 5,
-    Map = 0x80 | 6,
-    Flag = 0x80 | 7,
-}
-
-#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-#[repr(C)]
-pub enum ContainerStorage {
-    UnknownVector,
-    Option,
-    Fixed,
-    StdVector,
-    RitoVector,
-}
-
 #[repr(C)]
 pub struct ContainerIVtable {
     pub destructor: extern "thiscall" fn(this: &ContainerI, flag: bool),
@@ -143,15 +113,6 @@ impl ContainerI {
     }
 }
 
-#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-#[repr(C)]
-pub enum MapStorage {
-    UnknownMap,
-    StdMap,
-    StdUnorderedMap,
-    RitoVectorMap,
-}
-
 #[repr(C)]
 pub struct MapConstIterIVtable {
     pub destructor: extern "thiscall" fn(this: &mut MapConstIterI, flag: bool),
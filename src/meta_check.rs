@@ -0,0 +1,343 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::codegen::json_key;
+use crate::hashes::HashNames;
+use crate::schema::MetaFile;
+
+/// Classes from the checked class to an ancestor, each with whether it was
+/// reached through a secondary base.
+pub type InheritancePath = Vec<(u32, bool)>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conflict {
+    /// Registered more than once, only the last registration was dumped.
+    DuplicateClass { indices: Vec<usize> },
+    /// Declared again although `path` leads to a base declaring it too.
+    ShadowedProperty {
+        property: u32,
+        path: InheritancePath,
+    },
+    /// Inherited from two bases that each declare it.
+    AmbiguousProperty {
+        property: u32,
+        first: InheritancePath,
+        second: InheritancePath,
+    },
+    /// `hash` names a class at one end of `path` and a property declared by
+    /// the class at the other end.
+    ClassIsProperty { hash: u32, path: InheritancePath },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaConflict {
+    pub class: u32,
+    pub conflict: Conflict,
+}
+
+fn path_text(names: &HashNames, path: &InheritancePath) -> String {
+    path.iter()
+        .map(|&(class, secondary)| {
+            if secondary {
+                format!("{} (secondary)", json_key(names, class))
+            } else {
+                json_key(names, class)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+impl MetaConflict {
+    pub fn display(&self, names: &HashNames) -> String {
+        let conflict = match &self.conflict {
+            Conflict::DuplicateClass { indices } => {
+                format!("registered {} times at {:?}", indices.len(), indices)
+            }
+            Conflict::ShadowedProperty { property, path } => format!(
+                "{} shadows the base property through {}",
+                json_key(names, *property),
+                path_text(names, path)
+            ),
+            Conflict::AmbiguousProperty {
+                property,
+                first,
+                second,
+            } => format!(
+                "{} inherited through both {} and {}",
+                json_key(names, *property),
+                path_text(names, first),
+                path_text(names, second)
+            ),
+            Conflict::ClassIsProperty { hash, path } => format!(
+                "{} is both a class and a property in {}",
+                json_key(names, *hash),
+                path_text(names, path)
+            ),
+        };
+        format!("{}: {}", json_key(names, self.class), conflict)
+    }
+}
+
+/// Base class followed by secondary bases, flagged as such.
+fn base_edges(meta: &MetaFile, class: u32) -> Vec<(u32, bool)> {
+    let mut results = Vec::new();
+    if let Some(info) = meta.class(class) {
+        results.extend(info.base.map(|x| (x, false)));
+        results.extend(info.secondary_bases.keys().map(|&x| (x, true)));
+    }
+    results
+}
+
+/// Shortest path from `start` up to a class matching `found`, breadth first
+/// through base and secondary bases.
+fn find_ancestor(
+    meta: &MetaFile,
+    start: InheritancePath,
+    found: &dyn Fn(u32) -> bool,
+) -> Option<InheritancePath> {
+    let mut seen = BTreeSet::new();
+    let mut pending = VecDeque::from([start]);
+    while let Some(path) = pending.pop_front() {
+        let class = path.last()?.0;
+        if !seen.insert(class) {
+            continue;
+        }
+        if found(class) {
+            return Some(path);
+        }
+        for edge in base_edges(meta, class) {
+            let mut next = path.clone();
+            next.push(edge);
+            pending.push_back(next);
+        }
+    }
+    None
+}
+
+fn declares(meta: &MetaFile, class: u32, property: u32) -> bool {
+    meta.class(class)
+        .is_some_and(|x| x.properties.contains_key(&property))
+}
+
+struct Checker<'a> {
+    meta: &'a MetaFile,
+    results: Vec<MetaConflict>,
+}
+
+impl<'a> Checker<'a> {
+    fn report(&mut self, class: u32, conflict: Conflict) {
+        self.results.push(MetaConflict { class, conflict });
+    }
+
+    /// Paths from `class` through each direct base to a class declaring `property`.
+    fn declarations(&self, class: u32, property: u32) -> Vec<InheritancePath> {
+        base_edges(self.meta, class)
+            .into_iter()
+            .filter_map(|edge| {
+                let start = vec![(class, false), edge];
+                find_ancestor(self.meta, start, &|x| declares(self.meta, x, property))
+            })
+            .collect()
+    }
+
+    fn class(&mut self, class: u32) {
+        let meta = self.meta;
+        let info = match meta.class(class) {
+            Some(info) => info,
+            None => return,
+        };
+        for &property in info.properties.keys() {
+            let nearest = self
+                .declarations(class, property)
+                .into_iter()
+                .min_by_key(|x| x.len());
+            if let Some(path) = nearest {
+                self.report(class, Conflict::ShadowedProperty { property, path });
+            }
+        }
+        // Properties declared in more than one base, reported where the
+        // branches meet rather than on every class below.
+        let mut inherited = BTreeSet::new();
+        for (base, _) in base_edges(meta, class) {
+            for (_, property, _) in meta.all_properties(base) {
+                inherited.insert(property);
+            }
+        }
+        for property in inherited {
+            if info.properties.contains_key(&property) {
+                continue;
+            }
+            let paths = self.declarations(class, property);
+            let second = paths
+                .iter()
+                .find(|x| x.last() != paths.first().and_then(|x| x.last()));
+            if let (Some(first), Some(second)) = (paths.first(), second) {
+                let (first, second) = (first.clone(), second.clone());
+                let conflict = Conflict::AmbiguousProperty {
+                    property,
+                    first,
+                    second,
+                };
+                self.report(class, conflict);
+            }
+        }
+        // This class being a property of itself or an ancestor.
+        let start = vec![(class, false)];
+        if let Some(path) = find_ancestor(meta, start, &|x| declares(meta, x, class)) {
+            let conflict = Conflict::ClassIsProperty { hash: class, path };
+            self.report(class, conflict);
+        }
+        // A property of this class being a strict ancestor.
+        for &property in info.properties.keys() {
+            if property == class {
+                continue;
+            }
+            let start = vec![(class, false)];
+            if let Some(path) = find_ancestor(meta, start, &|x| x == property) {
+                let conflict = Conflict::ClassIsProperty {
+                    hash: property,
+                    path,
+                };
+                self.report(class, conflict);
+            }
+        }
+    }
+}
+
+/// Duplicate registrations, properties shadowing or inherited twice from
+/// bases and class hashes equal to a property hash within one hierarchy.
+pub fn check_meta(meta: &MetaFile) -> Vec<MetaConflict> {
+    let mut checker = Checker {
+        meta,
+        results: Vec::new(),
+    };
+    for (&class, indices) in &meta.duplicates {
+        let indices = indices.clone();
+        checker.report(class, Conflict::DuplicateClass { indices });
+    }
+    for &class in meta.classes.keys() {
+        checker.class(class);
+    }
+    checker.results
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::hashes::fnv1a;
+    use crate::schema::testing::{class, meta, property};
+    use crate::types::BinType;
+
+    fn h(name: &str) -> u32 {
+        fnv1a(name)
+    }
+
+    fn declaring(base: Option<&str>, properties: &[&str]) -> Value {
+        let properties = properties
+            .iter()
+            .enumerate()
+            .map(|(index, &name)| (h(name), property(index as u32 * 4, BinType::U32, None)))
+            .collect();
+        class(base.map(h), 64, properties)
+    }
+
+    fn secondary(mut class: Value, base: &str) -> Value {
+        class["secondary_bases"] = json!({ format!("{:#x}", h(base)): 32 });
+        class
+    }
+
+    fn findings(classes: Vec<(&str, Value)>) -> Vec<String> {
+        let mut names = HashNames::new();
+        for (name, _) in &classes {
+            names.insert(h(name), name);
+        }
+        for name in ["mA", "mB", "mC"] {
+            names.insert(h(name), name);
+        }
+        let meta = meta(classes.into_iter().map(|(n, c)| (h(n), c)).collect());
+        let mut results = check_meta(&meta)
+            .iter()
+            .map(|x| x.display(&names))
+            .collect::<Vec<_>>();
+        results.sort();
+        results
+    }
+
+    #[test]
+    fn duplicate_classes_are_reported() {
+        let mut meta = meta(vec![(h("Item"), declaring(None, &["mA"]))]);
+        meta.duplicates.insert(h("Item"), vec![3, 7]);
+        let mut names = HashNames::new();
+        names.insert(h("Item"), "Item");
+        let results = check_meta(&meta);
+        assert_eq!(
+            results,
+            vec![MetaConflict {
+                class: h("Item"),
+                conflict: Conflict::DuplicateClass {
+                    indices: vec![3, 7]
+                },
+            }]
+        );
+        assert_eq!(
+            results[0].display(&names),
+            "Item: registered 2 times at [3, 7]"
+        );
+    }
+
+    #[test]
+    fn shadowed_properties_report_the_nearest_path() {
+        let results = findings(vec![
+            ("Root", declaring(None, &["mA"])),
+            ("Mid", declaring(Some("Root"), &[])),
+            ("Other", declaring(None, &["mB"])),
+            (
+                "Leaf",
+                secondary(declaring(Some("Mid"), &["mA", "mB"]), "Other"),
+            ),
+        ]);
+        assert_eq!(
+            results,
+            vec![
+                "Leaf: mA shadows the base property through Leaf -> Mid -> Root",
+                "Leaf: mB shadows the base property through Leaf -> Other (secondary)",
+            ]
+        );
+    }
+
+    #[test]
+    fn ambiguous_properties_are_reported_where_branches_meet() {
+        let results = findings(vec![
+            ("Left", declaring(None, &["mC"])),
+            ("Right", declaring(None, &["mC"])),
+            ("Both", secondary(declaring(Some("Left"), &[]), "Right")),
+            ("Below", declaring(Some("Both"), &[])),
+        ]);
+        assert_eq!(
+            results,
+            vec!["Both: mC inherited through both Both -> Left and Both -> Right (secondary)"]
+        );
+    }
+
+    #[test]
+    fn class_hashes_used_as_properties_in_the_hierarchy() {
+        let results = findings(vec![
+            ("Base", declaring(None, &["Thing"])),
+            ("Thing", declaring(Some("Base"), &["Base"])),
+            ("Self", declaring(None, &["Self"])),
+            // Same hash as a property elsewhere, but not in the hierarchy.
+            ("mA", declaring(None, &[])),
+            ("Unrelated", declaring(None, &["mA"])),
+        ]);
+        assert_eq!(
+            results,
+            vec![
+                "Self: Self is both a class and a property in Self",
+                "Thing: Base is both a class and a property in Thing -> Base",
+                "Thing: Thing is both a class and a property in Thing -> Base",
+            ]
+        );
+    }
+}
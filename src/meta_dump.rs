@@ -1,12 +1,14 @@
 use core::ffi::c_void;
 use core::fmt::LowerHex;
+use std::collections::BTreeMap;
 
 use serde_json::json;
 use serde_json::{Map, Value};
 
 use crate::meta::*;
+use crate::type_expr::TypeExpr;
 
-fn dump_hex<T: Copy + LowerHex>(value: T) -> String {
+pub fn dump_hex<T: Copy + LowerHex>(value: T) -> String {
     format!("0x{:x}", value)
 }
 
@@ -181,8 +183,21 @@ fn dump_property_map(base: usize, map: &MapI) -> Value {
     })
 }
 
+fn dump_property_type(property: &Property) -> TypeExpr {
+    TypeExpr::new(
+        property.value_type,
+        property.map.map(|m| m.key_type),
+        property
+            .container
+            .map(|c| c.value_type)
+            .or_else(|| property.map.map(|m| m.value_type)),
+        property.other_class.map(|c| c.hash),
+    )
+}
+
 fn dump_property(base: usize, property: &Property) -> Value {
     json!({
+        "type": dump_property_type(property).to_string(),
         "other_class": property.other_class.map(|c| dump_hex(c.hash)),
         "offset": property.offset,
         "bitmask": property.bitmask,
@@ -276,3 +291,289 @@ pub fn dump_class_list(base: usize, classes: &[&Class]) -> Value {
     }
     results.into()
 }
+
+/// Hashes registered more than once with the index of each registration,
+/// since `dump_class_list` keeps only the last.
+pub fn dump_duplicates(classes: &[&Class]) -> Value {
+    let mut indices: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+    for (index, class) in classes.iter().enumerate() {
+        indices.entry(class.hash).or_default().push(index);
+    }
+    let mut results = Map::new();
+    for (hash, indices) in indices {
+        if indices.len() > 1 {
+            results.insert(dump_hex(hash), indices.into());
+        }
+    }
+    results.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FORMAT_VERSION;
+
+    /// Zeroed default instance shared by every constructible test class.
+    static INSTANCE: [u64; 16] = [0; 16];
+
+    extern "C" fn construct() -> usize {
+        INSTANCE.as_ptr() as usize
+    }
+
+    extern "C" fn destruct(_: usize) {}
+
+    extern "thiscall" fn container_destructor(_: &ContainerI, _: bool) {}
+    extern "thiscall" fn container_size(_: &ContainerI, _: usize) -> usize {
+        0
+    }
+    extern "thiscall" fn container_resize(_: &ContainerI, _: usize, _: usize) {}
+    extern "thiscall" fn container_item(_: &ContainerI, _: usize, _: usize) -> usize {
+        unreachable!()
+    }
+    extern "thiscall" fn container_clear(_: &ContainerI, _: usize) {}
+    extern "thiscall" fn container_dynamic(_: &ContainerI) -> i32 {
+        -1
+    }
+    extern "thiscall" fn container_fixed(_: &ContainerI) -> i32 {
+        4
+    }
+    extern "thiscall" fn container_fixed_item(
+        this: &ContainerI,
+        instance: usize,
+        index: usize,
+    ) -> usize {
+        instance + index * this.value_size as usize
+    }
+
+    static DYNAMIC_VTABLE: ContainerIVtable = ContainerIVtable {
+        destructor: container_destructor,
+        get_size: container_size,
+        set_size: container_resize,
+        get_mut: container_item,
+        get_const: container_item,
+        clear: container_clear,
+        push: container_item,
+        pop: container_clear,
+        get_fixed_size: container_dynamic,
+    };
+
+    static FIXED_VTABLE: ContainerIVtable = ContainerIVtable {
+        get_const: container_fixed_item,
+        get_fixed_size: container_fixed,
+        ..DYNAMIC_VTABLE
+    };
+
+    static LIST: ContainerI = ContainerI {
+        vtable: &DYNAMIC_VTABLE,
+        value_type: BinType::String,
+        value_size: 16,
+    };
+
+    static OPTION: ContainerI = ContainerI {
+        vtable: &DYNAMIC_VTABLE,
+        value_type: BinType::F32,
+        value_size: 4,
+    };
+
+    static FIXED: ContainerI = ContainerI {
+        vtable: &FIXED_VTABLE,
+        value_type: BinType::Vec3,
+        value_size: 12,
+    };
+
+    extern "thiscall" fn map_destructor(_: &MapI, _: bool) {}
+    extern "thiscall" fn map_size(_: &MapI, _: usize) -> usize {
+        0
+    }
+    extern "thiscall" fn map_resize(_: &MapI, _: usize, _: usize) {}
+    extern "thiscall" fn map_clear(_: &MapI, _: usize) {}
+    #[allow(improper_ctypes_definitions)]
+    extern "thiscall" fn map_find(_: &MapI, _: usize, _: usize) -> Option<usize> {
+        None
+    }
+    extern "thiscall" fn map_item(_: &MapI, _: usize, _: usize) -> usize {
+        unreachable!()
+    }
+    extern "thiscall" fn map_iter(_: &MapI, _: usize) -> usize {
+        unreachable!()
+    }
+    extern "thiscall" fn map_iter_const(_: &MapI, _: usize) -> &mut MapConstIterI {
+        unreachable!()
+    }
+
+    static MAP_VTABLE: MapIVtable = MapIVtable {
+        destructor: map_destructor,
+        get_size: map_size,
+        reserve_size: map_resize,
+        finalize: map_clear,
+        find: map_find,
+        clear: map_clear,
+        create: map_item,
+        inplace_ctor: map_item,
+        inplace_dtor: map_resize,
+        erase: map_item,
+        iter_mut: map_iter,
+        iter_const: map_iter_const,
+    };
+
+    static MAP: MapI = MapI {
+        vtable: &MAP_VTABLE,
+        key_type: BinType::Hash,
+        value_type: BinType::Pointer,
+    };
+
+    fn property(hash: u32, offset: u32, value_type: BinType) -> Property {
+        Property {
+            other_class: None,
+            hash,
+            offset,
+            bitmask: 0,
+            value_type,
+            container: None,
+            map: None,
+            unkptr: 0,
+        }
+    }
+
+    fn class(hash: u32, constructible: bool, properties: Vec<Property>) -> Class {
+        Class {
+            upcast_secondary_fn: None,
+            hash,
+            constructor_fn: constructible.then_some(construct as extern "C" fn() -> usize),
+            destructor_fn: constructible.then_some(destruct as extern "C" fn(usize)),
+            inplace_constructor_fn: None,
+            inplace_destructor_fn: None,
+            register_fn: None,
+            base_class: None,
+            class_size: 128,
+            alignment: 8,
+            is_value: false,
+            is_secondary_base: false,
+            is_unk5: false,
+            properties: RiotVector::leak(properties),
+            secondary_bases: RiotVector::leak(Vec::new()),
+            secondary_children: RiotVector::leak(Vec::new()),
+        }
+    }
+
+    /// Interface with every container kind, a class deriving from it with a
+    /// secondary base and default values of every nestable type, and one
+    /// duplicate registration.
+    fn synthetic_classes() -> Vec<&'static Class> {
+        let interface = Box::leak(Box::new(class(
+            0x1000,
+            false,
+            vec![
+                Property {
+                    container: Some(&FIXED),
+                    ..property(0x1, 0, BinType::List)
+                },
+                Property {
+                    map: Some(&MAP),
+                    ..property(0x2, 48, BinType::Map)
+                },
+            ],
+        )));
+        let mut secondary = class(0x2000, false, vec![property(0x3, 0, BinType::U16)]);
+        secondary.is_secondary_base = true;
+        let secondary: &'static Class = Box::leak(Box::new(secondary));
+        let mut properties = vec![
+            Property {
+                container: Some(&LIST),
+                ..property(0x10, 0, BinType::List2)
+            },
+            Property {
+                container: Some(&OPTION),
+                ..property(0x11, 16, BinType::Option)
+            },
+            Property {
+                bitmask: 3,
+                ..property(0x12, 20, BinType::Flag)
+            },
+            Property {
+                other_class: Some(interface),
+                ..property(0x13, 24, BinType::Link)
+            },
+            Property {
+                other_class: Some(interface),
+                ..property(0x14, 24, BinType::Pointer)
+            },
+            Property {
+                other_class: Some(secondary),
+                ..property(0x15, 24, BinType::Embed)
+            },
+        ];
+        for (index, &value_type) in BinType::ALL.iter().enumerate() {
+            if value_type.is_nestable()
+                && !matches!(
+                    value_type,
+                    BinType::Pointer | BinType::Embed | BinType::Link
+                )
+            {
+                properties.push(property(0x100 + index as u32, 32, value_type));
+            }
+        }
+        let mut derived = class(0x3000, true, properties);
+        derived.base_class = Some(interface);
+        derived.secondary_bases = RiotVector::leak(vec![BaseOff(secondary, 96)]);
+        let derived: &'static Class = Box::leak(Box::new(derived));
+        let duplicate: &'static Class = Box::leak(Box::new(class(0x1000, false, Vec::new())));
+        vec![duplicate, interface, secondary, derived]
+    }
+
+    fn dump(classes: &[&Class]) -> Value {
+        json!({
+            "format": FORMAT_VERSION,
+            "version": "1.0.0.0",
+            "classes": dump_class_list(0, classes),
+            "duplicates": dump_duplicates(classes),
+        })
+    }
+
+    fn schema_errors(document: &Value) -> Vec<String> {
+        let schema = serde_json::from_str(include_str!("../schema/meta.schema.json")).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        validator
+            .iter_errors(document)
+            .map(|x| x.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn synthetic_dump_matches_schema() {
+        let document = dump(&synthetic_classes());
+        assert_eq!(schema_errors(&document), Vec::<String>::new());
+        let classes = &document["classes"];
+        assert_eq!(classes["0x1000"]["defaults"], Value::Null);
+        assert_eq!(
+            classes["0x1000"]["properties"]["0x1"]["container"]["storage"],
+            "Fixed"
+        );
+        assert_eq!(classes["0x3000"]["secondary_bases"]["0x2000"], 96);
+        assert_eq!(
+            classes["0x3000"]["defaults"]["0x1"],
+            json!(vec![[0.0f32; 3]; 4])
+        );
+        assert_eq!(classes["0x3000"]["defaults"]["0x3"], 0);
+        assert_eq!(classes["0x3000"]["defaults"]["0x11"], Value::Null);
+        assert_eq!(document["duplicates"]["0x1000"], json!([0, 1]));
+    }
+
+    #[test]
+    fn schema_rejects_changed_layout() {
+        let mut document = dump(&synthetic_classes());
+        let property = &mut document["classes"]["0x3000"]["properties"]["0x11"];
+        property.as_object_mut().unwrap().remove("unkptr");
+        property["container"]["storage"] = json!("RitoVector");
+        document["classes"]["0x1000"]["size"] = json!(-1);
+        assert_eq!(schema_errors(&document).len(), 3);
+    }
+
+    #[test]
+    fn schema_accepts_format_version() {
+        let schema: Value =
+            serde_json::from_str(include_str!("../schema/meta.schema.json")).unwrap();
+        let formats = schema["properties"]["format"]["enum"].as_array().unwrap();
+        assert!(formats.contains(&json!(FORMAT_VERSION)));
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use pelite::pe64::{Pe, PeFile};
+
+use crate::bin::{BinFile, BinValue};
+use crate::bin_defaults::{default_object, visit_values};
+use crate::hashes::{fnv1a, HashNames};
+use crate::schema::MetaFile;
+use crate::type_expr::TypeExpr;
+
+/// Shortest run of characters kept as a string.
+pub const MIN_LENGTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HashKind {
+    Class,
+    Property,
+    /// Hash or link value, usually an entry path.
+    Link,
+    /// XXH64 of a file path.
+    File,
+}
+
+impl HashKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            HashKind::Class => "class",
+            HashKind::Property => "property",
+            HashKind::Link => "link",
+            HashKind::File => "file",
+        }
+    }
+}
+
+fn is_text(byte: u8) -> bool {
+    byte.is_ascii_graphic() || byte == b' '
+}
+
+fn push_run(run: &mut String, min_length: usize, results: &mut BTreeSet<String>) {
+    if run.len() >= min_length {
+        results.insert(std::mem::take(run));
+    }
+    run.clear();
+}
+
+/// Printable ASCII and UTF-16LE runs of at least `min_length` characters.
+pub fn data_strings(data: &[u8], min_length: usize, results: &mut BTreeSet<String>) {
+    let mut run = String::new();
+    for &byte in data {
+        if is_text(byte) {
+            run.push(byte as char);
+        } else {
+            push_run(&mut run, min_length, results);
+        }
+    }
+    push_run(&mut run, min_length, results);
+    // Both alignments, a UTF-16 string may start at an odd offset.
+    for start in 0..2 {
+        for pair in data[start.min(data.len())..].chunks_exact(2) {
+            if pair[1] == 0 && is_text(pair[0]) {
+                run.push(pair[0] as char);
+            } else {
+                push_run(&mut run, min_length, results);
+            }
+        }
+        push_run(&mut run, min_length, results);
+    }
+}
+
+/// Strings of every section of a PE image, or of the whole file when it is
+/// not one.
+pub fn pe_strings(data: &[u8], min_length: usize, results: &mut BTreeSet<String>) {
+    let file = match PeFile::from_bytes(data) {
+        Ok(file) => file,
+        Err(_) => return data_strings(data, min_length, results),
+    };
+    for section in file.section_headers() {
+        if let Ok(bytes) = file.get_section_bytes(section) {
+            data_strings(bytes, min_length, results);
+        }
+    }
+}
+
+fn value_strings(value: &BinValue, results: &mut BTreeSet<String>) {
+    visit_values(value, &mut |value| {
+        if let BinValue::String(text) = value {
+            results.insert(text.clone());
+        }
+    });
+}
+
+/// String values and linked file paths of `bin`.
+pub fn bin_strings(bin: &BinFile, results: &mut BTreeSet<String>) {
+    results.extend(bin.linked.iter().cloned());
+    for entry in &bin.entries {
+        for field in &entry.object.fields {
+            value_strings(&field.value, results);
+        }
+    }
+    for patch in &bin.patches {
+        results.insert(patch.path.clone());
+        value_strings(&patch.value, results);
+    }
+}
+
+/// Each string as is and every identifier inside it, so `mFoo` is found in
+/// `Characters/Annie:mFoo` too.
+pub fn candidates(strings: &BTreeSet<String>) -> BTreeSet<String> {
+    let mut results = BTreeSet::new();
+    for text in strings {
+        results.insert(text.trim().to_string());
+        for word in text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_')) {
+            if !word.is_empty() {
+                results.insert(word.to_string());
+            }
+        }
+    }
+    results.remove("");
+    results
+}
+
+/// Hash the dump refers to, with the context of its first use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpHash {
+    pub kind: HashKind,
+    /// Base of classes, declaring class of properties and class whose
+    /// default holds links and files.
+    pub class: Option<u32>,
+    /// Property whose default holds links and files.
+    pub property: Option<u32>,
+    /// Type of properties and of default values.
+    pub type_expr: Option<TypeExpr>,
+}
+
+/// Class, property, link and file hashes the dump refers to, keyed by
+/// whether they are XXH64 file hashes and the hash. Links and files are the
+/// values of class defaults. Declarations come first, so a hash keeps the
+/// context of the class or property it names.
+pub fn dump_hashes(meta: &MetaFile) -> BTreeMap<(bool, u64), DumpHash> {
+    let mut results = BTreeMap::new();
+    let mut add = |kind: HashKind, hash: u64, class, property, type_expr| {
+        results
+            .entry((kind == HashKind::File, hash))
+            .or_insert(DumpHash {
+                kind,
+                class,
+                property,
+                type_expr,
+            });
+    };
+    for (&class, info) in &meta.classes {
+        add(HashKind::Class, class as u64, info.base, None, None);
+        for (&property, info) in &info.properties {
+            let type_expr = Some(info.type_expr());
+            add(
+                HashKind::Property,
+                property as u64,
+                Some(class),
+                None,
+                type_expr,
+            );
+        }
+    }
+    for info in meta.classes.values() {
+        for &base in info.base.iter().chain(info.secondary_bases.keys()) {
+            add(HashKind::Class, base as u64, None, None, None);
+        }
+        for other in info.properties.values().filter_map(|x| x.other_class) {
+            add(HashKind::Class, other as u64, None, None, None);
+        }
+    }
+    for &class in meta.classes.keys() {
+        for field in default_object(meta, class).fields {
+            visit_values(&field.value, &mut |value| {
+                let (kind, hash) = match *value {
+                    BinValue::Hash(x) | BinValue::Link(x) if x != 0 => (HashKind::Link, x as u64),
+                    BinValue::File(x) if x != 0 => (HashKind::File, x),
+                    _ => return,
+                };
+                let type_expr = Some(value.type_expr());
+                add(kind, hash, Some(class), Some(field.hash), type_expr);
+            });
+        }
+    }
+    results
+}
+
+/// Whether `names` has the name or file path of a `dump_hashes` key.
+pub fn is_resolved(names: &HashNames, (is_file, hash): (bool, u64)) -> bool {
+    match is_file {
+        true => names.get_file(hash).is_some(),
+        false => names.get(hash as u32).is_some(),
+    }
+}
+
+/// Name hashes of `dump_hashes` without a name in `names`, the targets of
+/// name recovery.
+pub fn unresolved_hashes(meta: &MetaFile, names: &HashNames) -> BTreeMap<u32, HashKind> {
+    dump_hashes(meta)
+        .into_iter()
+        .filter(|&(key, _)| !key.0 && !is_resolved(names, key))
+        .map(|((_, hash), x)| (hash as u32, x.kind))
+        .collect()
+}
+
+/// Names among `candidates` whose FNV-1a hash is in `targets`. The first
+/// candidate in sorted order wins when several differ only by case.
+pub fn recover_names(
+    targets: &BTreeMap<u32, HashKind>,
+    candidates: &BTreeSet<String>,
+) -> BTreeMap<u32, String> {
+    let mut results = BTreeMap::new();
+    for text in candidates {
+        let hash = fnv1a(text);
+        if targets.contains_key(&hash) {
+            results.entry(hash).or_insert_with(|| text.clone());
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::hash_dict::coverage;
+    use crate::schema::testing::{class, container, meta, property};
+    use crate::types::BinType;
+    use crate::unresolved::collect_unresolved;
+
+    fn sample() -> MetaFile {
+        let mut holder = class(
+            Some(0x99),
+            32,
+            vec![
+                (0x1, property(0, BinType::Hash, None)),
+                (0x2, property(8, BinType::File, None)),
+                (0x3, container(16, BinType::List, BinType::Link, None)),
+                (0x4, property(24, BinType::Embed, Some(0x20))),
+            ],
+        );
+        holder["defaults"] = json!({
+            "0x1": "0x10",
+            "0x2": "0x123456789abcdef",
+            "0x3": ["0xaaaa", "0xbbbb"],
+        });
+        let item = class(None, 8, vec![(0x5, property(0, BinType::U32, None))]);
+        meta(vec![(0x10, holder), (0x20, item)])
+    }
+
+    #[test]
+    fn hash_counts_agree() {
+        let meta = sample();
+        let mut names = HashNames::new();
+        names.insert(0x10, "Holder");
+        names.insert(0xaaaa, "Annie");
+        names.insert_file(0x123456789abcdef, "data/annie.bin");
+
+        let hashes = dump_hashes(&meta);
+        assert_eq!(hashes.len(), 11);
+        assert_eq!(hashes[&(false, 0x10)].kind, HashKind::Class);
+        assert_eq!(hashes[&(false, 0x10)].class, Some(0x99));
+        assert_eq!(hashes[&(false, 0x99)].kind, HashKind::Class);
+        assert_eq!(hashes[&(false, 0xbbbb)].kind, HashKind::Link);
+        assert_eq!(hashes[&(false, 0xbbbb)].property, Some(0x3));
+        assert_eq!(hashes[&(true, 0x123456789abcdef)].kind, HashKind::File);
+
+        let unresolved = collect_unresolved(&meta, &names);
+        let coverage = coverage(&meta, &names);
+        let missing: usize = coverage.values().map(|(done, total)| total - done).sum();
+        assert_eq!(unresolved.len(), 8);
+        assert_eq!(missing, unresolved.len());
+        assert_eq!(unresolved_hashes(&meta, &names).len(), unresolved.len());
+        let link = unresolved.iter().find(|x| x.hash == 0xbbbb).unwrap();
+        assert_eq!(link.owner.as_deref(), Some("Holder.0x3"));
+        assert_eq!(link.type_name.as_deref(), Some("link"));
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::BTreeSet;
+
+use crate::brute_force::split_camel_case;
+use crate::hashes::{fnv1a, HashNames};
+use crate::schema::{MetaFile, PropertyInfo};
+use crate::types::BinType;
+
+/// Words swapped for each other in sibling names, both ways.
+const OPPOSITES: &[(&str, &str)] = &[
+    ("Min", "Max"),
+    ("Start", "End"),
+    ("Begin", "End"),
+    ("Start", "Stop"),
+    ("First", "Last"),
+    ("Left", "Right"),
+    ("Top", "Bottom"),
+    ("Up", "Down"),
+    ("Front", "Back"),
+    ("Inner", "Outer"),
+    ("Near", "Far"),
+    ("Lower", "Upper"),
+    ("Low", "High"),
+    ("In", "Out"),
+    ("On", "Off"),
+    ("Open", "Close"),
+    ("Enter", "Exit"),
+    ("Show", "Hide"),
+    ("Enable", "Disable"),
+    ("Enabled", "Disabled"),
+    ("Source", "Target"),
+    ("Src", "Dst"),
+    ("Width", "Height"),
+    ("X", "Y"),
+    ("Y", "Z"),
+    ("X", "Z"),
+    ("Horizontal", "Vertical"),
+    ("Ally", "Enemy"),
+    ("Friendly", "Enemy"),
+    ("Self", "Target"),
+    ("Old", "New"),
+    ("Previous", "Next"),
+    ("Before", "After"),
+    ("Local", "World"),
+];
+
+/// Leading words of bool names.
+const BOOL_PREFIXES: &[&str] = &["Is", "Has", "Can", "Should", "Use", "Enable", "Disable"];
+
+/// Words commonly ending names of a value type.
+fn type_words(value_type: BinType) -> &'static [&'static str] {
+    match value_type {
+        BinType::Bool | BinType::Flag => &["Enabled", "Disabled", "Visible", "Hidden", "Active"],
+        BinType::I8
+        | BinType::U8
+        | BinType::I16
+        | BinType::U16
+        | BinType::I32
+        | BinType::U32
+        | BinType::I64
+        | BinType::U64 => &[
+            "Count", "Index", "Id", "Flags", "Type", "Mode", "Level", "Priority", "Layer", "Max",
+            "Min", "Limit", "Size", "Number",
+        ],
+        BinType::F32 => &[
+            "Speed",
+            "Duration",
+            "Time",
+            "Radius",
+            "Range",
+            "Scale",
+            "Delay",
+            "Rate",
+            "Angle",
+            "Height",
+            "Width",
+            "Distance",
+            "Percent",
+            "Multiplier",
+            "Offset",
+            "Alpha",
+            "Weight",
+            "Amount",
+            "Value",
+            "Min",
+            "Max",
+            "Cooldown",
+            "Factor",
+            "Threshold",
+        ],
+        BinType::Vec2 | BinType::Vec3 | BinType::Vec4 => &[
+            "Position",
+            "Offset",
+            "Direction",
+            "Scale",
+            "Velocity",
+            "Size",
+            "Range",
+            "Rotation",
+            "Axis",
+            "Center",
+            "Extents",
+        ],
+        BinType::Mtx44 => &["Transform", "Matrix"],
+        BinType::Color => &["Color", "Tint", "Colour"],
+        BinType::String => &[
+            "Name", "Path", "Texture", "Mesh", "Skeleton", "Material", "Key", "Text", "Label",
+            "File", "Event", "Sound", "Tag",
+        ],
+        BinType::Hash | BinType::Link => &["Name", "Key", "Id", "Hash", "Link", "Ref", "Data"],
+        BinType::File => &["Path", "File", "Texture", "Mesh"],
+        BinType::Pointer | BinType::Embed => &["Data", "Info", "Definition", "Settings"],
+        _ => &[],
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub class: u32,
+    pub property: u32,
+    pub name: String,
+    /// Where the candidate came from, like `opposite of mMinSpeed`.
+    pub reason: String,
+}
+
+struct Suggester<'a> {
+    meta: &'a MetaFile,
+    names: &'a HashNames,
+    results: Vec<Suggestion>,
+}
+
+impl<'a> Suggester<'a> {
+    /// Keeps `name` when it hashes to `property`, once per spelling.
+    fn verify(&mut self, class: u32, property: u32, name: String, reason: &dyn Fn() -> String) {
+        if fnv1a(&name) != property
+            || self
+                .results
+                .iter()
+                .any(|x| x.property == property && x.name.eq_ignore_ascii_case(&name))
+        {
+            return;
+        }
+        self.results.push(Suggestion {
+            class,
+            property,
+            name,
+            reason: reason(),
+        });
+    }
+
+    /// Type of the stored values, the item type for containers and maps.
+    fn value_type(info: &PropertyInfo) -> BinType {
+        match (&info.container, &info.map) {
+            (Some(container), _) => container.value_type,
+            (_, Some(map)) => map.value_type,
+            _ => info.value_type,
+        }
+    }
+
+    fn property(&mut self, class: u32, property: u32, info: &PropertyInfo) {
+        let siblings: Vec<&str> = self
+            .meta
+            .all_properties(class)
+            .into_iter()
+            .filter_map(|(_, hash, _)| self.names.get(hash))
+            .collect();
+        let mut context = BTreeSet::new();
+        for name in &siblings {
+            context.extend(split_camel_case(name));
+        }
+        let mut classes = vec![class];
+        classes.extend(self.meta.direct_bases(class).into_iter().map(|(x, _)| x));
+        classes.extend(info.other_class);
+        for name in classes.into_iter().filter_map(|x| self.names.get(x)) {
+            context.insert(name.to_string());
+            context.extend(split_camel_case(name));
+        }
+        let value_type = Self::value_type(info);
+        let plural = info.container.is_some() || info.map.is_some();
+        let vocabulary: BTreeSet<String> = context
+            .iter()
+            .cloned()
+            .chain(type_words(value_type).iter().map(|x| x.to_string()))
+            .collect();
+
+        // Stronger evidence first, so each name keeps the best reason found.
+        for sibling in &siblings {
+            self.opposites(class, property, sibling);
+        }
+        for sibling in &siblings {
+            self.numbered(class, property, sibling);
+        }
+        for sibling in &siblings {
+            self.replaced(class, property, sibling, &vocabulary);
+        }
+        for sibling in &siblings {
+            self.extended(class, property, sibling, &vocabulary);
+        }
+        // Joins of a context word with a type word, like `mSpell` + `Name`.
+        for first in context.iter().map(|x| x.as_str()).chain([""]) {
+            for &last in type_words(value_type).iter().chain(&[""]) {
+                let base = format!("m{}{}", first, last);
+                let reason = || format!("{:?} vocabulary", value_type);
+                self.verify(class, property, base.clone(), &reason);
+                if plural {
+                    self.verify(class, property, format!("{}s", base), &reason);
+                }
+            }
+            if matches!(value_type, BinType::Bool | BinType::Flag) {
+                for prefix in BOOL_PREFIXES {
+                    let reason = || "bool prefix".to_string();
+                    self.verify(class, property, format!("m{}{}", prefix, first), &reason);
+                }
+            }
+        }
+    }
+
+    fn opposites(&mut self, class: u32, property: u32, sibling: &str) {
+        for &(a, b) in OPPOSITES {
+            for (from, to) in [(a, b), (b, a)] {
+                if sibling.contains(from) {
+                    let reason = || format!("opposite of {}", sibling);
+                    self.verify(class, property, sibling.replace(from, to), &reason);
+                }
+            }
+        }
+    }
+
+    fn numbered(&mut self, class: u32, property: u32, sibling: &str) {
+        let stem = sibling.trim_end_matches(|c: char| c.is_ascii_digit());
+        for number in 0..=9 {
+            let reason = || format!("numbered like {}", sibling);
+            self.verify(class, property, format!("{}{}", stem, number), &reason);
+        }
+        self.verify(class, property, stem.to_string(), &|| {
+            format!("unnumbered {}", sibling)
+        });
+    }
+
+    /// Words of `sibling` and the member prefix it uses.
+    fn words(sibling: &str) -> (&'static str, Vec<String>) {
+        let has_prefix = sibling
+            .strip_prefix('m')
+            .is_some_and(|x| x.starts_with(|c: char| c.is_ascii_uppercase()));
+        let prefix = if has_prefix { "m" } else { "" };
+        (prefix, split_camel_case(sibling))
+    }
+
+    /// Each word of `sibling` replaced by one from `vocabulary`.
+    fn replaced(
+        &mut self,
+        class: u32,
+        property: u32,
+        sibling: &str,
+        vocabulary: &BTreeSet<String>,
+    ) {
+        let (prefix, words) = Self::words(sibling);
+        for index in 0..words.len() {
+            for word in vocabulary {
+                if word.eq_ignore_ascii_case(&words[index]) {
+                    continue;
+                }
+                let mut replaced = words.clone();
+                replaced[index] = word.clone();
+                let name = format!("{}{}", prefix, replaced.concat());
+                let reason = || format!("{} with {} for {}", sibling, word, words[index]);
+                self.verify(class, property, name, &reason);
+            }
+        }
+    }
+
+    /// One more word anywhere, like `mSpeed` to `mSpeedMax`.
+    fn extended(
+        &mut self,
+        class: u32,
+        property: u32,
+        sibling: &str,
+        vocabulary: &BTreeSet<String>,
+    ) {
+        let (prefix, words) = Self::words(sibling);
+        for index in 0..=words.len() {
+            for word in vocabulary {
+                let mut extended = words.clone();
+                extended.insert(index, word.clone());
+                let name = format!("{}{}", prefix, extended.concat());
+                let reason = || format!("{} with {} added", sibling, word);
+                self.verify(class, property, name, &reason);
+            }
+        }
+    }
+}
+
+/// Names for unresolved properties built from resolved siblings, base and
+/// referenced class names and words typical of the property type. Only
+/// candidates whose FNV-1a hash matches are returned.
+pub fn suggest_names(meta: &MetaFile, names: &HashNames) -> Vec<Suggestion> {
+    let mut suggester = Suggester {
+        meta,
+        names,
+        results: Vec::new(),
+    };
+    for (&class, info) in &meta.classes {
+        for (&property, info) in &info.properties {
+            if names.get(property).is_none() {
+                suggester.property(class, property, info);
+            }
+        }
+    }
+    suggester.results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::{class, container, meta, property};
+
+    #[test]
+    fn suggestions_are_verified_and_keep_the_strongest_reason() {
+        let spell = fnv1a("SpellData");
+        let h = fnv1a;
+        let meta = meta(vec![(
+            spell,
+            class(
+                None,
+                64,
+                vec![
+                    (h("mMinSpeed"), property(0, BinType::F32, None)),
+                    (h("mMaxSpeed"), property(4, BinType::F32, None)),
+                    (h("mSpeed"), property(8, BinType::F32, None)),
+                    (h("mSpeed2"), property(12, BinType::F32, None)),
+                    (
+                        h("mSpellNames"),
+                        container(16, BinType::List, BinType::String, None),
+                    ),
+                    (h("mSpeedMax"), property(32, BinType::F32, None)),
+                    (h("mCastTime"), property(36, BinType::F32, None)),
+                ],
+            ),
+        )]);
+        let mut names = HashNames::new();
+        for name in ["SpellData", "mMinSpeed", "mSpeed"] {
+            names.insert(h(name), name);
+        }
+        let suggestions = suggest_names(&meta, &names);
+        for suggestion in &suggestions {
+            assert_eq!(suggestion.class, spell);
+            assert_eq!(fnv1a(&suggestion.name), suggestion.property);
+        }
+        let found = suggestions
+            .iter()
+            .map(|x| (x.name.as_str(), x.reason.as_str()))
+            .collect::<Vec<_>>();
+        // Ordered by property hash, mCastTime matches nothing.
+        assert_eq!(
+            found,
+            vec![
+                ("mSpeed2", "numbered like mSpeed"),
+                ("mSpeedMax", "mSpeed with Max added"),
+                ("mSpellNames", "String vocabulary"),
+                ("mMaxSpeed", "opposite of mMinSpeed"),
+            ]
+        );
+    }
+}
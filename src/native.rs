@@ -1,5 +1,10 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+
 use pelite::pattern;
 use pelite::pe::{Pe, PeView, Rva};
+use sha2::{Digest, Sha256};
 use winapi::um::consoleapi::AllocConsole;
 use winapi::um::handleapi::CloseHandle;
 use winapi::um::handleapi::INVALID_HANDLE_VALUE;
@@ -16,6 +21,12 @@ pub struct ModuleInfo {
     pub base: usize,
     pub version: String,
     pub image_size: usize,
+    pub image_base: u64,
+    pub size_of_image: u32,
+    pub time_date_stamp: u32,
+    pub version_strings: BTreeMap<String, String>,
+    /// `None` when the executable could not be read.
+    pub sha256: Option<String>,
 }
 
 impl ModuleInfo {
@@ -26,19 +37,33 @@ impl ModuleInfo {
             let code_base = module.optional_header().BaseOfCode as usize;
             let code_size = module.optional_header().SizeOfCode as usize;
             let image_size = code_base + code_size;
+            let image_base = module.optional_header().ImageBase as u64;
+            let size_of_image = module.optional_header().SizeOfImage;
+            let time_date_stamp = module.file_header().TimeDateStamp;
             let resources = module.resources().expect("Failed to open resources");
             let mut version = String::new();
+            let mut version_strings = BTreeMap::new();
             if let Ok(version_info) = resources.version_info() {
                 if let Some(lang) = version_info.translation().get(0) {
                     if let Some(product_version) = version_info.value(*lang, "ProductVersion") {
                         version = product_version.replace('\0', "");
                     }
+                    version_info.strings(*lang, |key, value| {
+                        version_strings.insert(key.replace('\0', ""), value.replace('\0', ""));
+                    });
                 }
             }
             Self {
                 base,
                 version,
                 image_size,
+                image_base,
+                size_of_image,
+                time_date_stamp,
+                version_strings,
+                sha256: executable_sha256()
+                    .map_err(|e| println!("Failed to hash executable: {}", e))
+                    .ok(),
             }
         }
     }
@@ -70,6 +95,17 @@ impl ModuleInfo {
     }
 }
 
+fn executable_sha256() -> io::Result<String> {
+    let mut file = File::open(std::env::current_exe()?)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect())
+}
+
 pub fn pause_threads() {
     unsafe {
         let process = GetCurrentProcessId();
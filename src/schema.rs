@@ -0,0 +1,510 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::hashes::parse_hex;
+use crate::type_expr::TypeExpr;
+use crate::types::{BinType, ContainerStorage, MapStorage};
+
+fn de_hash_opt<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(text) => parse_hex(&text)
+            .map(|x| Some(x as u32))
+            .ok_or_else(|| de::Error::custom(format!("Invalid hash: {}", text))),
+        None => Ok(None),
+    }
+}
+
+fn de_hash_map<'de, D, V>(deserializer: D) -> Result<BTreeMap<u32, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    let mut results = BTreeMap::new();
+    for (key, value) in BTreeMap::<String, V>::deserialize(deserializer)? {
+        let key =
+            parse_hex(&key).ok_or_else(|| de::Error::custom(format!("Invalid hash: {}", key)))?;
+        results.insert(key as u32, value);
+    }
+    Ok(results)
+}
+
+fn de_defaults<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<BTreeMap<u32, Value>>, D::Error> {
+    match Option::<Map<String, Value>>::deserialize(deserializer)? {
+        Some(map) => {
+            let mut results = BTreeMap::new();
+            for (key, value) in map {
+                let key = parse_hex(&key)
+                    .ok_or_else(|| de::Error::custom(format!("Invalid hash: {}", key)))?;
+                results.insert(key as u32, value);
+            }
+            Ok(Some(results))
+        }
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ContainerInfo {
+    pub vtable: String,
+    pub value_type: BinType,
+    pub value_size: u32,
+    pub fixed_size: Option<usize>,
+    pub storage: Option<ContainerStorage>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MapInfo {
+    pub vtable: String,
+    pub key_type: BinType,
+    pub value_type: BinType,
+    pub storage: MapStorage,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PropertyInfo {
+    #[serde(deserialize_with = "de_hash_opt")]
+    pub other_class: Option<u32>,
+    pub offset: u32,
+    pub bitmask: u8,
+    pub value_type: BinType,
+    pub container: Option<ContainerInfo>,
+    pub map: Option<MapInfo>,
+    pub unkptr: String,
+}
+
+impl PropertyInfo {
+    pub fn type_expr(&self) -> TypeExpr {
+        TypeExpr::new(
+            self.value_type,
+            self.map.as_ref().map(|m| m.key_type),
+            self.container
+                .as_ref()
+                .map(|c| c.value_type)
+                .or_else(|| self.map.as_ref().map(|m| m.value_type)),
+            self.other_class,
+        )
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClassFlags {
+    pub interface: bool,
+    pub value: bool,
+    pub secondary_base: bool,
+    pub unk5: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClassInfo {
+    #[serde(deserialize_with = "de_hash_opt")]
+    pub base: Option<u32>,
+    #[serde(deserialize_with = "de_hash_map")]
+    pub secondary_bases: BTreeMap<u32, u32>,
+    #[serde(deserialize_with = "de_hash_map")]
+    pub secondary_children: BTreeMap<u32, u32>,
+    pub size: usize,
+    pub alignment: usize,
+    pub is: ClassFlags,
+    #[serde(rename = "fn")]
+    pub functions: BTreeMap<String, Option<String>>,
+    #[serde(deserialize_with = "de_hash_map")]
+    pub properties: BTreeMap<u32, PropertyInfo>,
+    #[serde(deserialize_with = "de_defaults")]
+    pub defaults: Option<BTreeMap<u32, Value>>,
+}
+
+/// Typed view of a `meta_<version>.json` file written by the dumper.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MetaFile {
+    #[serde(default)]
+    pub format: Option<u32>,
+    pub version: String,
+    #[serde(default)]
+    pub source: Option<Value>,
+    #[serde(deserialize_with = "de_hash_map")]
+    pub classes: BTreeMap<u32, ClassInfo>,
+    /// Registry indices of class hashes registered more than once.
+    #[serde(default, deserialize_with = "de_hash_map")]
+    pub duplicates: BTreeMap<u32, Vec<usize>>,
+}
+
+impl MetaFile {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        serde_json::from_slice(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn class(&self, hash: u32) -> Option<&ClassInfo> {
+        self.classes.get(&hash)
+    }
+
+    /// Base class followed by secondary bases, with their offsets inside `hash`.
+    pub fn direct_bases(&self, hash: u32) -> Vec<(u32, u32)> {
+        let mut results = Vec::new();
+        if let Some(class) = self.class(hash) {
+            if let Some(base) = class.base {
+                results.push((base, 0));
+            }
+            for (&base, &offset) in &class.secondary_bases {
+                results.push((base, offset));
+            }
+        }
+        results
+    }
+
+    /// `hash` followed by every class it derives from, depth first, with
+    /// their offsets inside `hash`. Each class is listed once, so bases that
+    /// loop back in a malformed dump end the walk instead of recursing forever.
+    pub fn ancestors(&self, hash: u32) -> Vec<(u32, u32)> {
+        let mut results = Vec::new();
+        let mut seen = BTreeSet::new();
+        self.collect_ancestors(hash, 0, &mut seen, &mut results);
+        results
+    }
+
+    fn collect_ancestors(
+        &self,
+        hash: u32,
+        offset: u32,
+        seen: &mut BTreeSet<u32>,
+        results: &mut Vec<(u32, u32)>,
+    ) {
+        if !seen.insert(hash) {
+            return;
+        }
+        results.push((hash, offset));
+        for (base, base_offset) in self.direct_bases(hash) {
+            self.collect_ancestors(base, offset.wrapping_add(base_offset), seen, results);
+        }
+    }
+
+    /// Looks up a property on `hash` or any of its bases.
+    /// Returns the owning class and the offset of that class inside `hash`.
+    pub fn find_property(&self, hash: u32, property: u32) -> Option<(u32, u32, &PropertyInfo)> {
+        self.class(hash)?;
+        self.ancestors(hash)
+            .into_iter()
+            .find_map(|(owner, offset)| {
+                let info = self.class(owner)?.properties.get(&property)?;
+                Some((owner, offset, info))
+            })
+    }
+
+    /// All properties of `hash` including inherited ones, bases first.
+    pub fn all_properties(&self, hash: u32) -> Vec<(u32, u32, &PropertyInfo)> {
+        let mut results = Vec::new();
+        let mut seen = BTreeSet::new();
+        self.collect_properties(hash, &mut seen, &mut results);
+        results
+    }
+
+    fn collect_properties<'a>(
+        &'a self,
+        hash: u32,
+        seen: &mut BTreeSet<u32>,
+        results: &mut Vec<(u32, u32, &'a PropertyInfo)>,
+    ) {
+        if !seen.insert(hash) {
+            return;
+        }
+        for (base, _) in self.direct_bases(hash) {
+            self.collect_properties(base, seen, results);
+        }
+        if let Some(class) = self.class(hash) {
+            for (&property, info) in &class.properties {
+                if results.iter().all(|&(_, other, _)| other != property) {
+                    results.push((hash, property, info));
+                }
+            }
+        }
+    }
+
+    /// Default value of a property as dumped, looking at base classes when
+    /// the derived class did not record it.
+    pub fn default_value(&self, hash: u32, property: u32) -> Option<&Value> {
+        self.class(hash)?;
+        self.ancestors(hash)
+            .into_iter()
+            .find_map(|(class, _)| self.class(class)?.defaults.as_ref()?.get(&property))
+    }
+
+    pub fn is_subclass(&self, hash: u32, parent: u32) -> bool {
+        self.ancestors(hash)
+            .iter()
+            .any(|&(class, _)| class == parent)
+    }
+
+    /// Every class that derives from `parent`, including `parent` itself.
+    pub fn subclasses(&self, parent: u32) -> Vec<u32> {
+        self.classes
+            .keys()
+            .copied()
+            .filter(|&hash| self.is_subclass(hash, parent))
+            .collect()
+    }
+}
+
+/// Synthetic dumps for tests of the offline tools.
+#[cfg(test)]
+pub mod testing {
+    use serde_json::{json, Map, Value};
+
+    use super::MetaFile;
+    use crate::types::BinType;
+
+    fn hex(hash: u32) -> String {
+        format!("0x{:x}", hash)
+    }
+
+    pub fn property(offset: u32, value_type: BinType, other_class: Option<u32>) -> Value {
+        json!({
+            "other_class": other_class.map(hex),
+            "offset": offset,
+            "bitmask": 0,
+            "value_type": value_type,
+            "container": null,
+            "map": null,
+            "unkptr": "0x0",
+        })
+    }
+
+    /// List, list2 or option property holding `item_type`.
+    pub fn container(
+        offset: u32,
+        value_type: BinType,
+        item_type: BinType,
+        other_class: Option<u32>,
+    ) -> Value {
+        let mut result = property(offset, value_type, other_class);
+        result["container"] = json!({
+            "vtable": "0x0",
+            "value_type": item_type,
+            "value_size": 4,
+            "fixed_size": null,
+            "storage": (value_type != BinType::Option).then_some("RitoVector"),
+        });
+        result
+    }
+
+    pub fn map(
+        offset: u32,
+        key_type: BinType,
+        value_type: BinType,
+        other_class: Option<u32>,
+    ) -> Value {
+        let mut result = property(offset, BinType::Map, other_class);
+        result["map"] = json!({
+            "vtable": "0x0",
+            "key_type": key_type,
+            "value_type": value_type,
+            "storage": "UnknownMap",
+        });
+        result
+    }
+
+    /// Concrete class without defaults, `properties` keyed by hash.
+    pub fn class(base: Option<u32>, size: usize, properties: Vec<(u32, Value)>) -> Value {
+        let properties: Map<String, Value> = properties
+            .into_iter()
+            .map(|(hash, x)| (hex(hash), x))
+            .collect();
+        json!({
+            "base": base.map(hex),
+            "secondary_bases": {},
+            "secondary_children": {},
+            "size": size,
+            "alignment": 8,
+            "is": {"interface": false, "value": false, "secondary_base": false, "unk5": false},
+            "fn": {},
+            "properties": properties,
+            "defaults": {},
+        })
+    }
+
+    pub fn meta(classes: Vec<(u32, Value)>) -> MetaFile {
+        let classes: Map<String, Value> = classes
+            .into_iter()
+            .map(|(hash, x)| (hex(hash), x))
+            .collect();
+        serde_json::from_value(json!({
+            "format": crate::types::FORMAT_VERSION,
+            "version": "1.0.test",
+            "classes": classes,
+        }))
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::testing::{class, meta, property};
+    use super::MetaFile;
+    use crate::types::{BinType, FORMAT_VERSION};
+
+    /// Dump in the shape the dumper writes, an interface with a fixed list
+    /// and a map, and a class deriving from it with a secondary base.
+    fn sample() -> Value {
+        let functions = json!({
+            "upcast_secondary": null,
+            "constructor": "0x140001000",
+            "destructor": "0x140001100",
+            "inplace_constructor": null,
+            "inplace_destructor": null,
+            "register": null,
+        });
+        let property = |offset: u32, value_type: &str| {
+            json!({
+                "type": value_type.to_lowercase(),
+                "other_class": null,
+                "offset": offset,
+                "bitmask": 0,
+                "value_type": value_type,
+                "container": null,
+                "map": null,
+                "unkptr": "0x14a000000",
+            })
+        };
+        let mut fixed = property(0, "List");
+        fixed["type"] = json!("list[vec3]");
+        fixed["container"] = json!({
+            "vtable": "0x14a001000",
+            "value_type": "Vec3",
+            "value_size": 12,
+            "fixed_size": 4,
+            "storage": "Fixed",
+        });
+        let mut map = property(48, "Map");
+        map["type"] = json!("map[hash,pointer<0x1000>]");
+        map["other_class"] = json!("0x1000");
+        map["map"] = json!({
+            "vtable": "0x14a002000",
+            "key_type": "Hash",
+            "value_type": "Pointer",
+            "storage": "StdMap",
+        });
+        let mut option = property(64, "Option");
+        option["type"] = json!("option[f32]");
+        option["container"] = json!({
+            "vtable": "0x14a003000",
+            "value_type": "F32",
+            "value_size": 4,
+            "fixed_size": null,
+            "storage": null,
+        });
+        let mut flag = property(72, "Flag");
+        flag["bitmask"] = json!(3);
+        json!({
+            "format": FORMAT_VERSION,
+            "version": "14.1.0.0",
+            "source": {
+                "sha256": "0".repeat(64),
+                "time_date_stamp": 1700000000,
+                "image_base": "0x140000000",
+                "size_of_image": 40000000,
+                "version_info": {"ProductVersion": "14.1.0.0"},
+                "signature": "48 8B 05 ? ? ? ?",
+                "registry": "0x14b000000",
+                "dumper": "lolmetadumper2 0.1.0",
+                "dumped_at": 1700000100,
+            },
+            "classes": {
+                "0x1000": {
+                    "base": null,
+                    "secondary_bases": {},
+                    "secondary_children": {"0x2000": 96},
+                    "size": 64,
+                    "alignment": 8,
+                    "is": {"interface": true, "value": false, "secondary_base": false, "unk5": false},
+                    "fn": functions.clone(),
+                    "properties": {"0x1": fixed, "0x2": map},
+                    "defaults": null,
+                },
+                "0x3000": {
+                    "base": "0x1000",
+                    "secondary_bases": {"0x2000": 96},
+                    "secondary_children": {},
+                    "size": 128,
+                    "alignment": 8,
+                    "is": {"interface": false, "value": false, "secondary_base": false, "unk5": false},
+                    "fn": functions,
+                    "properties": {"0x11": option, "0x12": flag},
+                    "defaults": {"0x1": vec![[0.0f32; 3]; 4], "0x2": {}, "0x11": null, "0x12": false},
+                },
+            },
+            "duplicates": {"0x1000": [0, 1]},
+        })
+    }
+
+    fn schema_errors(document: &Value) -> Vec<String> {
+        let schema = serde_json::from_str(include_str!("../schema/meta.schema.json")).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        validator
+            .iter_errors(document)
+            .map(|x| x.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn sample_matches_schema_and_loads() {
+        let document = sample();
+        assert_eq!(schema_errors(&document), Vec::<String>::new());
+        let meta: MetaFile = serde_json::from_value(document).unwrap();
+        assert_eq!(meta.format, Some(FORMAT_VERSION));
+        assert_eq!(meta.class(0x3000).unwrap().base, Some(0x1000));
+        assert_eq!(meta.duplicates[&0x1000], vec![0, 1]);
+    }
+
+    #[test]
+    fn cyclic_bases_end_the_walk() {
+        let mut first = class(Some(0x20), 8, vec![(0x1, property(0, BinType::U8, None))]);
+        first["defaults"] = json!({"0x1": 3});
+        let second = class(Some(0x10), 8, vec![(0x2, property(4, BinType::U8, None))]);
+        let meta = meta(vec![(0x10, first), (0x20, second)]);
+        assert_eq!(meta.ancestors(0x10), vec![(0x10, 0), (0x20, 0)]);
+        assert!(meta.is_subclass(0x10, 0x20));
+        assert!(!meta.is_subclass(0x10, 0x30));
+        assert_eq!(meta.subclasses(0x20), vec![0x10, 0x20]);
+        assert_eq!(meta.find_property(0x20, 0x1).map(|x| x.0), Some(0x10));
+        assert!(meta.find_property(0x20, 0x3).is_none());
+        assert_eq!(meta.default_value(0x20, 0x1), Some(&json!(3)));
+        assert_eq!(meta.default_value(0x20, 0x2), None);
+        assert_eq!(meta.all_properties(0x10).len(), 2);
+    }
+
+    #[test]
+    fn schema_accepts_unreadable_executable() {
+        let mut document = sample();
+        document["source"]["sha256"] = Value::Null;
+        assert_eq!(schema_errors(&document), Vec::<String>::new());
+        document["source"]["sha256"] = json!("not a hash");
+        assert_eq!(schema_errors(&document).len(), 1);
+    }
+
+    #[test]
+    fn schema_rejects_changed_layout() {
+        let mut document = sample();
+        let property = &mut document["classes"]["0x3000"]["properties"]["0x11"];
+        property.as_object_mut().unwrap().remove("unkptr");
+        property["container"]["storage"] = json!("RitoVector");
+        document["classes"]["0x1000"]["size"] = json!(-1);
+        assert_eq!(schema_errors(&document).len(), 3);
+    }
+
+    #[test]
+    fn schema_accepts_format_version() {
+        let schema: Value =
+            serde_json::from_str(include_str!("../schema/meta.schema.json")).unwrap();
+        let formats = schema["properties"]["format"]["enum"].as_array().unwrap();
+        assert!(formats.contains(&json!(FORMAT_VERSION)));
+    }
+}
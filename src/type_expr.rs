@@ -0,0 +1,314 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::hashes::fnv1a;
+use crate::types::BinType;
+
+const TYPE_NAMES: [(BinType, &str); 27] = [
+    (BinType::None, "none"),
+    (BinType::Bool, "bool"),
+    (BinType::I8, "i8"),
+    (BinType::U8, "u8"),
+    (BinType::I16, "i16"),
+    (BinType::U16, "u16"),
+    (BinType::I32, "i32"),
+    (BinType::U32, "u32"),
+    (BinType::I64, "i64"),
+    (BinType::U64, "u64"),
+    (BinType::F32, "f32"),
+    (BinType::Vec2, "vec2"),
+    (BinType::Vec3, "vec3"),
+    (BinType::Vec4, "vec4"),
+    (BinType::Mtx44, "mtx44"),
+    (BinType::Color, "rgba"),
+    (BinType::String, "string"),
+    (BinType::Hash, "hash"),
+    (BinType::File, "file"),
+    (BinType::List, "list"),
+    (BinType::List2, "list2"),
+    (BinType::Pointer, "pointer"),
+    (BinType::Embed, "embed"),
+    (BinType::Link, "link"),
+    (BinType::Option, "option"),
+    (BinType::Map, "map"),
+    (BinType::Flag, "flag"),
+];
+
+/// Lowercase name of a type as used in type expressions and ritobin text.
+pub fn type_name(value: BinType) -> &'static str {
+    TYPE_NAMES.iter().find(|(t, _)| *t == value).unwrap().1
+}
+
+pub fn parse_type_name(text: &str) -> Option<BinType> {
+    TYPE_NAMES.iter().find(|(_, n)| *n == text).map(|(t, _)| *t)
+}
+
+/// Full type of a property, like `list2[embed<0x1234abcd>]` or `map[hash,pointer<Foo>]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeExpr {
+    /// Pointers, embeds and links carry their class when known.
+    Nestable(BinType, Option<u32>),
+    /// `list`, `list2` or `option` of an element type.
+    Container(BinType, Box<TypeExpr>),
+    Map(Box<TypeExpr>, Box<TypeExpr>),
+}
+
+impl TypeExpr {
+    pub fn nestable(value_type: BinType, class: Option<u32>) -> Self {
+        match value_type {
+            BinType::Pointer | BinType::Embed | BinType::Link => {
+                TypeExpr::Nestable(value_type, class)
+            }
+            _ => TypeExpr::Nestable(value_type, None),
+        }
+    }
+
+    /// Assembles the type from the separate fields the dump records per property.
+    pub fn new(
+        value_type: BinType,
+        key_type: Option<BinType>,
+        item_type: Option<BinType>,
+        class: Option<u32>,
+    ) -> Self {
+        let item = || Box::new(Self::nestable(item_type.unwrap_or(BinType::None), class));
+        match value_type {
+            BinType::List | BinType::List2 | BinType::Option => {
+                TypeExpr::Container(value_type, item())
+            }
+            BinType::Map => {
+                let key = Self::nestable(key_type.unwrap_or(BinType::None), None);
+                TypeExpr::Map(Box::new(key), item())
+            }
+            _ => Self::nestable(value_type, class),
+        }
+    }
+
+    pub fn value_type(&self) -> BinType {
+        match self {
+            TypeExpr::Nestable(value_type, _) | TypeExpr::Container(value_type, _) => *value_type,
+            TypeExpr::Map(..) => BinType::Map,
+        }
+    }
+
+    /// Element type of containers and value type of maps.
+    pub fn item(&self) -> Option<&TypeExpr> {
+        match self {
+            TypeExpr::Nestable(..) => None,
+            TypeExpr::Container(_, item) | TypeExpr::Map(_, item) => Some(item),
+        }
+    }
+
+    pub fn key(&self) -> Option<&TypeExpr> {
+        match self {
+            TypeExpr::Map(key, _) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Class of the pointer, embed or link, directly or as element.
+    pub fn class(&self) -> Option<u32> {
+        match self {
+            TypeExpr::Nestable(_, class) => *class,
+            _ => self.item()?.class(),
+        }
+    }
+
+    /// Same type with classes dropped, as written by ritobin.
+    pub fn without_classes(&self) -> Self {
+        match self {
+            TypeExpr::Nestable(value_type, _) => TypeExpr::Nestable(*value_type, None),
+            TypeExpr::Container(value_type, item) => {
+                TypeExpr::Container(*value_type, Box::new(item.without_classes()))
+            }
+            TypeExpr::Map(key, value) => TypeExpr::Map(
+                Box::new(key.without_classes()),
+                Box::new(value.without_classes()),
+            ),
+        }
+    }
+
+    /// Prints the expression with `class_name` deciding how classes are spelled.
+    pub fn format_with(&self, class_name: &dyn Fn(u32) -> String) -> String {
+        match self {
+            TypeExpr::Nestable(value_type, Some(class)) => {
+                format!("{}<{}>", type_name(*value_type), class_name(*class))
+            }
+            TypeExpr::Nestable(value_type, None) => type_name(*value_type).to_string(),
+            TypeExpr::Container(value_type, item) => {
+                format!(
+                    "{}[{}]",
+                    type_name(*value_type),
+                    item.format_with(class_name)
+                )
+            }
+            TypeExpr::Map(key, value) => format!(
+                "map[{},{}]",
+                key.format_with(class_name),
+                value.format_with(class_name)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format_with(&|class| format!("{:#x}", class)))
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_spaces(&mut self) {
+        while self.text[self.pos..].starts_with(' ') {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_spaces();
+        if self.text[self.pos..].starts_with(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("expected {:?} at {}", c, self.pos))
+        }
+    }
+
+    fn word(&mut self) -> &'a str {
+        self.skip_spaces();
+        let rest = &self.text[self.pos..];
+        let size = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        self.pos += size;
+        &rest[..size]
+    }
+
+    fn expr(&mut self) -> Result<TypeExpr, String> {
+        let start = self.pos;
+        let word = self.word();
+        let value_type =
+            parse_type_name(word).ok_or_else(|| format!("unknown type {:?} at {}", word, start))?;
+        match value_type {
+            BinType::List | BinType::List2 | BinType::Option => {
+                self.expect('[')?;
+                let item = self.expr()?;
+                self.expect(']')?;
+                Ok(TypeExpr::Container(value_type, Box::new(item)))
+            }
+            BinType::Map => {
+                self.expect('[')?;
+                let key = self.expr()?;
+                self.expect(',')?;
+                let value = self.expr()?;
+                self.expect(']')?;
+                Ok(TypeExpr::Map(Box::new(key), Box::new(value)))
+            }
+            BinType::Pointer | BinType::Embed | BinType::Link if self.eat('<') => {
+                let start = self.pos;
+                let name = self.word();
+                let class = match name.strip_prefix("0x") {
+                    Some(hex) => u32::from_str_radix(hex, 16)
+                        .map_err(|_| format!("invalid class {:?} at {}", name, start))?,
+                    None if !name.is_empty() => fnv1a(name),
+                    None => return Err(format!("expected class at {}", start)),
+                };
+                self.expect('>')?;
+                Ok(TypeExpr::Nestable(value_type, Some(class)))
+            }
+            _ => Ok(TypeExpr::Nestable(value_type, None)),
+        }
+    }
+}
+
+/// Classes may be written as `0x` hashes or names, names are hashed with FNV-1a.
+impl FromStr for TypeExpr {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { text, pos: 0 };
+        let result = parser.expr()?;
+        parser.skip_spaces();
+        if parser.pos != text.len() {
+            return Err(format!("unexpected {:?}", &text[parser.pos..]));
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples() -> Vec<TypeExpr> {
+        let mut results = Vec::new();
+        for value_type in BinType::ALL {
+            for class in [None, Some(fnv1a("Foo"))] {
+                let item = TypeExpr::nestable(value_type, class);
+                if value_type.is_nestable() {
+                    for container in [BinType::List, BinType::List2, BinType::Option] {
+                        results.push(TypeExpr::Container(container, Box::new(item.clone())));
+                    }
+                    let key = TypeExpr::nestable(BinType::Hash, None);
+                    results.push(TypeExpr::Map(Box::new(key), Box::new(item.clone())));
+                }
+                if !matches!(
+                    value_type,
+                    BinType::List | BinType::List2 | BinType::Option | BinType::Map
+                ) {
+                    results.push(item);
+                }
+            }
+        }
+        results
+    }
+
+    #[test]
+    fn printed_types_parse_back() {
+        for expr in samples() {
+            assert_eq!(expr.to_string().parse(), Ok(expr.clone()));
+            let named = expr.format_with(&|class| match class == fnv1a("Foo") {
+                true => "Foo".to_string(),
+                false => format!("{:#x}", class),
+            });
+            assert_eq!(named.parse(), Ok(expr));
+        }
+    }
+
+    #[test]
+    fn spaces_and_names_are_accepted() {
+        let expr = TypeExpr::Map(
+            Box::new(TypeExpr::nestable(BinType::U32, None)),
+            Box::new(TypeExpr::nestable(BinType::Pointer, Some(fnv1a("Foo")))),
+        );
+        assert_eq!("map[ u32, pointer<Foo> ]".parse(), Ok(expr));
+    }
+
+    #[test]
+    fn malformed_types_are_rejected() {
+        for text in [
+            "",
+            "foo",
+            "list[",
+            "list[u32",
+            "map[hash]",
+            "pointer<>",
+            "embed<0xzz>",
+            "u32 u32",
+        ] {
+            assert!(text.parse::<TypeExpr>().is_err(), "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Types shared by the dumper and the offline tools, free of the process
+//! memory layouts in `meta`.
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the dump layout, bump whenever schema/meta.schema.json changes.
+pub const FORMAT_VERSION: u32 = 4;
+
+#[allow(clippy::identity_op)]
+#[repr(u8)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum BinType {
+    None = 0,
+    Bool = 1,
+    I8 = 2,
+    U8 = 3,
+    I16 = 4,
+    U16 = 5,
+    I32 = 6,
+    U32 = 7,
+    I64 = 8,
+    U64 = 9,
+    F32 = 10,
+    Vec2 = 11,
+    Vec3 = 12,
+    Vec4 = 13,
+    Mtx44 = 14,
+    Color = 15,
+    String = 16,
+    Hash = 17,
+    File = 18,
+    List = 0x80 | 0,
+    List2 = 0x80 | 1,
+    Pointer = 0x80 | 2,
+    Embed = 0x80 | 3,
+    Link = 0x80 | 4,
+    Option = 0x80 | 5,
+    Map = 0x80 | 6,
+    Flag = 0x80 | 7,
+}
+
+impl BinType {
+    pub const ALL: [BinType; 27] = [
+        BinType::None,
+        BinType::Bool,
+        BinType::I8,
+        BinType::U8,
+        BinType::I16,
+        BinType::U16,
+        BinType::I32,
+        BinType::U32,
+        BinType::I64,
+        BinType::U64,
+        BinType::F32,
+        BinType::Vec2,
+        BinType::Vec3,
+        BinType::Vec4,
+        BinType::Mtx44,
+        BinType::Color,
+        BinType::String,
+        BinType::Hash,
+        BinType::File,
+        BinType::List,
+        BinType::List2,
+        BinType::Pointer,
+        BinType::Embed,
+        BinType::Link,
+        BinType::Option,
+        BinType::Map,
+        BinType::Flag,
+    ];
+
+    pub fn from_u8(value: u8) -> Option<BinType> {
+        Self::ALL.iter().copied().find(|&x| x as u8 == value)
+    }
+
+    pub fn is_nestable(self) -> bool {
+        !matches!(
+            self,
+            BinType::None
+                | BinType::List
+                | BinType::List2
+                | BinType::Map
+                | BinType::Option
+                | BinType::Flag
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[repr(C)]
+pub enum ContainerStorage {
+    UnknownVector,
+    Option,
+    Fixed,
+    StdVector,
+    RitoVector,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[repr(C)]
+#[allow(clippy::enum_variant_names)]
+pub enum MapStorage {
+    UnknownMap,
+    StdMap,
+    StdUnorderedMap,
+    RitoVectorMap,
+}
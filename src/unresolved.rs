@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::codegen::json_key;
+use crate::hashes::HashNames;
+use crate::name_recovery::{dump_hashes, is_resolved, HashKind};
+use crate::schema::MetaFile;
+
+/// Hash without a known name, with where the dump uses it first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedHash {
+    /// FNV-1a, or XXH64 for `HashKind::File`.
+    pub hash: u64,
+    pub kind: HashKind,
+    /// Base class of classes, declaring class of properties and
+    /// `Class.property` of default values.
+    pub owner: Option<String>,
+    /// Property type or value type of defaults.
+    pub type_name: Option<String>,
+}
+
+/// Hashes of `name_recovery::dump_hashes` missing from `names`, with the
+/// context of their first use.
+pub fn collect_unresolved(meta: &MetaFile, names: &HashNames) -> Vec<UnresolvedHash> {
+    let mut results = Vec::new();
+    for (key, info) in dump_hashes(meta) {
+        if is_resolved(names, key) {
+            continue;
+        }
+        let owner = match (info.class, info.property) {
+            (Some(class), Some(property)) => Some(format!(
+                "{}.{}",
+                json_key(names, class),
+                json_key(names, property)
+            )),
+            (class, _) => class.map(|x| json_key(names, x)),
+        };
+        results.push(UnresolvedHash {
+            hash: key.1,
+            kind: info.kind,
+            owner,
+            type_name: info
+                .type_expr
+                .map(|x| x.format_with(&|x| json_key(names, x))),
+        });
+    }
+    results.sort_by_key(|x| (x.kind, x.hash));
+    results
+}
+
+/// Version of the first dump in `dumps` that uses each hash, `dumps` being
+/// ordered oldest first.
+pub fn first_versions(dumps: &[MetaFile]) -> BTreeMap<(HashKind, u64), String> {
+    let names = HashNames::new();
+    let mut results = BTreeMap::new();
+    for meta in dumps {
+        for unresolved in collect_unresolved(meta, &names) {
+            results
+                .entry((unresolved.kind, unresolved.hash))
+                .or_insert_with(|| meta.version.clone());
+        }
+    }
+    results
+}
+
+/// Tab separated `hash kind owner type first_seen` lines under a header,
+/// `-` for missing context.
+pub fn unresolved_text(
+    unresolved: &[UnresolvedHash],
+    first_versions: &BTreeMap<(HashKind, u64), String>,
+) -> String {
+    let mut out = String::from("# hash\tkind\towner\ttype\tfirst_seen\n");
+    for entry in unresolved {
+        let hash = match entry.kind {
+            HashKind::File => format!("{:016x}", entry.hash),
+            _ => format!("{:08x}", entry.hash),
+        };
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}",
+            hash,
+            entry.kind.name(),
+            entry.owner.as_deref().unwrap_or("-"),
+            entry.type_name.as_deref().unwrap_or("-"),
+            first_versions
+                .get(&(entry.kind, entry.hash))
+                .map_or("-", |x| x.as_str())
+        )
+        .unwrap();
+    }
+    out
+}
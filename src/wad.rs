@@ -0,0 +1,364 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+use crate::bin::{read_bin, BinFile};
+use crate::hashes::xxh64;
+use crate::name_recovery::bin_strings;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WadCompression {
+    None,
+    Gzip,
+    /// Data is the path of a file in another archive.
+    Satellite,
+    Zstd,
+    /// Sequence of zstd frames and raw chunks described by the subchunk TOC.
+    ZstdChunked,
+}
+
+impl WadCompression {
+    fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => WadCompression::None,
+            1 => WadCompression::Gzip,
+            2 => WadCompression::Satellite,
+            3 => WadCompression::Zstd,
+            4 => WadCompression::ZstdChunked,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WadEntry {
+    /// XXH64 of the lowercase path.
+    pub path: u64,
+    pub offset: u32,
+    pub compressed_size: u32,
+    pub size: u32,
+    pub compression: WadCompression,
+    pub subchunk_count: u8,
+    pub is_duplicate: bool,
+    pub first_subchunk: u16,
+    pub checksum: u64,
+}
+
+/// Open WAD v3 archive, entry data is read on demand.
+pub struct WadFile {
+    file: File,
+    /// Length of the archive, entries must lie within it.
+    len: u64,
+    pub version: (u8, u8),
+    pub entries: Vec<WadEntry>,
+    /// Compressed and uncompressed size of every subchunk, when the archive
+    /// carries its `.subchunktoc`.
+    subchunks: Option<Vec<(u32, u32)>>,
+}
+
+/// `.wad` and `.wad.client` archives.
+pub fn is_wad(path: &Path) -> bool {
+    let name = path.to_string_lossy().to_ascii_lowercase();
+    name.ends_with(".wad") || name.ends_with(".wad.client")
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// Paths the archive's own `.subchunktoc` may be stored under, taken from the
+/// trailing components of `path` like `data/final/champions/annie.wad`.
+fn subchunk_toc_candidates(path: &Path) -> Vec<u64> {
+    let text = path
+        .to_string_lossy()
+        .replace('\\', "/")
+        .to_ascii_lowercase();
+    let text = text.strip_suffix(".client").unwrap_or(&text);
+    let parts: Vec<&str> = text.split('/').collect();
+    (0..parts.len())
+        .map(|start| xxh64(&format!("{}.subchunktoc", parts[start..].join("/"))))
+        .collect()
+}
+
+/// Decodes zstd frames one by one and copies the raw chunks in between.
+fn zstd_frames(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut result = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        if rest.starts_with(&ZSTD_MAGIC) {
+            let mut decoder = zstd::stream::read::Decoder::with_buffer(rest)?.single_frame();
+            decoder.read_to_end(&mut result)?;
+            rest = decoder.finish();
+        } else {
+            let end = rest
+                .windows(ZSTD_MAGIC.len())
+                .position(|x| x == ZSTD_MAGIC)
+                .unwrap_or(rest.len());
+            result.extend_from_slice(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+    Ok(result)
+}
+
+impl WadFile {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut header = [0u8; 272];
+        file.read_exact(&mut header)?;
+        if &header[..2] != b"RW" {
+            return Err(invalid("Not a WAD file"));
+        }
+        let version = (header[2], header[3]);
+        if version.0 != 3 {
+            return Err(invalid(format!(
+                "Unsupported WAD version {}.{}",
+                version.0, version.1
+            )));
+        }
+        // Signature and checksum sit between the version and the entry count.
+        let count = u32_at(&header, 268) as usize;
+        if (header.len() + count * 32) as u64 > len {
+            return Err(invalid(format!("{} entries past end of file", count)));
+        }
+        let mut toc = vec![0u8; count * 32];
+        file.read_exact(&mut toc)?;
+        let mut entries = Vec::with_capacity(count);
+        for data in toc.chunks(32) {
+            let kind = data[20];
+            let compression = WadCompression::from_u8(kind & 0xf)
+                .ok_or_else(|| invalid(format!("Unknown compression {}", kind & 0xf)))?;
+            entries.push(WadEntry {
+                path: u64_at(data, 0),
+                offset: u32_at(data, 8),
+                compressed_size: u32_at(data, 12),
+                size: u32_at(data, 16),
+                compression,
+                subchunk_count: kind >> 4,
+                is_duplicate: data[21] != 0,
+                first_subchunk: u16_at(data, 22),
+                checksum: u64_at(data, 24),
+            });
+        }
+        let mut result = Self {
+            file,
+            len,
+            version,
+            entries,
+            subchunks: None,
+        };
+        let candidates = subchunk_toc_candidates(path);
+        if let Some(entry) = result
+            .entries
+            .iter()
+            .find(|e| candidates.contains(&e.path))
+            .cloned()
+        {
+            let data = result.read(&entry)?;
+            let subchunks = data
+                .chunks_exact(16)
+                .map(|x| (u32_at(x, 0), u32_at(x, 4)))
+                .collect();
+            result.subchunks = Some(subchunks);
+        }
+        Ok(result)
+    }
+
+    pub fn entry(&self, path: u64) -> Option<&WadEntry> {
+        self.entries.iter().find(|e| e.path == path)
+    }
+
+    /// Reads and decompresses the data of `entry`. Satellite entries return
+    /// the redirection path as stored.
+    pub fn read(&mut self, entry: &WadEntry) -> io::Result<Vec<u8>> {
+        if entry.offset as u64 + entry.compressed_size as u64 > self.len {
+            return Err(invalid(format!(
+                "Entry {:016x} past end of file",
+                entry.path
+            )));
+        }
+        let mut data = vec![0u8; entry.compressed_size as usize];
+        self.file.seek(SeekFrom::Start(entry.offset as u64))?;
+        self.file.read_exact(&mut data)?;
+        let size = entry.size as usize;
+        let result = match entry.compression {
+            WadCompression::None | WadCompression::Satellite => data,
+            WadCompression::Gzip => {
+                let mut result = Vec::new();
+                GzDecoder::new(data.as_slice()).read_to_end(&mut result)?;
+                result
+            }
+            WadCompression::Zstd => zstd::stream::decode_all(data.as_slice())?,
+            WadCompression::ZstdChunked => match &self.subchunks {
+                Some(subchunks) => {
+                    let first = entry.first_subchunk as usize;
+                    let chunks = subchunks
+                        .get(first..first + entry.subchunk_count as usize)
+                        .ok_or_else(|| invalid("Subchunk out of range"))?;
+                    let mut result = Vec::new();
+                    let mut rest = data.as_slice();
+                    for &(compressed, uncompressed) in chunks {
+                        let (chunk, tail) = rest
+                            .split_at_checked(compressed as usize)
+                            .ok_or_else(|| invalid("Subchunk past end of entry"))?;
+                        if compressed == uncompressed {
+                            result.extend_from_slice(chunk);
+                        } else {
+                            result.extend_from_slice(&zstd::stream::decode_all(chunk)?);
+                        }
+                        rest = tail;
+                    }
+                    result
+                }
+                None => zstd_frames(&data)?,
+            },
+        };
+        if entry.compression != WadCompression::Satellite && result.len() != size {
+            return Err(invalid(format!(
+                "Entry {:016x} decompressed to {} bytes, expected {}",
+                entry.path,
+                result.len(),
+                size
+            )));
+        }
+        Ok(result)
+    }
+
+    /// Parses every entry that holds a `PROP` or `PTCH` bin, in TOC order.
+    /// Duplicate entries are separate paths sharing data and are read too.
+    pub fn for_each_bin(
+        &mut self,
+        mut f: impl FnMut(&WadEntry, io::Result<BinFile>),
+    ) -> io::Result<()> {
+        for entry in self.entries.clone() {
+            if entry.compression == WadCompression::Satellite {
+                continue;
+            }
+            let data = match self.read(&entry) {
+                Ok(data) => data,
+                Err(e) => {
+                    f(&entry, Err(e));
+                    continue;
+                }
+            };
+            if data.starts_with(b"PROP") || data.starts_with(b"PTCH") {
+                f(&entry, read_bin(&data));
+            }
+        }
+        Ok(())
+    }
+
+    /// Paths of entries recovered from strings inside the archive's bins,
+    /// matched against the XXH64 hashes of the TOC.
+    pub fn path_names(&mut self) -> io::Result<BTreeMap<u64, String>> {
+        let mut strings = BTreeSet::new();
+        self.for_each_bin(|_, bin| {
+            if let Ok(bin) = bin {
+                bin_strings(&bin, &mut strings);
+            }
+        })?;
+        let mut results = BTreeMap::new();
+        for text in strings {
+            let hash = xxh64(&text);
+            if self.entry(hash).is_some() {
+                results.insert(hash, text.to_ascii_lowercase());
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::bin::write_bin;
+
+    /// Uncompressed entries as `(path, offset of the data, size, duplicate)`,
+    /// written after the TOC.
+    fn write_wad(name: &str, entries: &[(u64, usize, u32, bool)], data: &[u8]) -> PathBuf {
+        let mut out = vec![0u8; 272];
+        out[..4].copy_from_slice(&[b'R', b'W', 3, 1]);
+        out[268..272].copy_from_slice(&(entries.len() as u32).to_le_bytes());
+        let start = 272 + entries.len() * 32;
+        for &(path, offset, size, duplicate) in entries {
+            let mut toc = [0u8; 32];
+            toc[0..8].copy_from_slice(&path.to_le_bytes());
+            toc[8..12].copy_from_slice(&((start + offset) as u32).to_le_bytes());
+            toc[12..16].copy_from_slice(&size.to_le_bytes());
+            toc[16..20].copy_from_slice(&size.to_le_bytes());
+            toc[21] = duplicate as u8;
+            out.extend_from_slice(&toc);
+        }
+        out.extend_from_slice(data);
+        let path = std::env::temp_dir().join(format!("{}-{}.wad", name, std::process::id()));
+        std::fs::write(&path, out).unwrap();
+        path
+    }
+
+    #[test]
+    fn corrupt_entry_count_is_an_error() {
+        let path = write_wad("corrupt-count", &[], &[]);
+        let mut data = std::fs::read(&path).unwrap();
+        data[268..272].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, data).unwrap();
+        assert!(WadFile::open(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn entry_past_end_is_an_error() {
+        let path = write_wad("past-end", &[(1, 0, u32::MAX, false)], b"PROP");
+        let mut wad = WadFile::open(&path).unwrap();
+        let entry = wad.entries[0].clone();
+        assert!(wad.read(&entry).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn duplicate_entries_are_read() {
+        let bin = BinFile {
+            version: 3,
+            patch_header: None,
+            linked: Vec::new(),
+            entries: Vec::new(),
+            patches: Vec::new(),
+        };
+        let data = write_bin(&bin).unwrap();
+        let size = data.len() as u32;
+        let entries = [
+            (xxh64("a.bin"), 0, size, false),
+            (xxh64("b.bin"), 0, size, true),
+        ];
+        let path = write_wad("duplicates", &entries, &data);
+        let mut wad = WadFile::open(&path).unwrap();
+        let mut paths = Vec::new();
+        wad.for_each_bin(|entry, bin| {
+            assert!(bin.is_ok());
+            paths.push(entry.path);
+        })
+        .unwrap();
+        assert_eq!(paths, vec![xxh64("a.bin"), xxh64("b.bin")]);
+        std::fs::remove_file(path).unwrap();
+    }
+}